@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+
+use crate::settings::config::parse_env;
+
+const ADMIN_CHAT_IDS_ENV: &str = "ADMIN_CHAT_IDS";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdminConfig {
+    /// Чаты администраторов; их нельзя снять командой из бота
+    pub chat_ids: Vec<i64>,
+}
+
+impl AdminConfig {
+    /// ADMIN_CHAT_IDS через запятую заменяет список из файла настроек
+    pub(crate) fn apply_env(&mut self) -> anyhow::Result<()> {
+        if let Ok(value) = env::var(ADMIN_CHAT_IDS_ENV) {
+            self.chat_ids = value
+                .split(',')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(|id| parse_env(ADMIN_CHAT_IDS_ENV, id))
+                .collect::<Result<_, _>>()?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn validate(&self, errors: &mut Vec<String>) {
+        if self.chat_ids.contains(&0) {
+            errors.push("admin.chat_ids must not contain 0".to_string());
+        }
+    }
+}
@@ -0,0 +1,174 @@
+pub mod config;
+
+use chrono::Utc;
+use std::error::Error;
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup};
+use uuid::Uuid;
+
+use crate::bot_state::{BotState, BotStateError};
+use crate::handlers::payments::refund_booking;
+use crate::handlers::sessions::finalize_session_in_background;
+use crate::i18n;
+use crate::models::{AIAssistant, Booking};
+use crate::tr;
+pub use config::AdminConfig;
+
+
+/// Настройки администраторов (секция `[admin]` основных настроек)
+pub fn config() -> &'static AdminConfig {
+    &crate::settings::config().admin
+}
+
+/// Администратор из настроек (не может быть снят из бота)
+pub fn is_configured_admin(chat_id: ChatId) -> bool {
+    config().chat_ids.contains(&chat_id.0)
+}
+
+/// Является ли чат администратором: по настройкам или по таблице admins
+pub async fn is_admin(state: &BotState, chat_id: ChatId) -> bool {
+    if is_configured_admin(chat_id) {
+        return true;
+    }
+
+    sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM admins WHERE chat_id = $1)")
+        .bind(chat_id.0)
+        .fetch_one(&state.db.pool)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!(chat_id = %chat_id, error = %e, "could not check admin role");
+            false
+        })
+}
+
+/// Назначает администратора; false, если он уже был в таблице
+pub async fn add_admin(state: &BotState, chat_id: ChatId, added_by: ChatId) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "INSERT INTO admins (chat_id, added_by) VALUES ($1, $2) ON CONFLICT (chat_id) DO NOTHING"
+    )
+    .bind(chat_id.0)
+    .bind(added_by.0)
+    .execute(&state.db.pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Снимает администратора, назначенного через бота
+pub async fn remove_admin(state: &BotState, chat_id: ChatId) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM admins WHERE chat_id = $1")
+        .bind(chat_id.0)
+        .execute(&state.db.pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Все администраторы: из настроек и из таблицы
+pub async fn list_admins(state: &BotState) -> Result<Vec<i64>, sqlx::Error> {
+    let mut admins = config().chat_ids.clone();
+    let added: Vec<i64> = sqlx::query_scalar("SELECT chat_id FROM admins ORDER BY created_at")
+        .fetch_all(&state.db.pool)
+        .await?;
+
+    for chat_id in added {
+        if !admins.contains(&chat_id) {
+            admins.push(chat_id);
+        }
+    }
+    Ok(admins)
+}
+
+/// Дарит пользователю бесплатную сессию по времени и сообщает ему об этом
+pub async fn grant_minutes(
+    bot: &Bot,
+    state: &BotState,
+    user_id: ChatId,
+    assistant: &AIAssistant,
+    minutes: u32,
+) -> Result<Booking, BotStateError> {
+    // Бесплатная бронь: оплачена, без счета и без списания с баланса
+    let booking = Booking {
+        id: Uuid::new_v4().to_string(),
+        user_id,
+        assistant_id: assistant.id,
+        duration_minutes: minutes,
+        total_price: 0.0,
+        invoice_payload: Uuid::new_v4().to_string(),
+        is_paid: true,
+        is_completed: false,
+        created_at: Utc::now(),
+        payment_invoice_message_id: None,
+        expires_at: None,
+        message_quota: None,
+        credit_applied: 0.0,
+        telegram_payment_charge_id: None,
+        is_refunded: false,
+        credit_reserved: false,
+        started_at: None,
+    };
+
+    state.save_booking(&booking).await?;
+
+    // Уведомляем пользователя на его языке
+    let lang = i18n::user_locale(state, user_id).await;
+    let name = AIAssistant::find_localized(state, assistant.id, lang).await
+        .map(|a| a.name)
+        .unwrap_or_else(|| assistant.name.clone());
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![InlineKeyboardButton::callback(
+        tr!(lang, "sessions.start_button"),
+        format!("start_booking_{}", booking.id),
+    )]]);
+
+    if let Err(e) = bot
+        .send_message(user_id, tr!(lang, "admin.granted_user", minutes = minutes, name = name))
+        .reply_markup(keyboard)
+        .await
+    {
+        tracing::warn!(chat_id = %user_id, error = %e, "could not notify user about granted minutes");
+    }
+
+    Ok(booking)
+}
+
+/// Возврат по оплаченной брони; если по ней идет сессия, она завершается без зачисления остатка
+pub async fn refund(
+    bot: &Bot,
+    state: &BotState,
+    booking: &mut Booking,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if !booking.is_paid || booking.is_refunded {
+        return Err("Booking is not paid or already refunded".into());
+    }
+
+    refund_booking(bot, state, booking).await?;
+
+    let user_id = booking.user_id;
+    let mut user_state = state.get_user_state(user_id).await?;
+    let lang = user_state.locale();
+
+    // Возвращена вся стоимость, поэтому сессию закрываем без пересчета остатка
+    let active_session = user_state
+        .current_session
+        .as_mut()
+        .filter(|s| s.is_active && s.booking_id.as_deref() == Some(booking.id.as_str()));
+
+    if let Some(session) = active_session {
+        session.is_active = false;
+        let finished_session = session.clone();
+
+        if let Err(e) = state.save_user_state(user_id, user_state).await {
+            tracing::error!(chat_id = %user_id, error = %e, "could not save user state");
+        }
+        finalize_session_in_background(state, &finished_session);
+    }
+
+    if let Err(e) = bot
+        .send_message(user_id, tr!(lang, "booking.refunded", stars = (booking.amount_due() * 100.0) as i32))
+        .await
+    {
+        tracing::warn!(chat_id = %user_id, error = %e, "could not notify user about refund");
+    }
+
+    Ok(())
+}
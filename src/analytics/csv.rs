@@ -0,0 +1,136 @@
+use std::fmt::Write as _;
+use std::io::{Cursor, Write};
+
+use zip::write::SimpleFileOptions;
+
+use super::{percent, Report};
+
+/// Экранирует значение по RFC 4180. Текст, который табличный редактор принял бы за формулу
+/// (имена консультантов задают администраторы), начинается с апострофа; числа не меняются
+fn field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) && value.parse::<f64>().is_err() {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn row(out: &mut String, values: &[String]) {
+    let line: Vec<String> = values.iter().map(|v| field(v)).collect();
+    let _ = writeln!(out, "{}", line.join(","));
+}
+
+pub fn daily(report: &Report) -> String {
+    let mut out = String::new();
+    row(&mut out, &[
+        "day", "revenue_stars", "refunded_stars", "bookings_created", "bookings_paid",
+        "bookings_abandoned", "conversion_percent", "sessions",
+    ].map(String::from));
+
+    for d in &report.daily {
+        row(&mut out, &[
+            d.day.to_string(),
+            d.revenue.to_string(),
+            d.refunded.to_string(),
+            d.bookings_created.to_string(),
+            d.bookings_paid.to_string(),
+            d.bookings_abandoned.to_string(),
+            format!("{:.1}", percent(d.bookings_paid, d.bookings_created)),
+            d.sessions.to_string(),
+        ]);
+    }
+    out
+}
+
+pub fn consultants(report: &Report) -> String {
+    let mut out = String::new();
+    row(&mut out, &[
+        "consultant_id", "name", "paid_bookings", "revenue_stars", "sessions",
+        "avg_session_minutes", "avg_messages_per_session", "avg_rating",
+    ].map(String::from));
+
+    for c in &report.consultants {
+        row(&mut out, &[
+            c.assistant_id.to_string(),
+            c.name.clone(),
+            c.paid_bookings.to_string(),
+            c.revenue.to_string(),
+            c.sessions.to_string(),
+            format!("{:.1}", c.avg_minutes),
+            format!("{:.1}", c.avg_messages),
+            c.avg_rating.map(|r| format!("{:.2}", r)).unwrap_or_default(),
+        ]);
+    }
+    out
+}
+
+/// Когорты: число пользователей и доля вернувшихся на каждой следующей неделе
+pub fn cohorts(report: &Report) -> String {
+    let weeks = report.cohorts.iter().map(|c| c.retained.len()).max().unwrap_or(0);
+
+    let mut out = String::new();
+    let mut header = vec!["cohort_week".to_string(), "users".to_string()];
+    header.extend((1..=weeks).map(|week| format!("week_{}_percent", week)));
+    row(&mut out, &header);
+
+    for cohort in &report.cohorts {
+        let mut values = vec![cohort.week.to_string(), cohort.users.to_string()];
+        values.extend((0..weeks).map(|week| {
+            cohort.retention_percent(week).map(|p| format!("{:.1}", p)).unwrap_or_default()
+        }));
+        row(&mut out, &values);
+    }
+    out
+}
+
+/// Все таблицы отчета одним ZIP-архивом
+pub fn archive(report: &Report) -> zip::result::ZipResult<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    for (name, content) in [
+        ("daily.csv", daily(report)),
+        ("consultants.csv", consultants(report)),
+        ("cohorts.csv", cohorts(report)),
+    ] {
+        zip.start_file(name, options)?;
+        zip.write_all(content.as_bytes())?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn special_characters_are_quoted() {
+        assert_eq!(field("plain"), "plain");
+        assert_eq!(field("a,b"), "\"a,b\"");
+        assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(field("line\nbreak"), "\"line\nbreak\"");
+        assert_eq!(field(""), "");
+    }
+
+    #[test]
+    fn formula_cells_are_neutralized() {
+        assert_eq!(field("=HYPERLINK(\"http://x\")"), "\"'=HYPERLINK(\"\"http://x\"\")\"");
+        assert_eq!(field("+1+2"), "'+1+2");
+        assert_eq!(field("-cmd"), "'-cmd");
+        assert_eq!(field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(field("\t=1"), "'\t=1");
+    }
+
+    #[test]
+    fn numbers_keep_their_sign() {
+        assert_eq!(field("-12.5"), "-12.5");
+        assert_eq!(field("42"), "42");
+    }
+}
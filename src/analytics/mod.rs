@@ -0,0 +1,289 @@
+pub mod csv;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+use crate::bot_state::BotState;
+use crate::models::UserSession;
+
+/// Период отчета по умолчанию и максимальный, в днях
+pub const DEFAULT_DAYS: i32 = 30;
+pub const MAX_DAYS: i32 = 365;
+
+/// Сколько недель показывать в таблице когорт
+const COHORT_WEEKS: i32 = 8;
+
+/// Показатели за один день. Суммы — в Stars, бесплатные (подаренные) брони не учитываются
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct DailyMetrics {
+    pub day: NaiveDate,
+    /// Оплачено счетами Telegram за вычетом возвратов
+    pub revenue: i64,
+    pub refunded: i64,
+    pub bookings_created: i64,
+    pub bookings_paid: i64,
+    /// Брони, удаленные с неоплаченным счетом по истечении срока
+    pub bookings_abandoned: i64,
+    pub sessions: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, FromRow)]
+pub struct SessionSummary {
+    pub sessions: i64,
+    pub avg_minutes: f64,
+    pub avg_messages: f64,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ConsultantMetrics {
+    pub assistant_id: i32,
+    pub name: String,
+    pub paid_bookings: i64,
+    pub revenue: i64,
+    pub sessions: i64,
+    pub avg_minutes: f64,
+    pub avg_messages: f64,
+    pub avg_rating: Option<f64>,
+}
+
+/// Пользователи, впервые начавшие сессию на одной неделе, и сколько из них возвращались потом
+#[derive(Debug, Clone, Serialize)]
+pub struct Cohort {
+    /// Понедельник недели первой сессии
+    pub week: NaiveDate,
+    pub users: i64,
+    /// `retained[k]` — сколько пользователей занимались на (k + 1)-й неделе после первой
+    pub retained: Vec<i64>,
+}
+
+impl Cohort {
+    pub fn retention_percent(&self, week: usize) -> Option<f64> {
+        self.retained.get(week).map(|count| percent(*count, self.users))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub days: i32,
+    pub generated_at: DateTime<Utc>,
+    pub daily: Vec<DailyMetrics>,
+    pub sessions: SessionSummary,
+    pub consultants: Vec<ConsultantMetrics>,
+    pub cohorts: Vec<Cohort>,
+}
+
+impl Report {
+    pub fn revenue(&self) -> i64 {
+        self.daily.iter().map(|d| d.revenue).sum()
+    }
+
+    pub fn refunded(&self) -> i64 {
+        self.daily.iter().map(|d| d.refunded).sum()
+    }
+
+    pub fn bookings_created(&self) -> i64 {
+        self.daily.iter().map(|d| d.bookings_created).sum()
+    }
+
+    pub fn bookings_paid(&self) -> i64 {
+        self.daily.iter().map(|d| d.bookings_paid).sum()
+    }
+
+    pub fn bookings_abandoned(&self) -> i64 {
+        self.daily.iter().map(|d| d.bookings_abandoned).sum()
+    }
+
+    /// Доля оплаченных броней среди созданных, в процентах
+    pub fn conversion_percent(&self) -> f64 {
+        percent(self.bookings_paid(), self.bookings_created())
+    }
+}
+
+pub fn percent(part: i64, total: i64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+/// Запоминает длительность и число сообщений завершенной сессии (без переписки).
+/// Повторный вызов для той же сессии ничего не меняет.
+#[tracing::instrument(name = "db.record_session_stats", skip_all, fields(chat_id = session.chat_id.0))]
+pub async fn record_session(state: &BotState, session: &UserSession) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO session_stats (chat_id, assistant_id, booking_id, started_at, ended_at, messages)
+        VALUES ($1, $2, $3, $4, GREATEST($4, LEAST(NOW(), $5)), $6)
+        ON CONFLICT (chat_id, started_at) DO NOTHING
+        "#
+    )
+    .bind(session.chat_id.0)
+    .bind(session.assistant_id)
+    .bind(&session.booking_id)
+    .bind(session.session_start)
+    .bind(session.paid_until)
+    .bind(session.messages_exchanged as i32)
+    .execute(&state.db.pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Собирает отчет за последние `days` дней (включая сегодняшний)
+#[tracing::instrument(name = "db.analytics_report", skip_all, fields(days))]
+pub async fn build_report(state: &BotState, days: i32) -> Result<Report, sqlx::Error> {
+    let pool = &state.db.pool;
+
+    let daily = sqlx::query_as::<_, DailyMetrics>(
+        r#"
+        WITH days AS (
+            SELECT generate_series((NOW() - make_interval(days => $1 - 1))::date, NOW()::date, INTERVAL '1 day')::date AS day
+        )
+        SELECT d.day,
+               COALESCE(b.revenue, 0) AS revenue,
+               COALESCE(b.refunded, 0) AS refunded,
+               COALESCE(b.created, 0) + COALESCE(a.bookings, 0) AS bookings_created,
+               COALESCE(b.paid, 0) AS bookings_paid,
+               COALESCE(a.bookings, 0)::BIGINT AS bookings_abandoned,
+               COALESCE(s.sessions, 0) AS sessions
+        FROM days d
+        LEFT JOIN (
+            SELECT created_at::date AS day,
+                   COUNT(*) AS created,
+                   COUNT(*) FILTER (WHERE is_paid) AS paid,
+                   ROUND(SUM(total_price - credit_applied) FILTER (WHERE is_paid AND NOT is_refunded) * 100)::BIGINT AS revenue,
+                   ROUND(SUM(total_price - credit_applied) FILTER (WHERE is_refunded) * 100)::BIGINT AS refunded
+            FROM bookings
+            WHERE total_price > 0 AND created_at >= (NOW() - make_interval(days => $1 - 1))::date
+            GROUP BY 1
+        ) b ON b.day = d.day
+        LEFT JOIN booking_abandonment a ON a.day = d.day
+        LEFT JOIN (
+            SELECT started_at::date AS day, COUNT(*) AS sessions
+            FROM session_stats
+            WHERE started_at >= (NOW() - make_interval(days => $1 - 1))::date
+            GROUP BY 1
+        ) s ON s.day = d.day
+        ORDER BY d.day
+        "#
+    )
+    .bind(days)
+    .fetch_all(pool)
+    .await?;
+
+    let sessions = sqlx::query_as::<_, SessionSummary>(
+        r#"
+        SELECT COUNT(*) AS sessions,
+               COALESCE(AVG(EXTRACT(EPOCH FROM ended_at - started_at) / 60), 0)::FLOAT8 AS avg_minutes,
+               COALESCE(AVG(messages), 0)::FLOAT8 AS avg_messages
+        FROM session_stats
+        WHERE started_at >= (NOW() - make_interval(days => $1 - 1))::date
+        "#
+    )
+    .bind(days)
+    .fetch_one(pool)
+    .await?;
+
+    let consultants = sqlx::query_as::<_, ConsultantMetrics>(
+        r#"
+        SELECT c.id AS assistant_id,
+               c.name,
+               COALESCE(b.paid, 0) AS paid_bookings,
+               COALESCE(b.revenue, 0) AS revenue,
+               COALESCE(s.sessions, 0) AS sessions,
+               COALESCE(s.avg_minutes, 0) AS avg_minutes,
+               COALESCE(s.avg_messages, 0) AS avg_messages,
+               r.avg_rating
+        FROM consultants c
+        LEFT JOIN (
+            SELECT assistant_id,
+                   COUNT(*) AS paid,
+                   ROUND(SUM(total_price - credit_applied) FILTER (WHERE NOT is_refunded) * 100)::BIGINT AS revenue
+            FROM bookings
+            WHERE is_paid AND total_price > 0 AND created_at >= (NOW() - make_interval(days => $1 - 1))::date
+            GROUP BY 1
+        ) b ON b.assistant_id = c.id
+        LEFT JOIN (
+            SELECT assistant_id,
+                   COUNT(*) AS sessions,
+                   AVG(EXTRACT(EPOCH FROM ended_at - started_at) / 60)::FLOAT8 AS avg_minutes,
+                   AVG(messages)::FLOAT8 AS avg_messages
+            FROM session_stats
+            WHERE started_at >= (NOW() - make_interval(days => $1 - 1))::date
+            GROUP BY 1
+        ) s ON s.assistant_id = c.id
+        LEFT JOIN (
+            SELECT assistant_id, AVG(rating)::FLOAT8 AS avg_rating
+            FROM session_ratings
+            WHERE created_at >= (NOW() - make_interval(days => $1 - 1))::date
+            GROUP BY 1
+        ) r ON r.assistant_id = c.id
+        ORDER BY revenue DESC, sessions DESC, c.id
+        "#
+    )
+    .bind(days)
+    .fetch_all(pool)
+    .await?;
+
+    let cohorts = build_cohorts(state).await?;
+
+    Ok(Report {
+        days,
+        generated_at: Utc::now(),
+        daily,
+        sessions,
+        consultants,
+        cohorts,
+    })
+}
+
+/// Недельные когорты по первой сессии пользователя
+async fn build_cohorts(state: &BotState) -> Result<Vec<Cohort>, sqlx::Error> {
+    let rows: Vec<(NaiveDate, i32, i64)> = sqlx::query_as(
+        r#"
+        WITH firsts AS (
+            SELECT chat_id, date_trunc('week', MIN(started_at))::date AS cohort
+            FROM session_stats
+            GROUP BY chat_id
+        ),
+        activity AS (
+            SELECT DISTINCT chat_id, date_trunc('week', started_at)::date AS week
+            FROM session_stats
+        )
+        SELECT f.cohort, ((a.week - f.cohort) / 7)::INT AS week_offset, COUNT(*) AS users
+        FROM firsts f
+        JOIN activity a ON a.chat_id = f.chat_id
+        WHERE f.cohort >= date_trunc('week', NOW() - make_interval(weeks => $1 - 1))::date
+        GROUP BY 1, 2
+        ORDER BY 1, 2
+        "#
+    )
+    .bind(COHORT_WEEKS)
+    .fetch_all(&state.db.pool)
+    .await?;
+
+    let current_week = Utc::now().date_naive().week(chrono::Weekday::Mon).first_day();
+    let mut cohorts: Vec<Cohort> = Vec::new();
+
+    for (week, offset, users) in rows {
+        if cohorts.last().is_none_or(|c| c.week != week) {
+            // Показываем только недели, которые уже наступили
+            let elapsed = ((current_week - week).num_days() / 7).max(0) as usize;
+            cohorts.push(Cohort { week, users: 0, retained: vec![0; elapsed] });
+        }
+
+        let cohort = cohorts.last_mut().expect("cohort was just pushed");
+        match offset {
+            0 => cohort.users = users,
+            n => {
+                if let Some(slot) = cohort.retained.get_mut(n as usize - 1) {
+                    *slot = users;
+                }
+            }
+        }
+    }
+
+    Ok(cohorts)
+}
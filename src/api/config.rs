@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::net::SocketAddr;
+
+const API_ENABLED_ENV: &str = "API_ENABLED";
+const API_BIND_ENV: &str = "API_BIND";
+const API_TOKEN_ENV: &str = "API_TOKEN";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Запускать ли HTTP API для бэк-офиса
+    pub enabled: bool,
+    /// Адрес, на котором слушает сервер
+    pub bind: String,
+    /// Токен доступа (заголовок `Authorization: Bearer <token>`); без него сервер не запускается
+    pub token: Option<String>,
+    /// Сколько записей максимум отдается в одном списке
+    pub max_page_size: i64,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "127.0.0.1:8080".to_string(),
+            token: None,
+            max_page_size: 200,
+        }
+    }
+}
+
+impl ApiConfig {
+    /// Переопределения из окружения (API_ENABLED, API_BIND, API_TOKEN)
+    pub(crate) fn apply_env(&mut self) {
+        if let Ok(value) = env::var(API_ENABLED_ENV) {
+            self.enabled = value == "true" || value == "1";
+        }
+        if let Ok(value) = env::var(API_BIND_ENV) {
+            self.bind = value;
+        }
+        if let Ok(value) = env::var(API_TOKEN_ENV) {
+            self.token = Some(value);
+        }
+    }
+
+    pub(crate) fn validate(&self, errors: &mut Vec<String>) {
+        if self.enabled && self.bind.parse::<SocketAddr>().is_err() {
+            errors.push(format!("api.bind {:?} is not a valid socket address", self.bind));
+        }
+        if self.max_page_size < 1 {
+            errors.push("api.max_page_size must be positive".to_string());
+        }
+    }
+}
@@ -0,0 +1,121 @@
+pub mod config;
+mod routes;
+
+use axum::extract::Request;
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
+use teloxide::Bot;
+
+use crate::bot_state::{BotState, BotStateError};
+pub use config::ApiConfig;
+
+
+/// Настройки HTTP API (секция `[api]` основных настроек)
+pub fn config() -> &'static ApiConfig {
+    &crate::settings::config().api
+}
+
+/// Общие зависимости обработчиков API
+#[derive(Clone)]
+pub struct ApiState {
+    pub bot: Bot,
+    pub state: BotState,
+}
+
+/// Ошибка запроса к API; тело ответа — `{"error": "..."}`
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    Unauthorized,
+    NotFound(String),
+    Conflict(String),
+    Internal(String),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, "Missing or invalid token".to_string()),
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ApiError::Conflict(message) => (StatusCode::CONFLICT, message),
+            ApiError::Internal(message) => {
+                // Подробности только в логах
+                tracing::error!(error = %message, "API request failed");
+                (StatusCode::INTERNAL_SERVER_ERROR, "Internal error".to_string())
+            }
+        };
+
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        ApiError::Internal(err.to_string())
+    }
+}
+
+impl From<BotStateError> for ApiError {
+    fn from(err: BotStateError) -> Self {
+        ApiError::Internal(err.to_string())
+    }
+}
+
+/// Сравнение токенов за время, не зависящее от места первого несовпадения
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+async fn require_token(request: Request, next: Next) -> Result<Response, ApiError> {
+    let expected = config().token.as_deref().ok_or(ApiError::Unauthorized)?;
+
+    let given = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or(ApiError::Unauthorized)?;
+
+    if !tokens_match(given, expected) {
+        tracing::warn!(path = request.uri().path(), "rejected API request with invalid token");
+        return Err(ApiError::Unauthorized);
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Запускает HTTP API бэк-офиса, если оно включено в настройках
+pub async fn serve(bot: Bot, state: BotState) {
+    let config = config();
+    if !config.enabled {
+        return;
+    }
+
+    if config.token.as_deref().is_none_or(|token| token.trim().is_empty()) {
+        tracing::error!("API is enabled but API_TOKEN is not set, not starting the API server");
+        return;
+    }
+
+    let app = Router::new()
+        .nest("/api", routes::router())
+        .layer(middleware::from_fn(require_token))
+        .with_state(ApiState { bot, state });
+
+    let listener = match tokio::net::TcpListener::bind(&config.bind).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!(bind = %config.bind, error = %e, "could not bind API server");
+            return;
+        }
+    };
+
+    tracing::info!(bind = %config.bind, "admin API listening");
+
+    if let Err(e) = axum::serve(listener, app).await {
+        tracing::error!(error = %e, "API server stopped");
+    }
+}
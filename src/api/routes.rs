@@ -0,0 +1,465 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::routing::{get, patch, post, put};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use teloxide::types::ChatId;
+
+use super::{ApiError, ApiState, config};
+use crate::admin;
+use crate::analytics::{self, Report, DEFAULT_DAYS, MAX_DAYS};
+use crate::i18n::Locale;
+use crate::models::{
+    AIAssistant, BillingMode, Booking, ConsultantTranslation, SessionArchive, SessionArchiveInfo, TimeSlot,
+};
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+/// Маршруты API; цены — в тех же единицах, что и в базе (1 Star = 0.01)
+pub fn router() -> Router<ApiState> {
+    Router::new()
+        .route("/consultants", get(list_consultants).post(create_consultant))
+        .route("/consultants/{id}", get(get_consultant).patch(update_consultant))
+        .route(
+            "/consultants/{id}/translations/{locale}",
+            put(save_translation).delete(delete_translation),
+        )
+        .route("/time-slots", get(list_time_slots).post(create_time_slot))
+        .route("/time-slots/{id}", patch(update_time_slot))
+        .route("/users", get(list_users))
+        .route("/users/{chat_id}", get(get_user))
+        .route("/users/{chat_id}/bookings", get(user_bookings))
+        .route("/users/{chat_id}/sessions", get(user_sessions))
+        .route("/users/{chat_id}/grants", post(grant_minutes))
+        .route("/bookings/{id}", get(get_booking))
+        .route("/bookings/{id}/refund", post(refund_booking))
+        .route("/payments", get(list_payments))
+        .route("/analytics", get(get_analytics))
+}
+
+#[derive(Debug, Deserialize)]
+struct Page {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+impl Page {
+    fn limit(&self) -> i64 {
+        let max = config().max_page_size;
+        self.limit.unwrap_or(max).clamp(1, max)
+    }
+
+    fn offset(&self) -> i64 {
+        self.offset.unwrap_or(0).max(0)
+    }
+}
+
+// ---------- Консультанты ----------
+
+#[derive(Debug, Serialize)]
+struct ConsultantView {
+    #[serde(flatten)]
+    consultant: AIAssistant,
+    is_active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    translations: Option<Vec<ConsultantTranslation>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NewConsultant {
+    model: String,
+    name: String,
+}
+
+/// Частичное обновление профиля: меняются только переданные поля
+#[derive(Debug, Deserialize)]
+struct ConsultantPatch {
+    model: Option<String>,
+    name: Option<String>,
+    description: Option<String>,
+    specialty: Option<String>,
+    greeting: Option<String>,
+    prompt: Option<String>,
+    billing_mode: Option<BillingMode>,
+    price_per_minute: Option<f64>,
+    price_per_message: Option<f64>,
+    is_active: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranslationBody {
+    name: Option<String>,
+    description: Option<String>,
+    specialty: Option<String>,
+    greeting: Option<String>,
+    prompt: Option<String>,
+}
+
+async fn find_consultant(api: &ApiState, id: i32) -> Result<(AIAssistant, bool), ApiError> {
+    AIAssistant::find_for_admin(&api.state, id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Consultant {} not found", id)))
+}
+
+async fn consultant_view(api: &ApiState, id: i32) -> Result<ConsultantView, ApiError> {
+    let (consultant, is_active) = find_consultant(api, id).await?;
+    let translations = ConsultantTranslation::list_for_consultant(&api.state, id).await?;
+
+    Ok(ConsultantView { consultant, is_active, translations: Some(translations) })
+}
+
+async fn list_consultants(State(api): State<ApiState>) -> ApiResult<Vec<ConsultantView>> {
+    let consultants = AIAssistant::list_for_admin(&api.state).await?;
+
+    Ok(Json(
+        consultants
+            .into_iter()
+            .map(|(consultant, is_active)| ConsultantView { consultant, is_active, translations: None })
+            .collect(),
+    ))
+}
+
+async fn get_consultant(State(api): State<ApiState>, Path(id): Path<i32>) -> ApiResult<ConsultantView> {
+    Ok(Json(consultant_view(&api, id).await?))
+}
+
+async fn create_consultant(
+    State(api): State<ApiState>,
+    Json(body): Json<NewConsultant>,
+) -> Result<(StatusCode, Json<ConsultantView>), ApiError> {
+    if body.model.trim().is_empty() || body.name.trim().is_empty() {
+        return Err(ApiError::BadRequest("model and name are required".to_string()));
+    }
+
+    let id = AIAssistant::create_assistant(&api.state, body.model.trim(), body.name.trim()).await?;
+    tracing::info!(consultant_id = id, "API created consultant");
+
+    Ok((StatusCode::CREATED, Json(consultant_view(&api, id).await?)))
+}
+
+async fn update_consultant(
+    State(api): State<ApiState>,
+    Path(id): Path<i32>,
+    Json(patch): Json<ConsultantPatch>,
+) -> ApiResult<ConsultantView> {
+    let (mut consultant, is_active) = find_consultant(&api, id).await?;
+
+    let prices = [patch.price_per_minute, patch.price_per_message];
+    if prices.iter().flatten().any(|price| !price.is_finite() || *price <= 0.0) {
+        return Err(ApiError::BadRequest("prices must be positive".to_string()));
+    }
+
+    let text_fields = [
+        (&mut consultant.model, patch.model),
+        (&mut consultant.name, patch.name),
+        (&mut consultant.description, patch.description),
+        (&mut consultant.specialty, patch.specialty),
+        (&mut consultant.greeting, patch.greeting),
+        (&mut consultant.prompt, patch.prompt),
+    ];
+    for (field, value) in text_fields {
+        if let Some(value) = value {
+            *field = value;
+        }
+    }
+    if let Some(billing_mode) = patch.billing_mode {
+        consultant.billing_mode = billing_mode;
+    }
+    if let Some(price) = patch.price_per_minute {
+        consultant.price_per_minute = price;
+    }
+    if let Some(price) = patch.price_per_message {
+        consultant.price_per_message = price;
+    }
+
+    AIAssistant::update_assistant(&api.state, &consultant)
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    match patch.is_active {
+        Some(true) if !is_active => AIAssistant::activate_assistant(&api.state, id).await,
+        Some(false) if is_active => AIAssistant::deactivate_assistant(&api.state, id).await,
+        _ => Ok(()),
+    }
+    .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    tracing::info!(consultant_id = id, "API updated consultant");
+    Ok(Json(consultant_view(&api, id).await?))
+}
+
+fn parse_locale(code: &str) -> Result<Locale, ApiError> {
+    Locale::from_code(code).ok_or_else(|| ApiError::BadRequest(format!("Unknown locale {}", code)))
+}
+
+async fn save_translation(
+    State(api): State<ApiState>,
+    Path((id, locale)): Path<(i32, String)>,
+    Json(body): Json<TranslationBody>,
+) -> ApiResult<ConsultantView> {
+    let locale = parse_locale(&locale)?;
+    find_consultant(&api, id).await?;
+
+    let translation = ConsultantTranslation {
+        consultant_id: id,
+        locale: locale.code().to_string(),
+        name: body.name,
+        description: body.description,
+        specialty: body.specialty,
+        greeting: body.greeting,
+        prompt: body.prompt,
+    };
+    translation.save(&api.state).await?;
+
+    tracing::info!(consultant_id = id, locale = locale.code(), "API saved consultant translation");
+    Ok(Json(consultant_view(&api, id).await?))
+}
+
+async fn delete_translation(
+    State(api): State<ApiState>,
+    Path((id, locale)): Path<(i32, String)>,
+) -> Result<StatusCode, ApiError> {
+    let locale = parse_locale(&locale)?;
+
+    if !ConsultantTranslation::delete(&api.state, id, locale).await? {
+        return Err(ApiError::NotFound(format!("Consultant {} has no {} translation", id, locale.code())));
+    }
+
+    tracing::info!(consultant_id = id, locale = locale.code(), "API deleted consultant translation");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ---------- Слоты времени ----------
+
+#[derive(Debug, Deserialize)]
+struct NewTimeSlot {
+    duration_minutes: i32,
+    description: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TimeSlotPatch {
+    duration_minutes: Option<i32>,
+    description: Option<String>,
+    is_active: Option<bool>,
+    sort_order: Option<i32>,
+}
+
+async fn list_time_slots(State(api): State<ApiState>) -> ApiResult<Vec<TimeSlot>> {
+    Ok(Json(TimeSlot::get_all_slots(&api.state).await?))
+}
+
+async fn find_time_slot(api: &ApiState, id: i32) -> Result<TimeSlot, ApiError> {
+    TimeSlot::find_by_id(&api.state, id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Time slot {} not found", id)))
+}
+
+async fn create_time_slot(
+    State(api): State<ApiState>,
+    Json(body): Json<NewTimeSlot>,
+) -> Result<(StatusCode, Json<TimeSlot>), ApiError> {
+    if body.duration_minutes <= 0 || body.description.trim().is_empty() {
+        return Err(ApiError::BadRequest("duration_minutes must be positive and description non-empty".to_string()));
+    }
+
+    let id = TimeSlot::create(&api.state, body.duration_minutes, body.description.trim()).await?;
+    tracing::info!(slot_id = id, "API created time slot");
+
+    Ok((StatusCode::CREATED, Json(find_time_slot(&api, id).await?)))
+}
+
+async fn update_time_slot(
+    State(api): State<ApiState>,
+    Path(id): Path<i32>,
+    Json(patch): Json<TimeSlotPatch>,
+) -> ApiResult<TimeSlot> {
+    let mut slot = find_time_slot(&api, id).await?;
+
+    if let Some(minutes) = patch.duration_minutes {
+        if minutes <= 0 {
+            return Err(ApiError::BadRequest("duration_minutes must be positive".to_string()));
+        }
+        slot.duration_minutes = minutes;
+    }
+    if let Some(description) = patch.description {
+        slot.description = description;
+    }
+    if let Some(is_active) = patch.is_active {
+        slot.is_active = is_active;
+    }
+    if let Some(sort_order) = patch.sort_order {
+        slot.sort_order = sort_order;
+    }
+
+    slot.save(&api.state).await?;
+    tracing::info!(slot_id = id, "API updated time slot");
+
+    Ok(Json(slot))
+}
+
+// ---------- Пользователи ----------
+
+/// Сведения о пользователе без переписки и памяти
+#[derive(Debug, Serialize, FromRow)]
+struct UserSummary {
+    chat_id: i64,
+    current_assistant_id: i32,
+    balance: f64,
+    locale: Option<String>,
+    language_code: Option<String>,
+    keep_history: bool,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+/// Текущая сессия без истории сообщений
+#[derive(Debug, Serialize)]
+struct SessionView {
+    assistant_id: i32,
+    booking_id: Option<String>,
+    session_start: DateTime<Utc>,
+    paid_until: DateTime<Utc>,
+    total_price: f64,
+    messages_exchanged: u32,
+    message_quota: Option<u32>,
+    is_active: bool,
+    flagged_for_review: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct UserView {
+    #[serde(flatten)]
+    summary: UserSummary,
+    current_session: Option<SessionView>,
+}
+
+const USER_SUMMARY_COLUMNS: &str =
+    "chat_id, current_assistant_id, balance, locale, language_code, keep_history, created_at, updated_at";
+
+async fn list_users(State(api): State<ApiState>, Query(page): Query<Page>) -> ApiResult<Vec<UserSummary>> {
+    let users = sqlx::query_as::<_, UserSummary>(&format!(
+        "SELECT {} FROM user_states ORDER BY updated_at DESC NULLS LAST LIMIT $1 OFFSET $2",
+        USER_SUMMARY_COLUMNS
+    ))
+    .bind(page.limit())
+    .bind(page.offset())
+    .fetch_all(&api.state.db.pool)
+    .await?;
+
+    Ok(Json(users))
+}
+
+async fn get_user(State(api): State<ApiState>, Path(chat_id): Path<i64>) -> ApiResult<UserView> {
+    let summary = sqlx::query_as::<_, UserSummary>(&format!(
+        "SELECT {} FROM user_states WHERE chat_id = $1",
+        USER_SUMMARY_COLUMNS
+    ))
+    .bind(chat_id)
+    .fetch_optional(&api.state.db.pool)
+    .await?
+    .ok_or_else(|| ApiError::NotFound(format!("User {} not found", chat_id)))?;
+
+    let current_session = api.state.get_user_state(ChatId(chat_id)).await?
+        .current_session
+        .map(|s| SessionView {
+            assistant_id: s.assistant_id,
+            booking_id: s.booking_id,
+            session_start: s.session_start,
+            paid_until: s.paid_until,
+            total_price: s.total_price,
+            messages_exchanged: s.messages_exchanged,
+            message_quota: s.message_quota,
+            is_active: s.is_active,
+            flagged_for_review: s.flagged_for_review,
+        });
+
+    Ok(Json(UserView { summary, current_session }))
+}
+
+async fn user_bookings(State(api): State<ApiState>, Path(chat_id): Path<i64>) -> ApiResult<Vec<Booking>> {
+    Ok(Json(api.state.get_user_bookings(ChatId(chat_id)).await?))
+}
+
+async fn user_sessions(State(api): State<ApiState>, Path(chat_id): Path<i64>) -> ApiResult<Vec<SessionArchiveInfo>> {
+    Ok(Json(SessionArchive::list_info_for_user(&api.state, ChatId(chat_id)).await?))
+}
+
+#[derive(Debug, Deserialize)]
+struct GrantBody {
+    consultant_id: i32,
+    minutes: u32,
+}
+
+async fn grant_minutes(
+    State(api): State<ApiState>,
+    Path(chat_id): Path<i64>,
+    Json(body): Json<GrantBody>,
+) -> Result<(StatusCode, Json<Booking>), ApiError> {
+    if body.minutes == 0 || body.minutes > i32::MAX as u32 {
+        return Err(ApiError::BadRequest("minutes must be positive".to_string()));
+    }
+
+    let consultant = AIAssistant::find_by_id_with_price(&api.state, body.consultant_id)
+        .await
+        .ok_or_else(|| ApiError::NotFound(format!("Active consultant {} not found", body.consultant_id)))?;
+
+    if consultant.is_per_message() {
+        return Err(ApiError::BadRequest("minutes can only be granted for per-minute consultants".to_string()));
+    }
+
+    let booking = admin::grant_minutes(&api.bot, &api.state, ChatId(chat_id), &consultant, body.minutes).await?;
+    tracing::info!(chat_id, consultant_id = consultant.id, minutes = body.minutes, booking_id = %booking.id, "API granted minutes");
+
+    Ok((StatusCode::CREATED, Json(booking)))
+}
+
+// ---------- Брони и платежи ----------
+
+async fn find_booking(api: &ApiState, id: &str) -> Result<Booking, ApiError> {
+    api.state
+        .get_booking_by_id(id)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("Booking {} not found", id)))
+}
+
+async fn get_booking(State(api): State<ApiState>, Path(id): Path<String>) -> ApiResult<Booking> {
+    Ok(Json(find_booking(&api, &id).await?))
+}
+
+async fn refund_booking(State(api): State<ApiState>, Path(id): Path<String>) -> ApiResult<Booking> {
+    let mut booking = find_booking(&api, &id).await?;
+
+    if !booking.is_paid || booking.is_refunded {
+        return Err(ApiError::Conflict(format!("Booking {} is not paid or already refunded", id)));
+    }
+
+    admin::refund(&api.bot, &api.state, &mut booking)
+        .await
+        .map_err(|e| ApiError::Internal(format!("Refund of booking {} failed: {}", id, e)))?;
+
+    tracing::info!(chat_id = %booking.user_id, booking_id = %booking.id, "API refunded booking");
+    Ok(Json(booking))
+}
+
+async fn list_payments(State(api): State<ApiState>, Query(page): Query<Page>) -> ApiResult<Vec<Booking>> {
+    Ok(Json(api.state.get_paid_bookings(page.limit(), page.offset()).await?))
+}
+
+// ---------- Аналитика ----------
+
+#[derive(Debug, Deserialize)]
+struct Period {
+    days: Option<i32>,
+}
+
+/// Тот же отчет, что и /analytics у администраторов; суммы — в Stars
+async fn get_analytics(State(api): State<ApiState>, Query(period): Query<Period>) -> ApiResult<Report> {
+    let days = period.days.unwrap_or(DEFAULT_DAYS);
+    if !(1..=MAX_DAYS).contains(&days) {
+        return Err(ApiError::BadRequest(format!("days must be between 1 and {}", MAX_DAYS)));
+    }
+
+    Ok(Json(analytics::build_report(&api.state, days).await?))
+}
@@ -5,10 +5,40 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use std::time::{Instant, SystemTime};
 use sqlx::Row;
+use sqlx::postgres::PgRow;
 use chrono::{DateTime, Utc};
 
 use crate::models::{UserState, Booking, UserSession};
 use crate::database::Database;
+use crate::crypto::{self, CryptoError};
+use crate::i18n::Locale;
+use crate::metrics;
+use crate::settings;
+
+/// Собирает бронирование из строки таблицы bookings
+fn booking_from_row(row: &PgRow) -> Booking {
+    Booking {
+        id: row.get("id"),
+        user_id: ChatId(row.get::<i64, _>("chat_id")),
+        assistant_id: row.get("assistant_id"),
+        duration_minutes: row.get::<i32, _>("duration_minutes") as u32,
+        total_price: row.get("total_price"),
+        invoice_payload: row.get("invoice_payload"),
+        is_paid: row.get("is_paid"),
+        is_completed: row.get("is_completed"),
+        payment_invoice_message_id: row.get::<Option<i64>, _>("payment_invoice_message_id")
+            .map(|id| MessageId(id as i32)),
+        created_at: row.get("created_at"),
+        expires_at: row.get("expires_at"),
+        message_quota: row.get::<Option<i32>, _>("message_quota")
+            .map(|quota| quota as u32),
+        credit_applied: row.get("credit_applied"),
+        telegram_payment_charge_id: row.get("telegram_payment_charge_id"),
+        is_refunded: row.get("is_refunded"),
+        started_at: row.get("started_at"),
+        credit_reserved: row.get("credit_reserved"),
+    }
+}
 
 type UserCache = Arc<RwLock<HashMap<ChatId, (UserState, SystemTime)>>>;
 
@@ -23,6 +53,7 @@ pub enum BotStateError {
     DatabaseError(String),
     SerializationError(String),
     DataTooLarge(usize),
+    EncryptionError(String),
 }
 
 impl std::fmt::Display for BotStateError {
@@ -31,6 +62,7 @@ impl std::fmt::Display for BotStateError {
             BotStateError::DatabaseError(e) => write!(f, "Database error: {}", e),
             BotStateError::SerializationError(e) => write!(f, "Serialization error: {}", e),
             BotStateError::DataTooLarge(size) => write!(f, "Data too large: {} bytes", size),
+            BotStateError::EncryptionError(e) => write!(f, "Encryption error: {}", e),
         }
     }
 }
@@ -43,6 +75,12 @@ impl From<sqlx::Error> for BotStateError {
     }
 }
 
+impl From<CryptoError> for BotStateError {
+    fn from(err: CryptoError) -> Self {
+        BotStateError::EncryptionError(err.to_string())
+    }
+}
+
 impl From<serde_json::Error> for BotStateError {
     fn from(err: serde_json::Error) -> Self {
         BotStateError::SerializationError(err.to_string())
@@ -57,31 +95,47 @@ impl BotState {
         }
     }
 
+    #[tracing::instrument(name = "db.save_user_state", skip_all, fields(chat_id = chat_id.0))]
     pub async fn save_user_state(&self, chat_id: ChatId, state: UserState) -> Result<(), BotStateError> {
         let start_time = Instant::now();
 
         let conversation_history_json = serde_json::to_value(&state.conversation_history)?;
         let user_temperatures_json = serde_json::to_value(&state.user_temperatures)?;
-        let current_session_json = state.current_session
+        // В базу история попадает зашифрованной, в кэше остается открытой
+        let mut stored_session = state.current_session.clone();
+        if let Some(session) = stored_session.as_mut() {
+            crypto::encrypt_history(&self.db.pool, chat_id, &mut session.history).await?;
+        }
+        let current_session_json = stored_session
+            .as_ref()
+            .map(serde_json::to_value)
+            .transpose()?;
+        let pending_input_json = state.pending_input
             .as_ref()
             .map(serde_json::to_value)
             .transpose()?;
 
-        self.validate_data_size(&conversation_history_json, 5120)?;
-        self.validate_data_size(&user_temperatures_json, 1024)?;
+        let limits = &settings::config().limits;
+        self.validate_data_size(&conversation_history_json, limits.max_history_kb)?;
+        self.validate_data_size(&user_temperatures_json, limits.max_temperatures_kb)?;
 
-        sqlx::query(
+        let balance: f64 = sqlx::query_scalar(
             r#"
             INSERT INTO user_states 
-            (chat_id, current_assistant_id, current_session, conversation_history, user_temperatures, updated_at)
-            VALUES ($1, $2, $3, $4, $5, NOW())
+            (chat_id, current_assistant_id, current_session, conversation_history, user_temperatures, balance,
+             pending_input, locale, language_code, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())
             ON CONFLICT (chat_id) 
             DO UPDATE SET 
                 current_assistant_id = EXCLUDED.current_assistant_id,
                 current_session = EXCLUDED.current_session,
                 conversation_history = EXCLUDED.conversation_history,
                 user_temperatures = EXCLUDED.user_temperatures,
+                pending_input = EXCLUDED.pending_input,
+                locale = EXCLUDED.locale,
+                language_code = EXCLUDED.language_code,
                 updated_at = NOW()
+            RETURNING balance
             "#
         )
         .bind(chat_id.0 as i64)
@@ -89,36 +143,48 @@ impl BotState {
         .bind(current_session_json)
         .bind(conversation_history_json)
         .bind(user_temperatures_json)
-        .execute(&self.db.pool)
+        .bind(state.balance)
+        .bind(pending_input_json)
+        .bind(state.locale.map(|l| l.code()))
+        .bind(&state.language_code)
+        .fetch_one(&self.db.pool)
         .await?;
 
+        // Баланс меняется только через add_balance/reserve_credit, в кэш кладем значение из базы
+        let mut state = state;
+        state.balance = balance;
         {
             let mut cache = self.cache.write().await;
             cache.insert(chat_id, (state, SystemTime::now()));
         }
 
-        let duration = start_time.elapsed();
-        log::debug!("💾 State saved for user {} in {:?}", chat_id, duration);
+        tracing::debug!(chat_id = %chat_id, elapsed_ms = start_time.elapsed().as_millis() as u64, "user state saved");
 
         Ok(())
     }
 
+    #[tracing::instrument(name = "db.save_booking", skip_all, fields(booking_id = %booking.id))]
     pub async fn save_booking(&self, booking: &Booking) -> Result<(), BotStateError> {
         sqlx::query(
             r#"
             INSERT INTO bookings 
             (id, chat_id, assistant_id, duration_minutes, total_price, 
              invoice_payload, is_paid, is_completed, payment_invoice_message_id, 
-             expires_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW() + INTERVAL '5 minutes', NOW())
+             message_quota, credit_applied, telegram_payment_charge_id, is_refunded,
+             started_at, credit_reserved, expires_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, NOW())
             ON CONFLICT (id) 
             DO UPDATE SET 
                 is_paid = EXCLUDED.is_paid,
                 is_completed = EXCLUDED.is_completed,
                 payment_invoice_message_id = EXCLUDED.payment_invoice_message_id,
+                telegram_payment_charge_id = EXCLUDED.telegram_payment_charge_id,
+                is_refunded = EXCLUDED.is_refunded,
+                started_at = EXCLUDED.started_at,
+                credit_reserved = EXCLUDED.credit_reserved,
                 expires_at = CASE 
                     WHEN EXCLUDED.is_paid = true THEN NULL 
-                    ELSE NOW() + INTERVAL '5 minutes' 
+                    ELSE EXCLUDED.expires_at 
                 END,
                 updated_at = NOW()
             "#
@@ -132,12 +198,20 @@ impl BotState {
         .bind(booking.is_paid)
         .bind(booking.is_completed)
         .bind(booking.payment_invoice_message_id.map(|id| id.0 as i64))
+        .bind(booking.message_quota.map(|quota| quota as i32))
+        .bind(booking.credit_applied)
+        .bind(&booking.telegram_payment_charge_id)
+        .bind(booking.is_refunded)
+        .bind(booking.started_at)
+        .bind(booking.credit_reserved)
+        .bind(booking.expires_at)
         .execute(&self.db.pool)
         .await?;
     
         Ok(())
     }
 
+    #[tracing::instrument(name = "db.get_user_bookings", skip_all, fields(chat_id = chat_id.0))]
     pub async fn get_user_bookings(&self, chat_id: ChatId) -> Result<Vec<Booking>, BotStateError> {
         // Сначала удаляем просроченные неоплаченные брони
         self.cleanup_expired_bookings().await?;
@@ -145,7 +219,8 @@ impl BotState {
         let rows = sqlx::query(
             "SELECT id, chat_id, assistant_id, duration_minutes, total_price, 
                     invoice_payload, is_paid, is_completed, payment_invoice_message_id, 
-                    created_at, expires_at
+                    created_at, expires_at, message_quota,
+                    credit_applied, telegram_payment_charge_id, is_refunded, started_at, credit_reserved
              FROM bookings 
              WHERE chat_id = $1 
              AND (is_paid = true OR expires_at > NOW())
@@ -157,48 +232,86 @@ impl BotState {
 
         let mut bookings = Vec::new();
         for row in rows {
-            let booking = Booking {
-                id: row.get("id"),
-                user_id: ChatId(row.get::<i64, _>("chat_id") as i64),
-                assistant_id: row.get("assistant_id"),
-                duration_minutes: row.get::<i32, _>("duration_minutes") as u32,
-                total_price: row.get("total_price"),
-                invoice_payload: row.get("invoice_payload"),
-                is_paid: row.get("is_paid"),
-                is_completed: row.get("is_completed"),
-                payment_invoice_message_id: row.get::<Option<i64>, _>("payment_invoice_message_id")
-                    .map(|id| MessageId(id as i32)),
-                created_at: row.get("created_at"),
-                expires_at: row.get("expires_at"),
-            };
+            let booking = booking_from_row(&row);
             bookings.push(booking);
         }
 
         Ok(bookings)
     }
 
+    /// Оплаченные брони всех пользователей, новые первыми (журнал платежей)
+    pub async fn get_paid_bookings(&self, limit: i64, offset: i64) -> Result<Vec<Booking>, BotStateError> {
+        let rows = sqlx::query(
+            "SELECT id, chat_id, assistant_id, duration_minutes, total_price,
+                    invoice_payload, is_paid, is_completed, payment_invoice_message_id,
+                    created_at, expires_at, message_quota,
+                    credit_applied, telegram_payment_charge_id, is_refunded, started_at, credit_reserved
+             FROM bookings
+             WHERE is_paid = true
+             ORDER BY created_at DESC
+             LIMIT $1 OFFSET $2"
+        )
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.db.pool)
+        .await?;
+
+        Ok(rows.iter().map(booking_from_row).collect())
+    }
+
+    #[tracing::instrument(name = "db.cleanup_expired_bookings", skip_all)]
     pub async fn cleanup_expired_bookings(&self) -> Result<u64, BotStateError> {
-        let result = sqlx::query(
-            "DELETE FROM bookings 
-             WHERE is_paid = false 
-             AND expires_at <= NOW()"
+        // Брошенные счета учитываются в воронке оплат; подаренные брони бесплатны и в нее не входят
+        // Зарезервированная часть баланса возвращается владельцу брошенного счета
+        let (deleted_count, released): (i64, Vec<i64>) = sqlx::query_as(
+            "WITH deleted AS (
+                 DELETE FROM bookings 
+                 WHERE is_paid = false 
+                 AND expires_at <= NOW()
+                 RETURNING chat_id, created_at, total_price, credit_applied, credit_reserved
+             ),
+             abandoned AS (
+                 INSERT INTO booking_abandonment (day, bookings)
+                 SELECT created_at::date, COUNT(*) FROM deleted WHERE total_price > 0 GROUP BY 1
+                 ON CONFLICT (day) DO UPDATE SET bookings = booking_abandonment.bookings + EXCLUDED.bookings
+             ),
+             released AS (
+                 UPDATE user_states u
+                 SET balance = u.balance + r.credit
+                 FROM (
+                     SELECT chat_id, SUM(credit_applied) AS credit
+                     FROM deleted
+                     WHERE credit_reserved AND credit_applied > 0
+                     GROUP BY chat_id
+                 ) r
+                 WHERE u.chat_id = r.chat_id
+                 RETURNING u.chat_id
+             )
+             SELECT (SELECT COUNT(*) FROM deleted), ARRAY(SELECT chat_id FROM released)"
         )
-        .execute(&self.db.pool)
+        .fetch_one(&self.db.pool)
         .await?;
 
-        let deleted_count = result.rows_affected();
+        // Балансы изменились в обход кэша
+        for chat_id in released {
+            self.evict_user_state(ChatId(chat_id)).await;
+        }
+
+        let deleted_count = deleted_count as u64;
         if deleted_count > 0 {
-            log::info!("🧹 Cleaned up {} expired unpaid bookings", deleted_count);
+            tracing::info!(bookings = deleted_count, "expired unpaid bookings cleaned up");
         }
 
         Ok(deleted_count)
     }
 
+    #[tracing::instrument(name = "db.get_booking_by_payload", skip_all)]
     pub async fn get_booking_by_payload(&self, invoice_payload: &str) -> Result<Option<Booking>, BotStateError> {
         let row = sqlx::query(
             "SELECT id, chat_id, assistant_id, duration_minutes, total_price, 
                     invoice_payload, is_paid, is_completed, payment_invoice_message_id, 
-                    created_at, expires_at
+                    created_at, expires_at, message_quota,
+                    credit_applied, telegram_payment_charge_id, is_refunded, started_at, credit_reserved
              FROM bookings 
              WHERE invoice_payload = $1"
         )
@@ -207,31 +320,20 @@ impl BotState {
         .await?;
     
         if let Some(row) = row {
-            let booking = Booking {
-                id: row.get("id"),
-                user_id: ChatId(row.get::<i64, _>("chat_id") as i64),
-                assistant_id: row.get("assistant_id"),
-                duration_minutes: row.get::<i32, _>("duration_minutes") as u32,
-                total_price: row.get("total_price"),
-                invoice_payload: row.get("invoice_payload"),
-                is_paid: row.get("is_paid"),
-                is_completed: row.get("is_completed"),
-                payment_invoice_message_id: row.get::<Option<i64>, _>("payment_invoice_message_id")
-                    .map(|id| MessageId(id as i32)),
-                created_at: row.get("created_at"),
-                expires_at: row.get("expires_at"),
-            };
+            let booking = booking_from_row(&row);
             Ok(Some(booking))
         } else {
             Ok(None)
         }
     }
 
+    #[tracing::instrument(name = "db.get_booking_by_id", skip_all, fields(booking_id = %booking_id))]
     pub async fn get_booking_by_id(&self, booking_id: &str) -> Result<Option<Booking>, BotStateError> {
         let row = sqlx::query(
             "SELECT id, chat_id, assistant_id, duration_minutes, total_price, 
                     invoice_payload, is_paid, is_completed, payment_invoice_message_id, 
-                    created_at, expires_at
+                    created_at, expires_at, message_quota,
+                    credit_applied, telegram_payment_charge_id, is_refunded, started_at, credit_reserved
              FROM bookings WHERE id = $1"
         )
         .bind(booking_id)
@@ -239,26 +341,14 @@ impl BotState {
         .await?;
 
         if let Some(row) = row {
-            let booking = Booking {
-                id: row.get("id"),
-                user_id: ChatId(row.get::<i64, _>("chat_id") as i64),
-                assistant_id: row.get("assistant_id"),
-                duration_minutes: row.get::<i32, _>("duration_minutes") as u32,
-                total_price: row.get("total_price"),
-                invoice_payload: row.get("invoice_payload"),
-                is_paid: row.get("is_paid"),
-                is_completed: row.get("is_completed"),
-                payment_invoice_message_id: row.get::<Option<i64>, _>("payment_invoice_message_id")
-                    .map(|id| MessageId(id as i32)),
-                created_at: row.get("created_at"),
-                expires_at: row.get("expires_at"),
-            };
+            let booking = booking_from_row(&row);
             Ok(Some(booking))
         } else {
             Ok(None)
         }
     }
 
+    #[tracing::instrument(name = "db.mark_booking_completed", skip_all, fields(booking_id = %booking_id))]
     pub async fn mark_booking_completed(&self, booking_id: &str) -> Result<(), BotStateError> {
         sqlx::query(
             "UPDATE bookings SET is_completed = true, updated_at = NOW() WHERE id = $1"
@@ -267,17 +357,21 @@ impl BotState {
         .execute(&self.db.pool)
         .await?;
         
-        log::info!("✅ Booking {} marked as completed", booking_id);
+        tracing::info!(booking_id = %booking_id, "booking marked as completed");
         Ok(())
     }
 
     pub async fn find_booking_for_session(&self, session: &UserSession) -> Result<Option<Booking>, BotStateError> {
-        // Теперь session должен хранить assistant_id вместо model
-        // Пока оставляем старую логику, но нужно обновить и UserSession
+        if let Some(booking_id) = &session.booking_id {
+            return self.get_booking_by_id(booking_id).await;
+        }
+
+        // Сессии, созданные до привязки к брони, ищем по консультанту
         let row = sqlx::query(
             "SELECT id, chat_id, assistant_id, duration_minutes, total_price, 
                     invoice_payload, is_paid, is_completed, payment_invoice_message_id, 
-                    created_at, expires_at
+                    created_at, expires_at, message_quota,
+                    credit_applied, telegram_payment_charge_id, is_refunded, started_at, credit_reserved
              FROM bookings 
              WHERE chat_id = $1 
              AND assistant_id = $2 
@@ -291,20 +385,7 @@ impl BotState {
         .await?;
 
         if let Some(row) = row {
-            let booking = Booking {
-                id: row.get("id"),
-                user_id: ChatId(row.get::<i64, _>("chat_id") as i64),
-                assistant_id: row.get("assistant_id"),
-                duration_minutes: row.get::<i32, _>("duration_minutes") as u32,
-                total_price: row.get("total_price"),
-                invoice_payload: row.get("invoice_payload"),
-                is_paid: row.get("is_paid"),
-                is_completed: row.get("is_completed"),
-                payment_invoice_message_id: row.get::<Option<i64>, _>("payment_invoice_message_id")
-                    .map(|id| MessageId(id as i32)),
-                created_at: row.get("created_at"),
-                expires_at: row.get("expires_at"),
-            };
+            let booking = booking_from_row(&row);
             Ok(Some(booking))
         } else {
             Ok(None)
@@ -326,38 +407,36 @@ impl BotState {
         }
     }
 
-    pub async fn get_user_state(&self, chat_id: ChatId) -> UserState {
+    /// Состояние пользователя. Ошибку чтения возвращаем, а не пустое состояние:
+    /// сохранив его, вызывающий затер бы сессию и баланс пользователя
+    pub async fn get_user_state(&self, chat_id: ChatId) -> Result<UserState, BotStateError> {
         let start_time = Instant::now();
 
         {
             let cache = self.cache.read().await;
             if let Some((state, timestamp)) = cache.get(&chat_id) {
-                if timestamp.elapsed().unwrap_or_default().as_secs() < 300 {
-                    return state.clone();
+                if timestamp.elapsed().unwrap_or_default().as_secs() < settings::config().timeouts.user_cache_ttl_seconds {
+                    metrics::user_cache_lookup(true);
+                    return Ok(state.clone());
                 }
             }
         }
+        metrics::user_cache_lookup(false);
 
-        match self.fetch_user_state_from_db(chat_id).await {
-            Ok(state) => {
-                let mut cache = self.cache.write().await;
-                cache.insert(chat_id, (state.clone(), SystemTime::now()));
+        let state = self.fetch_user_state_from_db(chat_id).await?;
+        let mut cache = self.cache.write().await;
+        cache.insert(chat_id, (state.clone(), SystemTime::now()));
 
-                let duration = start_time.elapsed();
-                log::debug!("🎯 State loaded for user {} in {:?}", chat_id, duration);
+        tracing::debug!(chat_id = %chat_id, elapsed_ms = start_time.elapsed().as_millis() as u64, "user state loaded");
 
-                state
-            }
-            Err(e) => {
-                log::error!("Error loading state for user {}: {}", chat_id, e);
-                UserState::default()
-            }
-        }
+        Ok(state)
     }
 
+    #[tracing::instrument(name = "db.fetch_user_state", skip_all, fields(chat_id = chat_id.0))]
     async fn fetch_user_state_from_db(&self, chat_id: ChatId) -> Result<UserState, BotStateError> {
         let row = sqlx::query(
-            "SELECT current_assistant_id, current_session, conversation_history, user_temperatures 
+            "SELECT current_assistant_id, current_session, conversation_history, user_temperatures, balance,
+                    pending_input, locale, language_code
              FROM user_states WHERE chat_id = $1"
         )
         .bind(chat_id.0 as i64)
@@ -370,7 +449,13 @@ impl BotState {
             let conversation_history_json: serde_json::Value = row.get("conversation_history");
             let user_temperatures_json: serde_json::Value = row.get("user_temperatures");
 
-            let current_session = current_session
+            let mut current_session: Option<UserSession> = current_session
+                .map(serde_json::from_value)
+                .transpose()?;
+            if let Some(session) = current_session.as_mut() {
+                crypto::decrypt_history(&self.db.pool, chat_id, &mut session.history).await?;
+            }
+            let pending_input = row.get::<Option<serde_json::Value>, _>("pending_input")
                 .map(serde_json::from_value)
                 .transpose()?;
 
@@ -380,17 +465,23 @@ impl BotState {
                 conversation_history: serde_json::from_value(conversation_history_json)?,
                 user_temperatures: serde_json::from_value(user_temperatures_json)?,
                 scheduled_time: None,
+                balance: row.get("balance"),
+                pending_input,
+                locale: row.get::<Option<String>, _>("locale").as_deref().and_then(Locale::from_code),
+                language_code: row.get("language_code"),
             })
         } else {
             Ok(UserState::default())
         }
     }
 
+    #[tracing::instrument(name = "db.get_all_user_states", skip_all)]
     pub async fn get_all_user_states(&self) -> HashMap<ChatId, UserState> {
         let mut states = HashMap::new();
 
         if let Ok(rows) = sqlx::query(
-            "SELECT chat_id, current_assistant_id, current_session, conversation_history, user_temperatures 
+            "SELECT chat_id, current_assistant_id, current_session, conversation_history, user_temperatures, balance,
+                    pending_input, locale, language_code
              FROM user_states"
         )
         .fetch_all(&self.db.pool)
@@ -406,10 +497,18 @@ impl BotState {
                     serde_json::from_value(conversation_history_json),
                     serde_json::from_value(user_temperatures_json),
                 ) {
-                    let current_session = current_session
+                    let mut current_session: Option<UserSession> = current_session
                         .map(serde_json::from_value)
                         .transpose()
                         .unwrap_or(None);
+                    // Сессия с нерасшифровываемой историей все равно должна истечь; такое состояние
+                    // нельзя сохранять целиком, поэтому сессию завершает deactivate_session
+                    if let Some(session) = current_session.as_mut()
+                        && let Err(e) = crypto::decrypt_history(&self.db.pool, chat_id, &mut session.history).await
+                    {
+                        tracing::error!(chat_id = %chat_id, error = %e, "could not decrypt session history");
+                        session.history.clear();
+                    }
 
                     let user_state = UserState {
                         current_assistant_id,
@@ -417,6 +516,13 @@ impl BotState {
                         conversation_history,
                         user_temperatures,
                         scheduled_time: None,
+                        balance: row.get("balance"),
+                        pending_input: row.get::<Option<serde_json::Value>, _>("pending_input")
+                            .map(serde_json::from_value)
+                            .transpose()
+                            .unwrap_or(None),
+                        locale: row.get::<Option<String>, _>("locale").as_deref().and_then(Locale::from_code),
+                        language_code: row.get("language_code"),
                     };
 
                     states.insert(chat_id, user_state);
@@ -427,17 +533,97 @@ impl BotState {
         states
     }
 
+    /// Обновляет баланс в кэшированном состоянии после изменения в базе
+    async fn cache_balance(&self, chat_id: ChatId, balance: f64) {
+        let mut cache = self.cache.write().await;
+        if let Some((state, _)) = cache.get_mut(&chat_id) {
+            state.balance = balance;
+        }
+    }
+
+    /// Зачисляет сумму на баланс пользователя и возвращает новый баланс
+    #[tracing::instrument(name = "db.add_balance", skip_all, fields(chat_id = chat_id.0))]
+    pub async fn add_balance(&self, chat_id: ChatId, amount: f64) -> Result<f64, BotStateError> {
+        let balance: f64 = sqlx::query_scalar(
+            "INSERT INTO user_states (chat_id, balance) VALUES ($1, $2)
+             ON CONFLICT (chat_id) DO UPDATE SET balance = user_states.balance + EXCLUDED.balance, updated_at = NOW()
+             RETURNING balance"
+        )
+        .bind(chat_id.0)
+        .bind(amount)
+        .fetch_one(&self.db.pool)
+        .await?;
+
+        self.cache_balance(chat_id, balance).await;
+        Ok(balance)
+    }
+
+    /// Списывает сумму с баланса одним запросом, если ее хватает.
+    /// Возвращает false, если баланс уже не покрывает сумму
+    #[tracing::instrument(name = "db.reserve_credit", skip_all, fields(chat_id = chat_id.0))]
+    pub async fn reserve_credit(&self, chat_id: ChatId, amount: f64) -> Result<bool, BotStateError> {
+        let balance: Option<f64> = sqlx::query_scalar(
+            "UPDATE user_states SET balance = balance - $2, updated_at = NOW()
+             WHERE chat_id = $1 AND balance >= $2
+             RETURNING balance"
+        )
+        .bind(chat_id.0)
+        .bind(amount)
+        .fetch_optional(&self.db.pool)
+        .await?;
+
+        match balance {
+            Some(balance) => {
+                self.cache_balance(chat_id, balance).await;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Помечает текущую сессию завершенной, не перезаписывая остальное состояние пользователя
+    #[tracing::instrument(name = "db.deactivate_session", skip_all, fields(chat_id = chat_id.0))]
+    pub async fn deactivate_session(&self, chat_id: ChatId) -> Result<(), BotStateError> {
+        sqlx::query(
+            "UPDATE user_states SET current_session = jsonb_set(current_session, '{is_active}', 'false'), updated_at = NOW()
+             WHERE chat_id = $1 AND current_session IS NOT NULL"
+        )
+        .bind(chat_id.0)
+        .execute(&self.db.pool)
+        .await?;
+
+        let mut cache = self.cache.write().await;
+        if let Some((state, _)) = cache.get_mut(&chat_id)
+            && let Some(session) = state.current_session.as_mut()
+        {
+            session.is_active = false;
+        }
+        Ok(())
+    }
+
+    /// Убирает состояние пользователя из кэша (например, после удаления его данных)
+    pub async fn evict_user_state(&self, chat_id: ChatId) {
+        let mut cache = self.cache.write().await;
+        cache.remove(&chat_id);
+    }
+
     pub async fn cleanup_cache(&self) {
         let mut cache = self.cache.write().await;
         let now = SystemTime::now();
         let previous_count = cache.len();
+        let ttl = settings::config().timeouts.user_cache_ttl_seconds;
 
         cache.retain(|_, (_, timestamp)| {
-            now.duration_since(*timestamp).unwrap_or_default().as_secs() < 300
+            now.duration_since(*timestamp).unwrap_or_default().as_secs() < ttl
         });
 
         let current_count = cache.len();
-        log::debug!("🧹 Cache cleaned: {} -> {} entries", previous_count, current_count);
+        tracing::debug!(before = previous_count, after = current_count, "user state cache cleaned");
+    }
+
+    /// Сколько состояний пользователей сейчас в кэше
+    pub async fn cached_users(&self) -> usize {
+        self.cache.read().await.len()
     }
 
     fn validate_data_size(&self, data: &serde_json::Value, max_kb: usize) -> Result<(), BotStateError> {
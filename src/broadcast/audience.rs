@@ -0,0 +1,148 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bot_state::BotState;
+use crate::i18n::Locale;
+
+/// Кому отправляется рассылка; пользователи, заблокировавшие бота, исключаются всегда
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Audience {
+    /// Все пользователи
+    All,
+    /// Начинали сессию за последние N дней
+    RecentSessions(i32),
+    /// Хотя бы раз платили за сессию
+    Paid,
+    /// Не заходили в бот N дней
+    Inactive(i32),
+    /// Занимались с консультантом
+    Consultant(i32),
+    /// Язык интерфейса
+    Locale(Locale),
+}
+
+impl FromStr for Audience {
+    type Err = String;
+
+    /// Формат: `all`, `sessions:30`, `paid`, `inactive:60`, `consultant:3`, `locale:en`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (kind, param) = match value.split_once(':') {
+            Some((kind, param)) => (kind, Some(param)),
+            None => (value, None),
+        };
+
+        let number = || {
+            param
+                .and_then(|p| p.parse::<i32>().ok())
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("{} needs a positive number, e.g. {}:30", kind, kind))
+        };
+
+        match kind {
+            "all" => Ok(Audience::All),
+            "paid" => Ok(Audience::Paid),
+            "sessions" => Ok(Audience::RecentSessions(number()?)),
+            "inactive" => Ok(Audience::Inactive(number()?)),
+            "consultant" => Ok(Audience::Consultant(number()?)),
+            "locale" => param
+                .and_then(Locale::from_code)
+                .map(Audience::Locale)
+                .ok_or_else(|| "locale needs a language code, e.g. locale:en".to_string()),
+            _ => Err(format!("unknown audience {}", value)),
+        }
+    }
+}
+
+impl fmt::Display for Audience {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Audience::All => write!(f, "all"),
+            Audience::Paid => write!(f, "paid"),
+            Audience::RecentSessions(days) => write!(f, "sessions:{}", days),
+            Audience::Inactive(days) => write!(f, "inactive:{}", days),
+            Audience::Consultant(id) => write!(f, "consultant:{}", id),
+            Audience::Locale(locale) => write!(f, "locale:{}", locale.code()),
+        }
+    }
+}
+
+impl Audience {
+    /// Чаты получателей рассылки
+    #[tracing::instrument(name = "db.broadcast_recipients", skip_all)]
+    pub async fn recipients(&self, state: &BotState) -> Result<Vec<i64>, sqlx::Error> {
+        let pool = &state.db.pool;
+
+        match self {
+            Audience::All => {
+                sqlx::query_scalar("SELECT chat_id FROM user_states WHERE blocked_at IS NULL ORDER BY chat_id")
+                    .fetch_all(pool)
+                    .await
+            }
+            Audience::Paid => {
+                sqlx::query_scalar(
+                    "SELECT u.chat_id FROM user_states u
+                     WHERE u.blocked_at IS NULL
+                       AND EXISTS (SELECT 1 FROM bookings b
+                                   WHERE b.chat_id = u.chat_id AND b.is_paid AND b.total_price > 0)
+                     ORDER BY u.chat_id"
+                )
+                .fetch_all(pool)
+                .await
+            }
+            Audience::RecentSessions(days) => {
+                sqlx::query_scalar(
+                    "SELECT u.chat_id FROM user_states u
+                     WHERE u.blocked_at IS NULL
+                       AND EXISTS (SELECT 1 FROM bookings b
+                                   WHERE b.chat_id = u.chat_id AND b.started_at > NOW() - make_interval(days => $1))
+                     ORDER BY u.chat_id"
+                )
+                .bind(days)
+                .fetch_all(pool)
+                .await
+            }
+            Audience::Inactive(days) => {
+                sqlx::query_scalar(
+                    "SELECT chat_id FROM user_states
+                     WHERE blocked_at IS NULL AND updated_at < NOW() - make_interval(days => $1)
+                     ORDER BY chat_id"
+                )
+                .bind(days)
+                .fetch_all(pool)
+                .await
+            }
+            Audience::Consultant(id) => {
+                sqlx::query_scalar(
+                    "SELECT u.chat_id FROM user_states u
+                     WHERE u.blocked_at IS NULL
+                       AND EXISTS (SELECT 1 FROM bookings b
+                                   WHERE b.chat_id = u.chat_id AND b.assistant_id = $1 AND b.started_at IS NOT NULL)
+                     ORDER BY u.chat_id"
+                )
+                .bind(id)
+                .fetch_all(pool)
+                .await
+            }
+            Audience::Locale(locale) => {
+                // Язык определяется так же, как в UserState::locale
+                let rows: Vec<(i64, Option<String>, Option<String>)> = sqlx::query_as(
+                    "SELECT chat_id, locale, language_code FROM user_states WHERE blocked_at IS NULL ORDER BY chat_id"
+                )
+                .fetch_all(pool)
+                .await?;
+
+                Ok(rows
+                    .into_iter()
+                    .filter(|(_, chosen, language_code)| {
+                        let user_locale = chosen
+                            .as_deref()
+                            .and_then(Locale::from_code)
+                            .unwrap_or_else(|| Locale::from_language_code(language_code.as_deref()));
+                        user_locale == *locale
+                    })
+                    .map(|(chat_id, _, _)| chat_id)
+                    .collect())
+            }
+        }
+    }
+}
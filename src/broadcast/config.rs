@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+
+use crate::settings::config::parse_env;
+
+const BROADCAST_RATE_ENV: &str = "BROADCAST_MESSAGES_PER_SECOND";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BroadcastConfig {
+    /// Сколько сообщений в секунду отправлять (лимит Telegram — около 30 разным чатам)
+    pub messages_per_second: u32,
+    /// Сколько получателей выбирать из очереди за один запрос
+    pub batch_size: i64,
+    /// Как часто обновлять у администратора сообщение с ходом рассылки
+    pub progress_interval_seconds: u64,
+    /// Как часто проверять очередь рассылок
+    pub poll_interval_seconds: u64,
+}
+
+impl Default for BroadcastConfig {
+    fn default() -> Self {
+        Self {
+            messages_per_second: 25,
+            batch_size: 100,
+            progress_interval_seconds: 15,
+            poll_interval_seconds: 10,
+        }
+    }
+}
+
+impl BroadcastConfig {
+    /// BROADCAST_MESSAGES_PER_SECOND задает скорость рассылки
+    pub(crate) fn apply_env(&mut self) -> anyhow::Result<()> {
+        if let Ok(value) = env::var(BROADCAST_RATE_ENV) {
+            self.messages_per_second = parse_env(BROADCAST_RATE_ENV, &value)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn validate(&self, errors: &mut Vec<String>) {
+        if self.messages_per_second == 0 {
+            errors.push("broadcast.messages_per_second must be positive".to_string());
+        }
+        if self.batch_size < 1 {
+            errors.push("broadcast.batch_size must be positive".to_string());
+        }
+        if self.progress_interval_seconds == 0 {
+            errors.push("broadcast.progress_interval_seconds must be positive".to_string());
+        }
+        if self.poll_interval_seconds == 0 {
+            errors.push("broadcast.poll_interval_seconds must be positive".to_string());
+        }
+    }
+}
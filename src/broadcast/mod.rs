@@ -0,0 +1,396 @@
+pub mod audience;
+pub mod config;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use std::time::{Duration, Instant};
+use teloxide::prelude::*;
+use teloxide::types::MessageId;
+use teloxide::{ApiError, RequestError};
+use tokio::time::{self, MissedTickBehavior};
+
+use crate::bot_state::BotState;
+use crate::i18n;
+use crate::tr;
+pub use audience::Audience;
+pub use config::BroadcastConfig;
+
+
+/// Настройки рассылок (секция `[broadcast]` основных настроек)
+pub fn config() -> &'static BroadcastConfig {
+    &crate::settings::config().broadcast
+}
+
+/// Этапы рассылки: черновик ждет подтверждения администратора, очередь и отправка переживают перезапуск
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BroadcastStatus {
+    Draft,
+    Queued,
+    Running,
+    Done,
+    Cancelled,
+}
+
+impl BroadcastStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BroadcastStatus::Draft => "draft",
+            BroadcastStatus::Queued => "queued",
+            BroadcastStatus::Running => "running",
+            BroadcastStatus::Done => "done",
+            BroadcastStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl From<String> for BroadcastStatus {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "queued" => BroadcastStatus::Queued,
+            "running" => BroadcastStatus::Running,
+            "done" => BroadcastStatus::Done,
+            "cancelled" => BroadcastStatus::Cancelled,
+            _ => BroadcastStatus::Draft,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Broadcast {
+    pub id: i32,
+    pub created_by: i64,
+    pub audience: String,
+    pub text: String,
+    #[sqlx(try_from = "String")]
+    pub status: BroadcastStatus,
+    pub total: i32,
+    pub sent: i32,
+    pub failed: i32,
+    pub blocked: i32,
+    pub progress_message_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Итог доставки одному получателю
+enum Delivery {
+    Sent,
+    Blocked,
+    Failed(String),
+}
+
+const BROADCAST_COLUMNS: &str = "id, created_by, audience, text, status, total, sent, failed, blocked,
+    progress_message_id, created_at, started_at, finished_at";
+
+impl Broadcast {
+    /// Создает черновик и сразу фиксирует список получателей
+    pub async fn create(
+        state: &BotState,
+        created_by: ChatId,
+        audience: Audience,
+        text: &str,
+    ) -> Result<Self, sqlx::Error> {
+        let recipients = audience.recipients(state).await?;
+        let mut tx = state.db.pool.begin().await?;
+
+        let broadcast = sqlx::query_as::<_, Broadcast>(&format!(
+            "INSERT INTO broadcasts (created_by, audience, text, total) VALUES ($1, $2, $3, $4) RETURNING {}",
+            BROADCAST_COLUMNS
+        ))
+        .bind(created_by.0)
+        .bind(audience.to_string())
+        .bind(text)
+        .bind(recipients.len() as i32)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "INSERT INTO broadcast_recipients (broadcast_id, chat_id) SELECT $1, UNNEST($2::BIGINT[])"
+        )
+        .bind(broadcast.id)
+        .bind(&recipients)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(broadcast)
+    }
+
+    pub async fn find(state: &BotState, id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Broadcast>(&format!("SELECT {} FROM broadcasts WHERE id = $1", BROADCAST_COLUMNS))
+            .bind(id)
+            .fetch_optional(&state.db.pool)
+            .await
+    }
+
+    /// Последние рассылки, новые первыми
+    pub async fn list_recent(state: &BotState, limit: i64) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Broadcast>(&format!(
+            "SELECT {} FROM broadcasts ORDER BY created_at DESC LIMIT $1",
+            BROADCAST_COLUMNS
+        ))
+        .bind(limit)
+        .fetch_all(&state.db.pool)
+        .await
+    }
+
+    /// Ставит черновик в очередь; сообщение с кнопками становится отчетом о ходе рассылки
+    pub async fn enqueue(state: &BotState, id: i32, progress_message_id: MessageId) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE broadcasts SET status = 'queued', progress_message_id = $2 WHERE id = $1 AND status = 'draft'"
+        )
+        .bind(id)
+        .bind(progress_message_id.0)
+        .execute(&state.db.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Отменяет рассылку, которая еще не завершена
+    pub async fn cancel(state: &BotState, id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE broadcasts SET status = 'cancelled', finished_at = NOW()
+             WHERE id = $1 AND status IN ('draft', 'queued', 'running')"
+        )
+        .bind(id)
+        .execute(&state.db.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Рассылка, которую нужно отправлять: прерванная перезапуском или самая старая в очереди
+    async fn next_pending(state: &BotState) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Broadcast>(&format!(
+            "SELECT {} FROM broadcasts WHERE status IN ('running', 'queued')
+             ORDER BY status = 'running' DESC, created_at ASC
+             LIMIT 1",
+            BROADCAST_COLUMNS
+        ))
+        .fetch_optional(&state.db.pool)
+        .await
+    }
+
+    async fn pending_recipients(&self, state: &BotState, limit: i64) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT chat_id FROM broadcast_recipients
+             WHERE broadcast_id = $1 AND status = 'pending'
+             ORDER BY chat_id
+             LIMIT $2"
+        )
+        .bind(self.id)
+        .bind(limit)
+        .fetch_all(&state.db.pool)
+        .await
+    }
+
+    /// Записывает итог доставки и обновляет счетчики рассылки
+    async fn record(&mut self, state: &BotState, chat_id: i64, delivery: &Delivery) -> Result<(), sqlx::Error> {
+        let (status, error, counter) = match delivery {
+            Delivery::Sent => ("sent", None, "sent"),
+            Delivery::Blocked => ("blocked", None, "blocked"),
+            Delivery::Failed(error) => ("failed", Some(error.as_str()), "failed"),
+        };
+
+        let mut tx = state.db.pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE broadcast_recipients SET status = $3, error = $4, sent_at = NOW()
+             WHERE broadcast_id = $1 AND chat_id = $2"
+        )
+        .bind(self.id)
+        .bind(chat_id)
+        .bind(status)
+        .bind(error)
+        .execute(&mut *tx)
+        .await?;
+
+        // Имя колонки берется из фиксированного списка выше
+        sqlx::query(&format!("UPDATE broadcasts SET {0} = {0} + 1 WHERE id = $1", counter))
+            .bind(self.id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        match delivery {
+            Delivery::Sent => self.sent += 1,
+            Delivery::Blocked => self.blocked += 1,
+            Delivery::Failed(_) => self.failed += 1,
+        }
+        Ok(())
+    }
+
+    async fn set_status(&mut self, state: &BotState, status: BroadcastStatus) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE broadcasts SET status = $2,
+                 started_at = CASE WHEN $2 = 'running' THEN COALESCE(started_at, NOW()) ELSE started_at END,
+                 finished_at = CASE WHEN $2 IN ('done', 'cancelled') THEN NOW() ELSE finished_at END
+             WHERE id = $1 AND status <> 'cancelled'"
+        )
+        .bind(self.id)
+        .bind(status.as_str())
+        .execute(&state.db.pool)
+        .await?;
+
+        self.status = status;
+        Ok(())
+    }
+
+    /// Не отменил ли администратор рассылку во время отправки
+    async fn is_cancelled(&self, state: &BotState) -> bool {
+        match Self::find(state, self.id).await {
+            Ok(Some(current)) => current.status == BroadcastStatus::Cancelled,
+            Ok(None) => true,
+            Err(e) => {
+                tracing::error!(broadcast_id = self.id, error = %e, "could not check broadcast status");
+                false
+            }
+        }
+    }
+
+    /// Сколько получателей уже обработано
+    pub fn processed(&self) -> i32 {
+        self.sent + self.failed + self.blocked
+    }
+}
+
+/// Запоминает, что пользователь заблокировал бота (или разблокировал его снова)
+pub async fn set_user_blocked(state: &BotState, chat_id: ChatId, blocked: bool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE user_states SET blocked_at = CASE WHEN $2 THEN COALESCE(blocked_at, NOW()) END WHERE chat_id = $1"
+    )
+    .bind(chat_id.0)
+    .bind(blocked)
+    .execute(&state.db.pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Текст отчета о ходе рассылки на языке администратора
+pub async fn progress_text(state: &BotState, broadcast: &Broadcast) -> String {
+    let lang = i18n::user_locale(state, ChatId(broadcast.created_by)).await;
+
+    tr!(
+        lang,
+        "broadcast.progress",
+        id = broadcast.id,
+        status = tr!(lang, &format!("broadcast.status_{}", broadcast.status.as_str())),
+        audience = broadcast.audience,
+        processed = broadcast.processed(),
+        total = broadcast.total,
+        sent = broadcast.sent,
+        blocked = broadcast.blocked,
+        failed = broadcast.failed,
+    )
+}
+
+/// Обновляет у администратора сообщение с ходом рассылки
+async fn report_progress(bot: &Bot, state: &BotState, broadcast: &Broadcast) {
+    let Some(message_id) = broadcast.progress_message_id else {
+        return;
+    };
+
+    let text = progress_text(state, broadcast).await;
+    if let Err(e) = bot.edit_message_text(ChatId(broadcast.created_by), MessageId(message_id), text).await {
+        // Telegram отвечает ошибкой, если текст не изменился
+        tracing::debug!(broadcast_id = broadcast.id, error = %e, "could not update broadcast progress");
+    }
+}
+
+/// Отправляет одно сообщение, дожидаясь снятия ограничения Telegram при превышении лимита
+async fn deliver(bot: &Bot, chat_id: ChatId, text: &str) -> Delivery {
+    loop {
+        match bot.send_message(chat_id, text).await {
+            Ok(_) => return Delivery::Sent,
+            Err(RequestError::RetryAfter(wait)) => {
+                tracing::warn!(wait_seconds = wait.duration().as_secs(), "Telegram rate limit hit, pausing broadcast");
+                time::sleep(wait.duration()).await;
+            }
+            Err(RequestError::Api(
+                ApiError::BotBlocked | ApiError::UserDeactivated | ApiError::ChatNotFound,
+            )) => return Delivery::Blocked,
+            Err(e) => return Delivery::Failed(e.to_string()),
+        }
+    }
+}
+
+/// Отправляет одну рассылку из очереди (или продолжает прерванную), если она есть
+#[tracing::instrument(name = "db.broadcast_process_next", skip_all)]
+pub async fn process_next(bot: &Bot, state: &BotState, heartbeat: impl Fn()) -> Result<(), sqlx::Error> {
+    let Some(mut broadcast) = Broadcast::next_pending(state).await? else {
+        return Ok(());
+    };
+
+    let config = config();
+    broadcast.set_status(state, BroadcastStatus::Running).await?;
+    tracing::info!(broadcast_id = broadcast.id, audience = %broadcast.audience, recipients = broadcast.total, "sending broadcast");
+
+    let mut rate = time::interval(Duration::from_secs_f64(1.0 / config.messages_per_second.max(1) as f64));
+    rate.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let progress_interval = Duration::from_secs(config.progress_interval_seconds.max(1));
+    let mut last_report = Instant::now();
+
+    report_progress(bot, state, &broadcast).await;
+
+    loop {
+        let recipients = broadcast.pending_recipients(state, config.batch_size.max(1)).await?;
+        if recipients.is_empty() {
+            break;
+        }
+
+        for chat_id in recipients {
+            rate.tick().await;
+            heartbeat();
+
+            let delivery = deliver(bot, ChatId(chat_id), &broadcast.text).await;
+            match &delivery {
+                Delivery::Blocked => {
+                    if let Err(e) = set_user_blocked(state, ChatId(chat_id), true).await {
+                        tracing::error!(chat_id, error = %e, "could not mark user as blocked");
+                    }
+                }
+                Delivery::Failed(error) => {
+                    tracing::warn!(broadcast_id = broadcast.id, chat_id, error = %error, "could not deliver broadcast");
+                }
+                Delivery::Sent => {}
+            }
+            broadcast.record(state, chat_id, &delivery).await?;
+
+            if last_report.elapsed() >= progress_interval {
+                last_report = Instant::now();
+
+                if broadcast.is_cancelled(state).await {
+                    broadcast.status = BroadcastStatus::Cancelled;
+                    tracing::info!(broadcast_id = broadcast.id, processed = broadcast.processed(), "broadcast cancelled");
+                    report_progress(bot, state, &broadcast).await;
+                    return Ok(());
+                }
+                report_progress(bot, state, &broadcast).await;
+            }
+        }
+    }
+
+    // Отмена могла прийти уже после последнего получателя
+    if broadcast.is_cancelled(state).await {
+        broadcast.status = BroadcastStatus::Cancelled;
+    } else {
+        broadcast.set_status(state, BroadcastStatus::Done).await?;
+    }
+
+    tracing::info!(
+        broadcast_id = broadcast.id,
+        sent = broadcast.sent,
+        blocked = broadcast.blocked,
+        failed = broadcast.failed,
+        "broadcast finished"
+    );
+    report_progress(bot, state, &broadcast).await;
+
+    Ok(())
+}
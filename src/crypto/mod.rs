@@ -0,0 +1,568 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::types::Json;
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::env;
+use std::sync::{OnceLock, RwLock};
+use teloxide::types::ChatId;
+
+use crate::llm::config::ChatMessage;
+
+const DATA_ENCRYPTION_KEY_ENV: &str = "DATA_ENCRYPTION_KEY";
+const DATA_ENCRYPTION_KEY_PREVIOUS_ENV: &str = "DATA_ENCRYPTION_KEY_PREVIOUS";
+/// Пространство префиксов, зарезервированное за хранилищем: открытый текст с таким началом экранируется
+const RESERVED_PREFIX: &str = "enc:";
+/// Префикс зашифрованного значения
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+/// Префикс открытого текста, который сам начинается с зарезервированного префикса
+const ESCAPED_PREFIX: &str = "enc:raw:";
+const NONCE_LENGTH: usize = 12;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CryptoError {
+    #[error("invalid encryption key in {0}")]
+    InvalidKey(&'static str),
+    #[error("encryption failed")]
+    Encrypt,
+    #[error("decryption failed")]
+    Decrypt,
+    #[error("data key is wrapped with unknown master key {0}, run `rotate-keys`")]
+    UnknownMasterKey(String),
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Мастер-ключ из конфигурации, которым шифруются ключи пользователей
+struct MasterKey {
+    /// Отпечаток ключа, чтобы знать, каким ключом обернут ключ пользователя
+    id: String,
+    cipher: Aes256Gcm,
+}
+
+impl MasterKey {
+    fn from_env(var: &'static str) -> Result<Option<Self>, CryptoError> {
+        let Ok(encoded) = env::var(var) else {
+            return Ok(None);
+        };
+
+        let bytes = BASE64.decode(encoded.trim()).map_err(|_| CryptoError::InvalidKey(var))?;
+        if bytes.len() != 32 {
+            return Err(CryptoError::InvalidKey(var));
+        }
+
+        let id = hex_prefix(&Sha256::digest(&bytes));
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&bytes));
+
+        Ok(Some(Self { id, cipher }))
+    }
+}
+
+fn hex_prefix(bytes: &[u8]) -> String {
+    bytes.iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+static MASTER_KEY: OnceLock<Option<MasterKey>> = OnceLock::new();
+static DATA_KEYS: OnceLock<RwLock<HashMap<i64, Aes256Gcm>>> = OnceLock::new();
+
+fn master_key() -> Option<&'static MasterKey> {
+    MASTER_KEY.get().and_then(Option::as_ref)
+}
+
+fn data_keys() -> &'static RwLock<HashMap<i64, Aes256Gcm>> {
+    DATA_KEYS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Загружает мастер-ключ при старте, чтобы ошибка конфигурации проявилась сразу
+pub fn init() -> Result<(), CryptoError> {
+    let key = MasterKey::from_env(DATA_ENCRYPTION_KEY_ENV)?;
+
+    match &key {
+        Some(key) => tracing::info!(master_key = %key.id, "encryption at rest enabled"),
+        None => tracing::warn!("{} is not set, conversation content is stored unencrypted", DATA_ENCRYPTION_KEY_ENV),
+    }
+
+    let _ = MASTER_KEY.set(key);
+    Ok(())
+}
+
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let mut sealed = nonce.to_vec();
+    sealed.extend(cipher.encrypt(&nonce, plaintext).map_err(|_| CryptoError::Encrypt)?);
+    Ok(sealed)
+}
+
+fn open(cipher: &Aes256Gcm, sealed: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    if sealed.len() < NONCE_LENGTH {
+        return Err(CryptoError::Decrypt);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| CryptoError::Decrypt)
+}
+
+/// Ключ данных пользователя; создается при первом обращении. None — шифрование выключено.
+async fn data_key(pool: &PgPool, chat_id: ChatId) -> Result<Option<Aes256Gcm>, CryptoError> {
+    let Some(master) = master_key() else {
+        return Ok(None);
+    };
+
+    if let Some(key) = data_keys().read().unwrap().get(&chat_id.0) {
+        return Ok(Some(key.clone()));
+    }
+
+    // Создаем ключ заранее; если он уже есть в базе, вставка ничего не изменит
+    let new_key = Aes256Gcm::generate_key(OsRng);
+    let wrapped = BASE64.encode(seal(&master.cipher, new_key.as_slice())?);
+
+    sqlx::query(
+        "INSERT INTO user_data_keys (chat_id, wrapped_key, master_key_id) VALUES ($1, $2, $3)
+         ON CONFLICT (chat_id) DO NOTHING"
+    )
+    .bind(chat_id.0)
+    .bind(&wrapped)
+    .bind(&master.id)
+    .execute(pool)
+    .await?;
+
+    let row = sqlx::query("SELECT wrapped_key, master_key_id FROM user_data_keys WHERE chat_id = $1")
+        .bind(chat_id.0)
+        .fetch_one(pool)
+        .await?;
+
+    let master_key_id: String = row.get("master_key_id");
+    if master_key_id != master.id {
+        return Err(CryptoError::UnknownMasterKey(master_key_id));
+    }
+
+    let wrapped: String = row.get("wrapped_key");
+    let key_bytes = open(&master.cipher, &BASE64.decode(wrapped).map_err(|_| CryptoError::Decrypt)?)?;
+    let key = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key_bytes));
+
+    data_keys().write().unwrap().insert(chat_id.0, key.clone());
+
+    Ok(Some(key))
+}
+
+/// Забывает ключ пользователя из кэша (после удаления данных)
+pub fn forget_data_key(chat_id: ChatId) {
+    data_keys().write().unwrap().remove(&chat_id.0);
+}
+
+/// Шифрует текст всегда, даже если он похож на шифртекст
+fn encrypt_with(key: &Aes256Gcm, text: &str) -> Result<String, CryptoError> {
+    Ok(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(seal(key, text.as_bytes())?)))
+}
+
+/// Открытый текст для хранения без ключа: значения с зарезервированным префиксом экранируются
+fn escape_plaintext(text: &str) -> String {
+    if text.starts_with(RESERVED_PREFIX) {
+        format!("{}{}", ESCAPED_PREFIX, text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn encrypt_or_escape(key: Option<&Aes256Gcm>, text: &str) -> Result<String, CryptoError> {
+    match key {
+        Some(key) => encrypt_with(key, text),
+        None => Ok(escape_plaintext(text)),
+    }
+}
+
+fn decrypt_with(key: Option<&Aes256Gcm>, text: &str) -> Result<String, CryptoError> {
+    if let Some(plaintext) = text.strip_prefix(ESCAPED_PREFIX) {
+        return Ok(plaintext.to_string());
+    }
+    // Открытые значения (записанные до включения шифрования) возвращаем как есть
+    let Some(encoded) = text.strip_prefix(ENCRYPTED_PREFIX) else {
+        return Ok(text.to_string());
+    };
+    let key = key.ok_or(CryptoError::Decrypt)?;
+    let sealed = BASE64.decode(encoded).map_err(|_| CryptoError::Decrypt)?;
+    String::from_utf8(open(key, &sealed)?).map_err(|_| CryptoError::Decrypt)
+}
+
+/// Значение уже зашифровано этим ключом. Префикса недостаточно: открытый текст мог начинаться так же
+fn is_sealed_with(key: &Aes256Gcm, text: &str) -> bool {
+    text.starts_with(ENCRYPTED_PREFIX) && decrypt_with(Some(key), text).is_ok()
+}
+
+pub async fn encrypt_text(pool: &PgPool, chat_id: ChatId, text: &str) -> Result<String, CryptoError> {
+    let key = data_key(pool, chat_id).await?;
+    encrypt_or_escape(key.as_ref(), text)
+}
+
+pub async fn decrypt_text(pool: &PgPool, chat_id: ChatId, text: &str) -> Result<String, CryptoError> {
+    let key = data_key(pool, chat_id).await?;
+    decrypt_with(key.as_ref(), text)
+}
+
+/// Шифрует текст сообщения и аргументы вызовов инструментов
+fn encrypt_message_with(key: Option<&Aes256Gcm>, message: &mut ChatMessage) -> Result<(), CryptoError> {
+    if let Some(content) = &message.content {
+        message.content = Some(encrypt_or_escape(key, content)?);
+    }
+    for call in message.tool_calls.iter_mut().flatten() {
+        call.function.arguments = encrypt_or_escape(key, &call.function.arguments)?;
+    }
+    Ok(())
+}
+
+fn decrypt_message_with(key: Option<&Aes256Gcm>, message: &mut ChatMessage) -> Result<(), CryptoError> {
+    if let Some(content) = &message.content {
+        message.content = Some(decrypt_with(key, content)?);
+    }
+    for call in message.tool_calls.iter_mut().flatten() {
+        call.function.arguments = decrypt_with(key, &call.function.arguments)?;
+    }
+    Ok(())
+}
+
+/// В сообщении есть значения, которые нужно расшифровать или разэкранировать
+fn is_stored_message(message: &ChatMessage) -> bool {
+    message.content.as_deref().is_some_and(|c| c.starts_with(RESERVED_PREFIX))
+        || message.tool_calls.iter().flatten().any(|call| call.function.arguments.starts_with(RESERVED_PREFIX))
+}
+
+/// Шифрует содержимое сообщений истории
+pub async fn encrypt_history(pool: &PgPool, chat_id: ChatId, history: &mut [ChatMessage]) -> Result<(), CryptoError> {
+    let key = data_key(pool, chat_id).await?;
+    for message in history.iter_mut() {
+        encrypt_message_with(key.as_ref(), message)?;
+    }
+    Ok(())
+}
+
+/// Расшифровывает содержимое сообщений истории
+pub async fn decrypt_history(pool: &PgPool, chat_id: ChatId, history: &mut [ChatMessage]) -> Result<(), CryptoError> {
+    if !history.iter().any(is_stored_message) {
+        return Ok(());
+    }
+
+    let key = data_key(pool, chat_id).await?;
+    for message in history.iter_mut() {
+        decrypt_message_with(key.as_ref(), message)?;
+    }
+    Ok(())
+}
+
+/// Расшифровывает все зашифрованные строки внутри JSON (для выгрузки данных пользователя)
+pub async fn decrypt_json(pool: &PgPool, chat_id: ChatId, value: &mut Value) -> Result<(), CryptoError> {
+    let key = data_key(pool, chat_id).await?;
+    decrypt_json_with(key.as_ref(), value)
+}
+
+fn decrypt_json_with(key: Option<&Aes256Gcm>, value: &mut Value) -> Result<(), CryptoError> {
+    match value {
+        Value::String(text) if text.starts_with(RESERVED_PREFIX) => {
+            *text = decrypt_with(key, text)?;
+        }
+        Value::Array(items) => {
+            for item in items {
+                decrypt_json_with(key, item)?;
+            }
+        }
+        Value::Object(map) => {
+            for item in map.values_mut() {
+                decrypt_json_with(key, item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Перешифровывает ключи пользователей текущим мастер-ключом.
+/// Ключи, обернутые предыдущим ключом, читаются с помощью DATA_ENCRYPTION_KEY_PREVIOUS.
+#[tracing::instrument(name = "db.rotate_keys", skip_all)]
+pub async fn rotate_keys(pool: &PgPool) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+    let master = master_key().ok_or(CryptoError::InvalidKey(DATA_ENCRYPTION_KEY_ENV))?;
+    let previous = MasterKey::from_env(DATA_ENCRYPTION_KEY_PREVIOUS_ENV)?;
+
+    let rows = sqlx::query(
+        "SELECT chat_id, wrapped_key, master_key_id FROM user_data_keys WHERE master_key_id <> $1"
+    )
+    .bind(&master.id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut rotated = 0;
+    for row in rows {
+        let chat_id: i64 = row.get("chat_id");
+        let master_key_id: String = row.get("master_key_id");
+        let wrapped: String = row.get("wrapped_key");
+
+        let Some(previous) = previous.as_ref().filter(|k| k.id == master_key_id) else {
+            tracing::error!(chat_id, master_key = %master_key_id, "data key is wrapped with unknown master key");
+            continue;
+        };
+
+        let key_bytes = open(&previous.cipher, &BASE64.decode(wrapped)?)?;
+        let rewrapped = BASE64.encode(seal(&master.cipher, &key_bytes)?);
+
+        sqlx::query(
+            "UPDATE user_data_keys SET wrapped_key = $1, master_key_id = $2, rotated_at = NOW() WHERE chat_id = $3"
+        )
+        .bind(rewrapped)
+        .bind(&master.id)
+        .bind(chat_id)
+        .execute(pool)
+        .await?;
+
+        rotated += 1;
+    }
+
+    Ok(rotated)
+}
+
+/// Имя однократной миграции, шифрующей данные, записанные до включения шифрования
+const ENCRYPT_EXISTING_MIGRATION: &str = "encrypt_existing_v1";
+
+/// Шифрует значения сообщения, которые еще не зашифрованы этим ключом; возвращает, изменилось ли оно
+fn encrypt_plaintext_in_message(key: &Aes256Gcm, message: &mut ChatMessage) -> Result<bool, CryptoError> {
+    let mut changed = false;
+    if let Some(content) = message.content.as_mut()
+        && !is_sealed_with(key, content)
+    {
+        *content = encrypt_with(key, content)?;
+        changed = true;
+    }
+    for call in message.tool_calls.iter_mut().flatten() {
+        if !is_sealed_with(key, &call.function.arguments) {
+            call.function.arguments = encrypt_with(key, &call.function.arguments)?;
+            changed = true;
+        }
+    }
+    Ok(changed)
+}
+
+/// Шифрует открытые сообщения истории; возвращает, изменилась ли она
+async fn encrypt_plaintext_in_history(
+    pool: &PgPool,
+    chat_id: ChatId,
+    history: &mut [ChatMessage],
+) -> Result<bool, CryptoError> {
+    let Some(key) = data_key(pool, chat_id).await? else {
+        return Ok(false);
+    };
+    let mut changed = false;
+    for message in history.iter_mut() {
+        changed |= encrypt_plaintext_in_message(&key, message)?;
+    }
+    Ok(changed)
+}
+
+/// Однократно шифрует данные пользователей, сохраненные открытым текстом до включения шифрования.
+/// Выполняется при старте, если задан мастер-ключ; возвращает число обновленных записей
+#[tracing::instrument(name = "db.encrypt_existing", skip_all)]
+pub async fn encrypt_existing(pool: &PgPool) -> Result<u64, CryptoError> {
+    if master_key().is_none() {
+        return Ok(0);
+    }
+
+    let done: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM data_migrations WHERE name = $1)")
+        .bind(ENCRYPT_EXISTING_MIGRATION)
+        .fetch_one(pool)
+        .await?;
+    if done {
+        return Ok(0);
+    }
+
+    let mut updated = 0;
+
+    // Отрывки сообщений и воспоминания; обезличенные записи (chat_id = 0) не принадлежат никому
+    for (table, column) in [
+        ("safety_flags", "excerpt"),
+        ("moderation_violations", "excerpt"),
+        ("user_memories", "content"),
+    ] {
+        let rows: Vec<(i32, i64, String)> = sqlx::query_as(&format!(
+            "SELECT id, chat_id, {column} FROM {table} WHERE chat_id <> 0 AND {column} <> ''"
+        ))
+        .fetch_all(pool)
+        .await?;
+
+        for (id, chat_id, text) in rows {
+            let Some(key) = data_key(pool, ChatId(chat_id)).await? else {
+                continue;
+            };
+            if is_sealed_with(&key, &text) {
+                continue;
+            }
+            let encrypted = encrypt_with(&key, &text)?;
+            sqlx::query(&format!("UPDATE {table} SET {column} = $1 WHERE id = $2"))
+                .bind(encrypted)
+                .bind(id)
+                .execute(pool)
+                .await?;
+            updated += 1;
+        }
+    }
+
+    // Архив сессий
+    let rows: Vec<(i32, i64, Json<Vec<ChatMessage>>)> =
+        sqlx::query_as("SELECT id, chat_id, history FROM session_archive")
+            .fetch_all(pool)
+            .await?;
+    for (id, chat_id, Json(mut history)) in rows {
+        if !encrypt_plaintext_in_history(pool, ChatId(chat_id), &mut history).await? {
+            continue;
+        }
+        sqlx::query("UPDATE session_archive SET history = $1 WHERE id = $2")
+            .bind(Json(&history))
+            .bind(id)
+            .execute(pool)
+            .await?;
+        updated += 1;
+    }
+
+    // Текущие сессии
+    let rows: Vec<(i64, Value)> = sqlx::query_as(
+        "SELECT chat_id, current_session FROM user_states WHERE current_session IS NOT NULL"
+    )
+    .fetch_all(pool)
+    .await?;
+    for (chat_id, mut session) in rows {
+        let Some(history) = session.get("history").cloned() else {
+            continue;
+        };
+        let Ok(mut history) = serde_json::from_value::<Vec<ChatMessage>>(history) else {
+            tracing::warn!(chat_id, "skipping unreadable session history");
+            continue;
+        };
+        if !encrypt_plaintext_in_history(pool, ChatId(chat_id), &mut history).await? {
+            continue;
+        }
+        session["history"] = serde_json::to_value(&history).map_err(|_| CryptoError::Encrypt)?;
+        sqlx::query("UPDATE user_states SET current_session = $1 WHERE chat_id = $2")
+            .bind(session)
+            .bind(chat_id)
+            .execute(pool)
+            .await?;
+        updated += 1;
+    }
+
+    sqlx::query("INSERT INTO data_migrations (name) VALUES ($1) ON CONFLICT (name) DO NOTHING")
+        .bind(ENCRYPT_EXISTING_MIGRATION)
+        .execute(pool)
+        .await?;
+
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::config::{FunctionCall, ToolCall};
+
+    fn key() -> Aes256Gcm {
+        Aes256Gcm::new(&Aes256Gcm::generate_key(OsRng))
+    }
+
+    fn tamper(encrypted: &str) -> String {
+        let mut sealed = BASE64.decode(encrypted.strip_prefix(ENCRYPTED_PREFIX).unwrap()).unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 0x01;
+        format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(sealed))
+    }
+
+    #[test]
+    fn round_trip() {
+        let key = key();
+        let encrypted = encrypt_with(&key, "Мне тревожно перед экзаменом").unwrap();
+
+        assert!(encrypted.starts_with(ENCRYPTED_PREFIX));
+        assert!(!encrypted.contains("экзамен"));
+        assert_eq!(decrypt_with(Some(&key), &encrypted).unwrap(), "Мне тревожно перед экзаменом");
+        assert!(is_sealed_with(&key, &encrypted));
+    }
+
+    #[test]
+    fn plaintext_that_looks_encrypted_round_trips() {
+        let key = key();
+        for text in ["enc:v1:", "enc:v1:AAAA", "enc:raw:x", "enc:"] {
+            let encrypted = encrypt_with(&key, text).unwrap();
+            assert_ne!(encrypted, text);
+            assert_eq!(decrypt_with(Some(&key), &encrypted).unwrap(), text);
+            assert!(!is_sealed_with(&key, text));
+
+            // Без ключа такой текст хранится экранированным
+            let stored = escape_plaintext(text);
+            assert_eq!(decrypt_with(None, &stored).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn plaintext_passes_through_decryption() {
+        assert_eq!(decrypt_with(None, "старая запись").unwrap(), "старая запись");
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_rejected() {
+        let key = key();
+        let encrypted = encrypt_with(&key, "secret").unwrap();
+
+        assert!(matches!(decrypt_with(Some(&key), &tamper(&encrypted)), Err(CryptoError::Decrypt)));
+        assert!(matches!(decrypt_with(Some(&key), "enc:v1:AAAA"), Err(CryptoError::Decrypt)));
+        assert!(matches!(decrypt_with(Some(&key), "enc:v1:not base64!"), Err(CryptoError::Decrypt)));
+    }
+
+    #[test]
+    fn wrong_or_missing_key_is_rejected() {
+        let encrypted = encrypt_with(&key(), "secret").unwrap();
+
+        assert!(matches!(decrypt_with(Some(&key()), &encrypted), Err(CryptoError::Decrypt)));
+        assert!(matches!(decrypt_with(None, &encrypted), Err(CryptoError::Decrypt)));
+    }
+
+    #[test]
+    fn message_content_and_tool_arguments_round_trip() {
+        let key = key();
+        let original = ChatMessage {
+            role: "assistant".to_string(),
+            content: Some("Поставлю напоминание".to_string()),
+            tool_calls: Some(vec![ToolCall {
+                id: "call_1".to_string(),
+                type_: "function".to_string(),
+                function: FunctionCall {
+                    name: "set_reminder".to_string(),
+                    arguments: r#"{"text":"позвонить маме"}"#.to_string(),
+                },
+            }]),
+            tool_call_id: None,
+            name: None,
+        };
+
+        let mut message = original.clone();
+        encrypt_message_with(Some(&key), &mut message).unwrap();
+        assert!(is_stored_message(&message));
+        assert!(!serde_json::to_string(&message).unwrap().contains("маме"));
+
+        decrypt_message_with(Some(&key), &mut message).unwrap();
+        assert_eq!(message.content, original.content);
+        assert_eq!(
+            message.tool_calls.unwrap()[0].function.arguments,
+            original.tool_calls.unwrap()[0].function.arguments
+        );
+    }
+
+    #[test]
+    fn nested_json_is_decrypted() {
+        let key = key();
+        let mut value = serde_json::json!({
+            "memories": [{ "content": encrypt_with(&key, "любит бегать").unwrap(), "id": 1 }],
+            "plain": "as is",
+        });
+
+        decrypt_json_with(Some(&key), &mut value).unwrap();
+        assert_eq!(value["memories"][0]["content"], "любит бегать");
+        assert_eq!(value["plain"], "as is");
+    }
+}
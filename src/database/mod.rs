@@ -1,21 +1,30 @@
-use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions};
 use std::time::Duration;
 
+use crate::settings::config::DatabaseConfig;
+
 #[derive(Clone, Debug)]
 pub struct Database {
     pub pool: PgPool,
 }
 
 impl Database {
-    pub async fn new(database_url: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+    pub async fn new(config: &DatabaseConfig) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let options = PgConnectOptions::new()
+            .host(&config.host)
+            .port(config.port)
+            .username(&config.user)
+            .password(&config.password)
+            .database(&config.name);
+
         let pool = PgPoolOptions::new()
-            .max_connections(20)
-            .min_connections(5)
-            .acquire_timeout(Duration::from_secs(30))
-            .idle_timeout(Duration::from_secs(300))
-            .max_lifetime(Duration::from_secs(1800))
+            .max_connections(config.max_connections)
+            .min_connections(config.min_connections)
+            .acquire_timeout(Duration::from_secs(config.acquire_timeout_seconds))
+            .idle_timeout(Duration::from_secs(config.idle_timeout_seconds))
+            .max_lifetime(Duration::from_secs(config.max_lifetime_seconds))
             .test_before_acquire(true)
-            .connect(database_url)
+            .connect_with(options)
             .await?;
 
         Ok(Database { pool })
@@ -38,12 +47,21 @@ impl Database {
         )
         .execute(&self.pool)
         .await?;
-    
-        // Удаляем старую таблицу bookings
-        sqlx::query("DROP TABLE IF EXISTS bookings")
-            .execute(&self.pool)
-            .await?;
 
+        sqlx::query(
+            r#"
+            ALTER TABLE user_states
+                ADD COLUMN IF NOT EXISTS balance DOUBLE PRECISION NOT NULL DEFAULT 0,
+                ADD COLUMN IF NOT EXISTS pending_input JSONB,
+                ADD COLUMN IF NOT EXISTS keep_history BOOLEAN NOT NULL DEFAULT false,
+                ADD COLUMN IF NOT EXISTS locale TEXT,
+                ADD COLUMN IF NOT EXISTS language_code TEXT,
+                ADD COLUMN IF NOT EXISTS blocked_at TIMESTAMP WITH TIME ZONE
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+    
         // Таблица для консультантов
         sqlx::query(
             r#"
@@ -56,6 +74,8 @@ impl Database {
                 greeting TEXT NOT NULL,
                 prompt TEXT NOT NULL,
                 price_per_minute DOUBLE PRECISION NOT NULL DEFAULT 0.1,
+                billing_mode TEXT NOT NULL DEFAULT 'per_minute',
+                price_per_message DOUBLE PRECISION NOT NULL DEFAULT 0.05,
                 is_active BOOLEAN NOT NULL DEFAULT true,
                 created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
                 updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
@@ -65,7 +85,18 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
-        // Создаем новую таблицу bookings с assistant_id
+        // Колонки тарификации за сообщения для уже существующих таблиц
+        sqlx::query(
+            r#"
+            ALTER TABLE consultants
+                ADD COLUMN IF NOT EXISTS billing_mode TEXT NOT NULL DEFAULT 'per_minute',
+                ADD COLUMN IF NOT EXISTS price_per_message DOUBLE PRECISION NOT NULL DEFAULT 0.05
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Таблица bookings с assistant_id
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS bookings (
@@ -78,6 +109,11 @@ impl Database {
                 is_paid BOOLEAN NOT NULL DEFAULT false,
                 is_completed BOOLEAN NOT NULL DEFAULT false,
                 payment_invoice_message_id BIGINT,
+                message_quota INTEGER,
+                credit_applied DOUBLE PRECISION NOT NULL DEFAULT 0,
+                telegram_payment_charge_id TEXT,
+                is_refunded BOOLEAN NOT NULL DEFAULT false,
+                started_at TIMESTAMP WITH TIME ZONE,
                 created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
                 updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
                 expires_at TIMESTAMP WITH TIME ZONE DEFAULT (NOW() + INTERVAL '5 minutes'),
@@ -87,7 +123,21 @@ impl Database {
         )
         .execute(&self.pool)
         .await?;
-    
+
+        // Оплаченные брони хранятся между перезапусками, поэтому новые колонки добавляем миграцией
+        sqlx::query(
+            r#"
+            ALTER TABLE bookings
+                ADD COLUMN IF NOT EXISTS message_quota INTEGER,
+                ADD COLUMN IF NOT EXISTS credit_applied DOUBLE PRECISION NOT NULL DEFAULT 0,
+                ADD COLUMN IF NOT EXISTS telegram_payment_charge_id TEXT,
+                ADD COLUMN IF NOT EXISTS is_refunded BOOLEAN NOT NULL DEFAULT false,
+                ADD COLUMN IF NOT EXISTS started_at TIMESTAMP WITH TIME ZONE,
+                ADD COLUMN IF NOT EXISTS credit_reserved BOOLEAN NOT NULL DEFAULT false
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
 
         // Таблица для слотов времени
         sqlx::query(
@@ -106,39 +156,368 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
-        // Инициализация консультантов по умолчанию
+        // Таблица для пакетов сообщений
         sqlx::query(
             r#"
-            INSERT INTO consultants (id, model, name, description, specialty, greeting, prompt, price_per_minute) 
+            CREATE TABLE IF NOT EXISTS message_bundles (
+                id SERIAL PRIMARY KEY,
+                messages_count INTEGER NOT NULL,
+                description TEXT NOT NULL,
+                is_active BOOLEAN NOT NULL DEFAULT true,
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+                updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Таблица оценок сессий
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS session_ratings (
+                id SERIAL PRIMARY KEY,
+                chat_id BIGINT NOT NULL,
+                assistant_id INTEGER NOT NULL REFERENCES consultants(id),
+                booking_id TEXT NOT NULL UNIQUE,
+                rating SMALLINT NOT NULL CHECK (rating BETWEEN 1 AND 5),
+                comment TEXT,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+                updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Отметки подсистемы безопасности для ручной проверки
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS safety_flags (
+                id SERIAL PRIMARY KEY,
+                chat_id BIGINT NOT NULL,
+                booking_id TEXT,
+                assistant_id INTEGER,
+                category TEXT NOT NULL,
+                source TEXT NOT NULL,
+                excerpt TEXT NOT NULL,
+                reviewed BOOLEAN NOT NULL DEFAULT false,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS moderation_violations (
+                id SERIAL PRIMARY KEY,
+                chat_id BIGINT NOT NULL,
+                assistant_id INTEGER NOT NULL,
+                rule_id TEXT NOT NULL,
+                category TEXT NOT NULL,
+                action TEXT NOT NULL,
+                excerpt TEXT NOT NULL,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS user_memories (
+                id SERIAL PRIMARY KEY,
+                chat_id BIGINT NOT NULL,
+                kind TEXT NOT NULL DEFAULT 'fact',
+                content TEXT NOT NULL,
+                embedding REAL[] NOT NULL,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Напоминания, которые консультанты ставят инструментом во время ответа
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS reminders (
+                id SERIAL PRIMARY KEY,
+                chat_id BIGINT NOT NULL,
+                text TEXT NOT NULL,
+                remind_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                is_sent BOOLEAN NOT NULL DEFAULT false,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Цели и задачи пользователя (/goals); консультанты ведут их теми же инструментами
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS goals (
+                id SERIAL PRIMARY KEY,
+                chat_id BIGINT NOT NULL,
+                title TEXT NOT NULL,
+                is_done BOOLEAN NOT NULL DEFAULT false,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            ALTER TABLE goals
+            ADD COLUMN IF NOT EXISTS parent_id INTEGER REFERENCES goals(id) ON DELETE CASCADE,
+            ADD COLUMN IF NOT EXISTS completed_at TIMESTAMP WITH TIME ZONE
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Дневник настроения (/mood) и ежедневные опросы
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS mood_entries (
+                id SERIAL PRIMARY KEY,
+                chat_id BIGINT NOT NULL,
+                score SMALLINT NOT NULL CHECK (score BETWEEN 1 AND 10),
+                note TEXT,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS mood_checkins (
+                chat_id BIGINT PRIMARY KEY,
+                local_minute INTEGER NOT NULL CHECK (local_minute BETWEEN 0 AND 1439),
+                utc_offset_minutes INTEGER NOT NULL DEFAULT 180,
+                is_enabled BOOLEAN NOT NULL DEFAULT true,
+                last_sent_on DATE,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+                updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS session_archive (
+                id SERIAL PRIMARY KEY,
+                chat_id BIGINT NOT NULL,
+                assistant_id INTEGER NOT NULL,
+                booking_id TEXT,
+                started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                ended_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                history JSONB NOT NULL,
+                UNIQUE (chat_id, started_at)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Ключи шифрования пользователей, обернутые мастер-ключом
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS user_data_keys (
+                chat_id BIGINT PRIMARY KEY,
+                wrapped_key TEXT NOT NULL,
+                master_key_id TEXT NOT NULL,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+                rotated_at TIMESTAMP WITH TIME ZONE
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Однократные преобразования данных, уже выполненные на этой базе
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS data_migrations (
+                name TEXT PRIMARY KEY,
+                applied_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Рассылки и их получатели (очередь отправки переживает перезапуск)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS broadcasts (
+                id SERIAL PRIMARY KEY,
+                created_by BIGINT NOT NULL,
+                audience TEXT NOT NULL,
+                text TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'draft',
+                total INTEGER NOT NULL DEFAULT 0,
+                sent INTEGER NOT NULL DEFAULT 0,
+                failed INTEGER NOT NULL DEFAULT 0,
+                blocked INTEGER NOT NULL DEFAULT 0,
+                progress_message_id INTEGER,
+                created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+                started_at TIMESTAMP WITH TIME ZONE,
+                finished_at TIMESTAMP WITH TIME ZONE
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS broadcast_recipients (
+                broadcast_id INTEGER NOT NULL REFERENCES broadcasts(id) ON DELETE CASCADE,
+                chat_id BIGINT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                error TEXT,
+                sent_at TIMESTAMP WITH TIME ZONE,
+                PRIMARY KEY (broadcast_id, chat_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Администраторы, назначенные через бота (в дополнение к ADMIN_CHAT_IDS)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS admins (
+                chat_id BIGINT PRIMARY KEY,
+                added_by BIGINT,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Длительность и объем завершенных сессий для аналитики (переписка здесь не хранится)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS session_stats (
+                id SERIAL PRIMARY KEY,
+                chat_id BIGINT NOT NULL,
+                assistant_id INTEGER NOT NULL,
+                booking_id TEXT,
+                started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                ended_at TIMESTAMP WITH TIME ZONE NOT NULL,
+                messages INTEGER NOT NULL DEFAULT 0,
+                UNIQUE (chat_id, started_at)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Неоплаченные брони удаляются по истечении срока — для воронки оплат храним их количество по дням
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS booking_abandonment (
+                day DATE PRIMARY KEY,
+                bookings INTEGER NOT NULL DEFAULT 0
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Инициализация консультантов по умолчанию (профили правят администраторы, поэтому не перезаписываем)
+        sqlx::query(
+            r#"
+            INSERT INTO consultants (id, model, name, description, specialty, greeting, prompt, price_per_minute,
+                                     billing_mode, price_per_message) 
             VALUES 
                 (1, 'GigaChat-2-Max', 'Анна', 'Интерактивный помощник', 'Общение и поддержка в повседневных задачах', 
                  'Здравствуйте! Я Анна. Я помогу вам обсудить вопросы и получить полезные советы. Расскажите, что вас интересует?',
                  'Ты — Анна, виртуальный помощник, ориентированный на поддержку и советы в повседневной жизни. Твоя цель — помогать пользователю разбирать задачи, давать рекомендации и задавать уточняющие вопросы, чтобы пользователь самостоятельно находил решения.',
-                 0.1),
+                 0.1, 'per_minute', 0.05),
                 
                 (2, 'GigaChat-2-Pro', 'Максим', 'Наставник', 'Помощь в саморазвитии и планировании',
                  'Привет! Я Максим. Я помогу вам планировать задачи, развивать навыки и лучше понимать себя. С чего начнем?',
                  'Ты — Максим, виртуальный наставник для саморазвития. Твоя цель — помогать пользователю в постановке целей, планировании и развитии навыков. Ты задаешь наводящие вопросы и даешь советы, не навязывая решений.',
-                 0.09),
+                 0.09, 'per_minute', 0.05),
                 
                 (3, 'deepseek-chat', 'София', 'консультант', 'Поддержка и мотивация',
                  'Добрый день! Я София. Готова помочь обсудить идеи, задачи или получить мотивацию для новых целей.',
                  'Ты — София, виртуальный консультант для поддержки и мотивации. Твоя цель — создавать безопасное пространство для обсуждения идей и целей, помогать структурировать мысли и находить решения самостоятельно.',
-                 0.08),
+                 0.08, 'per_message', 0.04),
                 
                 (4, 'GigaChat-2', 'Алексей', 'Коуч', 'Целеполагание и продуктивность',
                  'Здравствуйте! Я Алексей. Я помогу вам определить цели и разработать план действий. С чего начнем?',
                  'Ты — Алексей, виртуальный коуч по постановке целей и повышению продуктивности. Твоя цель — помогать пользователю выявлять задачи, строить планы и находить пути достижения целей. Ты даешь советы и задаешь уточняющие вопросы, чтобы пользователь сам находил оптимальные решения.',
-                 0.07)
-            ON CONFLICT (id) DO UPDATE SET
-                model = EXCLUDED.model,
-                name = EXCLUDED.name,
-                description = EXCLUDED.description,
-                specialty = EXCLUDED.specialty,
-                greeting = EXCLUDED.greeting,
-                prompt = EXCLUDED.prompt,
-                price_per_minute = EXCLUDED.price_per_minute,
-                updated_at = NOW()
+                 0.07, 'per_minute', 0.03)
+            ON CONFLICT (id) DO NOTHING
+            "#
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Консультанты по умолчанию вставлены с явными id — сдвигаем счетчик для новых
+        sqlx::query(
+            "SELECT setval(pg_get_serial_sequence('consultants', 'id'), (SELECT MAX(id) FROM consultants))"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Переводы профилей консультантов; основные поля consultants — язык по умолчанию
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS consultant_translations (
+                consultant_id INTEGER NOT NULL REFERENCES consultants(id) ON DELETE CASCADE,
+                locale TEXT NOT NULL,
+                name TEXT,
+                description TEXT,
+                specialty TEXT,
+                greeting TEXT,
+                prompt TEXT,
+                created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+                updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+                PRIMARY KEY (consultant_id, locale)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Английские профили консультантов по умолчанию (правки администраторов не перезаписываем)
+        sqlx::query(
+            r#"
+            INSERT INTO consultant_translations (consultant_id, locale, name, description, specialty, greeting, prompt)
+            VALUES
+                (1, 'en', 'Anna', 'Interactive assistant', 'Conversation and support with everyday tasks',
+                 'Hello! I am Anna. I will help you talk things through and get useful advice. What would you like to discuss?',
+                 'You are Anna, a virtual assistant focused on support and advice in everyday life. Your goal is to help the user work through tasks, give recommendations and ask clarifying questions so that the user finds solutions on their own. Reply in English.'),
+
+                (2, 'en', 'Maxim', 'Mentor', 'Self-development and planning',
+                 'Hi! I am Maxim. I will help you plan tasks, build skills and understand yourself better. Where shall we start?',
+                 'You are Maxim, a virtual mentor for self-development. Your goal is to help the user set goals, plan and develop skills. You ask guiding questions and give advice without imposing decisions. Reply in English.'),
+
+                (3, 'en', 'Sofia', 'Consultant', 'Support and motivation',
+                 'Good afternoon! I am Sofia. I am happy to help you discuss ideas and tasks or find motivation for new goals.',
+                 'You are Sofia, a virtual consultant for support and motivation. Your goal is to create a safe space to discuss ideas and goals, help structure thoughts and find solutions independently. Reply in English.'),
+
+                (4, 'en', 'Alexey', 'Coach', 'Goal setting and productivity',
+                 'Hello! I am Alexey. I will help you define goals and build an action plan. Where shall we start?',
+                 'You are Alexey, a virtual coach for goal setting and productivity. Your goal is to help the user identify tasks, build plans and find ways to reach goals. You give advice and ask clarifying questions so the user finds the best solutions themselves. Reply in English.')
+            ON CONFLICT (consultant_id, locale) DO NOTHING
             "#
         )
         .execute(&self.pool)
@@ -216,6 +595,66 @@ impl Database {
         )
         .execute(&self.pool)
         .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_session_ratings_assistant_id ON session_ratings (assistant_id)"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_safety_flags_reviewed ON safety_flags (reviewed)"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_moderation_violations_assistant ON moderation_violations (assistant_id)"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_user_memories_chat ON user_memories (chat_id)"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_goals_chat ON goals (chat_id)"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_mood_entries_chat ON mood_entries (chat_id, created_at)"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_reminders_due ON reminders (is_sent, remind_at)"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_message_bundles_active ON message_bundles (is_active)"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_broadcasts_status ON broadcasts (status)"
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_broadcast_recipients_pending ON broadcast_recipients (broadcast_id, status)"
+        )
+        .execute(&self.pool)
+        .await?;
     
         Ok(())
     }
@@ -0,0 +1,714 @@
+use teloxide::prelude::*;
+use std::error::Error;
+use std::str::FromStr;
+
+use crate::admin;
+use crate::bot_state::BotState;
+use crate::i18n::{self, Locale};
+use crate::tr;
+use crate::models::{AIAssistant, BillingMode, ConsultantTranslation, TimeSlot};
+
+use crate::AdminCommand;
+use super::{analytics, broadcast};
+
+/// Поля профиля, у которых бывают переводы (`name:en`)
+const TRANSLATABLE_FIELDS: [&str; 5] = ["name", "description", "specialty", "greeting", "prompt"];
+const CONSULTANT_FIELDS: [&str; 9] = [
+    "name", "description", "specialty", "greeting", "prompt",
+    "model", "billing", "minute_price", "message_price",
+];
+const SLOT_FIELDS: [&str; 4] = ["minutes", "description", "order", "active"];
+
+pub(crate) type HandlerResult = Result<(), Box<dyn Error + Send + Sync>>;
+
+/// Команды администраторов; в меню команд Telegram не регистрируются
+pub async fn admin_command_handler(
+    bot: Bot,
+    msg: Message,
+    cmd: AdminCommand,
+    state: BotState,
+) -> HandlerResult {
+    let chat_id = msg.chat.id;
+    let lang = i18n::locale_for(&state, chat_id, msg.from.as_ref()).await;
+
+    tracing::info!(admin_id = %chat_id, command = ?cmd, "admin command");
+
+    match cmd {
+        AdminCommand::Admin => {
+            bot.send_message(chat_id, tr!(lang, "admin.help")).await?;
+        }
+        AdminCommand::Consultants => list_consultants(&bot, &state, chat_id, lang).await?,
+        AdminCommand::Consultant(args) => show_consultant(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::AddConsultant(args) => add_consultant(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::EditConsultant(args) => edit_consultant(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::ActivateConsultant(args) => set_consultant_active(&bot, &state, chat_id, &args, true, lang).await?,
+        AdminCommand::DeactivateConsultant(args) => set_consultant_active(&bot, &state, chat_id, &args, false, lang).await?,
+        AdminCommand::DeleteTranslation(args) => delete_translation(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::Slots => list_slots(&bot, &state, chat_id, lang).await?,
+        AdminCommand::AddSlot(args) => add_slot(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::EditSlot(args) => edit_slot(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::Bookings(args) => show_user_bookings(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::Grant(args) => grant_minutes(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::Refund(args) => refund(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::Admins => list_admins(&bot, &state, chat_id, lang).await?,
+        AdminCommand::AddAdmin(args) => add_admin(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::RemoveAdmin(args) => remove_admin(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::Broadcast(args) => broadcast::create_broadcast(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::Broadcasts => broadcast::list_broadcasts(&bot, &state, chat_id, lang).await?,
+        AdminCommand::CancelBroadcast(args) => broadcast::cancel_broadcast(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::Analytics(args) => analytics::show_analytics(&bot, &state, chat_id, &args, lang).await?,
+        AdminCommand::AnalyticsCsv(args) => analytics::export_analytics(&bot, &state, chat_id, &args, lang).await?,
+    }
+
+    Ok(())
+}
+
+/// Подсказка по синтаксису команды
+pub(crate) async fn send_usage(bot: &Bot, chat_id: ChatId, command: &str, lang: Locale) -> HandlerResult {
+    let usage = tr!(lang, &format!("admin.usage.{}", command));
+    bot.send_message(chat_id, tr!(lang, "admin.usage_line", usage = usage)).await?;
+    Ok(())
+}
+
+pub(crate) async fn send_db_error(bot: &Bot, chat_id: ChatId, error: sqlx::Error, lang: Locale) -> HandlerResult {
+    tracing::error!(chat_id = %chat_id, error = %error, "admin command database error");
+    bot.send_message(chat_id, tr!(lang, "admin.db_error")).await?;
+    Ok(())
+}
+
+/// Первое слово аргументов и остаток строки
+pub(crate) fn split_first(args: &str) -> (&str, &str) {
+    let args = args.trim();
+    match args.split_once(char::is_whitespace) {
+        Some((first, rest)) => (first, rest.trim()),
+        None => (args, ""),
+    }
+}
+
+/// Разбирает ровно N аргументов-чисел
+fn parse_numbers<T: FromStr, const N: usize>(args: &str) -> Option<[T; N]> {
+    let values: Vec<T> = args
+        .split_whitespace()
+        .map(|value| value.parse().ok())
+        .collect::<Option<_>>()?;
+    values.try_into().ok()
+}
+
+/// Цена в Stars (в базе цены хранятся в сотых: 1 Star = 0.01)
+fn format_stars(price: f64) -> String {
+    let stars = price * 100.0;
+    if stars.fract().abs() < f64::EPSILON {
+        format!("{}", stars as i64)
+    } else {
+        format!("{:.2}", stars)
+    }
+}
+
+fn format_price(assistant: &AIAssistant, lang: Locale) -> String {
+    match assistant.billing_mode {
+        BillingMode::PerMinute => tr!(lang, "admin.price_per_minute", stars = format_stars(assistant.price_per_minute)),
+        BillingMode::PerMessage => tr!(lang, "admin.price_per_message", stars = format_stars(assistant.price_per_message)),
+    }
+}
+
+fn status_text(is_active: bool, lang: Locale) -> String {
+    if is_active {
+        tr!(lang, "admin.status_active")
+    } else {
+        tr!(lang, "admin.status_inactive")
+    }
+}
+
+async fn list_consultants(bot: &Bot, state: &BotState, chat_id: ChatId, lang: Locale) -> HandlerResult {
+    let consultants = match AIAssistant::list_for_admin(state).await {
+        Ok(consultants) => consultants,
+        Err(e) => return send_db_error(bot, chat_id, e, lang).await,
+    };
+
+    if consultants.is_empty() {
+        bot.send_message(chat_id, tr!(lang, "admin.consultants_empty")).await?;
+        return Ok(());
+    }
+
+    let lines: Vec<String> = consultants
+        .iter()
+        .map(|(assistant, is_active)| {
+            tr!(
+                lang,
+                "admin.consultant_line",
+                id = assistant.id,
+                name = assistant.name,
+                model = assistant.model,
+                price = format_price(assistant, lang),
+                status = status_text(*is_active, lang),
+            )
+        })
+        .collect();
+
+    bot.send_message(chat_id, format!("{}\n\n{}", tr!(lang, "admin.consultants_title"), lines.join("\n")))
+        .await?;
+    Ok(())
+}
+
+async fn show_consultant(bot: &Bot, state: &BotState, chat_id: ChatId, args: &str, lang: Locale) -> HandlerResult {
+    let Some([id]) = parse_numbers::<i32, 1>(args) else {
+        return send_usage(bot, chat_id, "consultant", lang).await;
+    };
+
+    let (assistant, is_active) = match AIAssistant::find_for_admin(state, id).await {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            bot.send_message(chat_id, tr!(lang, "admin.consultant_not_found", id = id)).await?;
+            return Ok(());
+        }
+        Err(e) => return send_db_error(bot, chat_id, e, lang).await,
+    };
+
+    let translations = match ConsultantTranslation::list_for_consultant(state, id).await {
+        Ok(translations) => translations,
+        Err(e) => return send_db_error(bot, chat_id, e, lang).await,
+    };
+
+    // Для каждого языка перечисляем переведенные поля
+    let translations_text = if translations.is_empty() {
+        tr!(lang, "admin.translations_none")
+    } else {
+        translations
+            .iter()
+            .map(|t| {
+                let filled: Vec<&str> = TRANSLATABLE_FIELDS
+                    .iter()
+                    .zip([&t.name, &t.description, &t.specialty, &t.greeting, &t.prompt])
+                    .filter(|(_, value)| value.as_ref().is_some_and(|v| !v.trim().is_empty()))
+                    .map(|(field, _)| *field)
+                    .collect();
+                format!("{} ({})", t.locale, filled.join(", "))
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+
+    let rating = match assistant.rating {
+        Some(rating) => tr!(lang, "admin.rating", rating = format!("{:.1}", rating), count = assistant.ratings_count),
+        None => tr!(lang, "admin.no_rating"),
+    };
+
+    let card = tr!(
+        lang,
+        "admin.consultant_card",
+        id = assistant.id,
+        name = assistant.name,
+        status = status_text(is_active, lang),
+        model = assistant.model,
+        price = format_price(&assistant, lang),
+        rating = rating,
+        description = assistant.description,
+        specialty = assistant.specialty,
+        greeting = assistant.greeting,
+        prompt = assistant.prompt,
+        translations = translations_text,
+    );
+
+    bot.send_message(chat_id, card).await?;
+    Ok(())
+}
+
+async fn add_consultant(bot: &Bot, state: &BotState, chat_id: ChatId, args: &str, lang: Locale) -> HandlerResult {
+    let (model, name) = split_first(args);
+    if model.is_empty() || name.is_empty() {
+        return send_usage(bot, chat_id, "addconsultant", lang).await;
+    }
+
+    match AIAssistant::create_assistant(state, model, name).await {
+        Ok(id) => {
+            tracing::info!(admin_id = %chat_id, assistant_id = id, model = %model, "consultant created");
+            bot.send_message(chat_id, tr!(lang, "admin.consultant_created", id = id)).await?;
+        }
+        Err(e) => send_db_error(bot, chat_id, e, lang).await?,
+    }
+    Ok(())
+}
+
+/// `/editconsultant <id> <поле>[:<язык>] <значение>`; значение "-" удаляет поле перевода
+async fn edit_consultant(bot: &Bot, state: &BotState, chat_id: ChatId, args: &str, lang: Locale) -> HandlerResult {
+    let (id, rest) = split_first(args);
+    let (field, value) = split_first(rest);
+    let Ok(id) = id.parse::<i32>() else {
+        return send_usage(bot, chat_id, "editconsultant", lang).await;
+    };
+    if field.is_empty() || value.is_empty() {
+        return send_usage(bot, chat_id, "editconsultant", lang).await;
+    }
+
+    let (field, locale) = match field.split_once(':') {
+        Some((field, code)) => match Locale::from_code(code) {
+            Some(locale) => (field, locale),
+            None => {
+                let locales = Locale::ALL.map(|l| l.code()).join(", ");
+                bot.send_message(chat_id, tr!(lang, "admin.unknown_locale", locale = code, locales = locales))
+                    .await?;
+                return Ok(());
+            }
+        },
+        None => (field, Locale::default()),
+    };
+
+    let known = if locale == Locale::default() {
+        CONSULTANT_FIELDS.contains(&field)
+    } else {
+        TRANSLATABLE_FIELDS.contains(&field)
+    };
+    if !known {
+        let fields = if locale == Locale::default() { CONSULTANT_FIELDS.join(", ") } else { TRANSLATABLE_FIELDS.join(", ") };
+        bot.send_message(chat_id, tr!(lang, "admin.unknown_field", field = field, fields = fields)).await?;
+        return Ok(());
+    }
+
+    let mut assistant = match AIAssistant::find_for_admin(state, id).await {
+        Ok(Some((assistant, _))) => assistant,
+        Ok(None) => {
+            bot.send_message(chat_id, tr!(lang, "admin.consultant_not_found", id = id)).await?;
+            return Ok(());
+        }
+        Err(e) => return send_db_error(bot, chat_id, e, lang).await,
+    };
+
+    // Переводы хранятся отдельно от основного профиля
+    if locale != Locale::default() {
+        let mut translation = match ConsultantTranslation::find(state, id, locale).await {
+            Ok(translation) => translation,
+            Err(e) => return send_db_error(bot, chat_id, e, lang).await,
+        };
+
+        let new_value = (value != "-").then(|| value.to_string());
+        match field {
+            "name" => translation.name = new_value,
+            "description" => translation.description = new_value,
+            "specialty" => translation.specialty = new_value,
+            "greeting" => translation.greeting = new_value,
+            _ => translation.prompt = new_value,
+        }
+
+        if let Err(e) = translation.save(state).await {
+            return send_db_error(bot, chat_id, e, lang).await;
+        }
+    } else {
+        let invalid = match field {
+            "name" => { assistant.name = value.to_string(); false }
+            "description" => { assistant.description = value.to_string(); false }
+            "specialty" => { assistant.specialty = value.to_string(); false }
+            "greeting" => { assistant.greeting = value.to_string(); false }
+            "prompt" => { assistant.prompt = value.to_string(); false }
+            "model" => { assistant.model = value.to_string(); false }
+            "billing" => match value {
+                "per_minute" | "per_message" => { assistant.billing_mode = BillingMode::from(value.to_string()); false }
+                _ => true,
+            },
+            // Цены вводятся в Stars, как их видят пользователи
+            "minute_price" | "message_price" => match value.replace(',', ".").parse::<f64>() {
+                Ok(stars) if stars > 0.0 => {
+                    if field == "minute_price" {
+                        assistant.price_per_minute = stars / 100.0;
+                    } else {
+                        assistant.price_per_message = stars / 100.0;
+                    }
+                    false
+                }
+                _ => true,
+            },
+            _ => true,
+        };
+
+        if invalid {
+            bot.send_message(chat_id, tr!(lang, "admin.invalid_value", field = field, value = value)).await?;
+            return Ok(());
+        }
+
+        if let Err(e) = AIAssistant::update_assistant(state, &assistant).await {
+            tracing::error!(consultant_id = id, error = %e, "could not update consultant");
+            bot.send_message(chat_id, tr!(lang, "admin.db_error")).await?;
+            return Ok(());
+        }
+    }
+
+    tracing::info!(admin_id = %chat_id, assistant_id = id, field = %field, locale = locale.code(), "consultant changed");
+    bot.send_message(chat_id, tr!(lang, "admin.consultant_updated", id = id, field = field, locale = locale.code()))
+        .await?;
+    Ok(())
+}
+
+async fn set_consultant_active(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    args: &str,
+    active: bool,
+    lang: Locale,
+) -> HandlerResult {
+    let command = if active { "activateconsultant" } else { "deactivateconsultant" };
+    let Some([id]) = parse_numbers::<i32, 1>(args) else {
+        return send_usage(bot, chat_id, command, lang).await;
+    };
+
+    match AIAssistant::find_for_admin(state, id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            bot.send_message(chat_id, tr!(lang, "admin.consultant_not_found", id = id)).await?;
+            return Ok(());
+        }
+        Err(e) => return send_db_error(bot, chat_id, e, lang).await,
+    }
+
+    let result = if active {
+        AIAssistant::activate_assistant(state, id).await
+    } else {
+        AIAssistant::deactivate_assistant(state, id).await
+    };
+
+    if let Err(e) = result {
+        tracing::error!(consultant_id = id, error = %e, "could not change consultant activity");
+        bot.send_message(chat_id, tr!(lang, "admin.db_error")).await?;
+        return Ok(());
+    }
+
+    tracing::info!(admin_id = %chat_id, assistant_id = id, active, "consultant activity changed");
+    let key = if active { "admin.consultant_activated" } else { "admin.consultant_deactivated" };
+    bot.send_message(chat_id, tr!(lang, key, id = id)).await?;
+    Ok(())
+}
+
+async fn delete_translation(bot: &Bot, state: &BotState, chat_id: ChatId, args: &str, lang: Locale) -> HandlerResult {
+    let (id, code) = split_first(args);
+    let (Ok(id), Some(locale)) = (id.parse::<i32>(), Locale::from_code(code)) else {
+        return send_usage(bot, chat_id, "deletetranslation", lang).await;
+    };
+
+    match ConsultantTranslation::delete(state, id, locale).await {
+        Ok(true) => {
+            tracing::info!(admin_id = %chat_id, assistant_id = id, locale = locale.code(), "consultant translation deleted");
+            bot.send_message(chat_id, tr!(lang, "admin.translation_deleted", id = id, locale = locale.code()))
+                .await?;
+        }
+        Ok(false) => {
+            bot.send_message(chat_id, tr!(lang, "admin.translation_not_found", id = id, locale = locale.code()))
+                .await?;
+        }
+        Err(e) => send_db_error(bot, chat_id, e, lang).await?,
+    }
+    Ok(())
+}
+
+async fn list_slots(bot: &Bot, state: &BotState, chat_id: ChatId, lang: Locale) -> HandlerResult {
+    let slots = match TimeSlot::get_all_slots(state).await {
+        Ok(slots) => slots,
+        Err(e) => return send_db_error(bot, chat_id, e, lang).await,
+    };
+
+    if slots.is_empty() {
+        bot.send_message(chat_id, tr!(lang, "admin.slots_empty")).await?;
+        return Ok(());
+    }
+
+    let lines: Vec<String> = slots
+        .iter()
+        .map(|slot| {
+            tr!(
+                lang,
+                "admin.slot_line",
+                id = slot.id,
+                minutes = slot.duration_minutes,
+                description = slot.description,
+                order = slot.sort_order,
+                status = status_text(slot.is_active, lang),
+            )
+        })
+        .collect();
+
+    bot.send_message(chat_id, format!("{}\n\n{}", tr!(lang, "admin.slots_title"), lines.join("\n")))
+        .await?;
+    Ok(())
+}
+
+async fn add_slot(bot: &Bot, state: &BotState, chat_id: ChatId, args: &str, lang: Locale) -> HandlerResult {
+    let (minutes, description) = split_first(args);
+    let Ok(minutes) = minutes.parse::<i32>() else {
+        return send_usage(bot, chat_id, "addslot", lang).await;
+    };
+    if minutes <= 0 || description.is_empty() {
+        return send_usage(bot, chat_id, "addslot", lang).await;
+    }
+
+    match TimeSlot::create(state, minutes, description).await {
+        Ok(id) => {
+            tracing::info!(admin_id = %chat_id, slot_id = id, minutes, "time slot added");
+            bot.send_message(chat_id, tr!(lang, "admin.slot_created", id = id)).await?;
+        }
+        Err(e) => send_db_error(bot, chat_id, e, lang).await?,
+    }
+    Ok(())
+}
+
+async fn edit_slot(bot: &Bot, state: &BotState, chat_id: ChatId, args: &str, lang: Locale) -> HandlerResult {
+    let (id, rest) = split_first(args);
+    let (field, value) = split_first(rest);
+    let Ok(id) = id.parse::<i32>() else {
+        return send_usage(bot, chat_id, "editslot", lang).await;
+    };
+    if value.is_empty() {
+        return send_usage(bot, chat_id, "editslot", lang).await;
+    }
+
+    if !SLOT_FIELDS.contains(&field) {
+        bot.send_message(chat_id, tr!(lang, "admin.unknown_field", field = field, fields = SLOT_FIELDS.join(", ")))
+            .await?;
+        return Ok(());
+    }
+
+    let mut slot = match TimeSlot::find_by_id(state, id).await {
+        Ok(Some(slot)) => slot,
+        Ok(None) => {
+            bot.send_message(chat_id, tr!(lang, "admin.slot_not_found", id = id)).await?;
+            return Ok(());
+        }
+        Err(e) => return send_db_error(bot, chat_id, e, lang).await,
+    };
+
+    let valid = match field {
+        "minutes" => value.parse::<i32>().ok().filter(|m| *m > 0).map(|m| slot.duration_minutes = m).is_some(),
+        "description" => { slot.description = value.to_string(); true }
+        "order" => value.parse::<i32>().map(|order| slot.sort_order = order).is_ok(),
+        _ => match value {
+            "on" | "yes" | "1" => { slot.is_active = true; true }
+            "off" | "no" | "0" => { slot.is_active = false; true }
+            _ => false,
+        },
+    };
+
+    if !valid {
+        bot.send_message(chat_id, tr!(lang, "admin.invalid_value", field = field, value = value)).await?;
+        return Ok(());
+    }
+
+    if let Err(e) = slot.save(state).await {
+        return send_db_error(bot, chat_id, e, lang).await;
+    }
+
+    tracing::info!(admin_id = %chat_id, slot_id = id, field = %field, "time slot changed");
+    bot.send_message(chat_id, tr!(lang, "admin.slot_updated", id = id, field = field)).await?;
+    Ok(())
+}
+
+async fn show_user_bookings(bot: &Bot, state: &BotState, chat_id: ChatId, args: &str, lang: Locale) -> HandlerResult {
+    let Some([user_id]) = parse_numbers::<i64, 1>(args) else {
+        return send_usage(bot, chat_id, "bookings", lang).await;
+    };
+    let user_id = ChatId(user_id);
+
+    let bookings = match state.get_user_bookings(user_id).await {
+        Ok(bookings) => bookings,
+        Err(e) => {
+            tracing::error!(user_id = %user_id, error = %e, "could not fetch user bookings");
+            bot.send_message(chat_id, tr!(lang, "admin.db_error")).await?;
+            return Ok(());
+        }
+    };
+
+    let consultants = AIAssistant::list_for_admin(state).await.unwrap_or_else(|e| {
+        tracing::error!(error = %e, "could not fetch consultants");
+        Vec::new()
+    });
+    let user_state = state.get_user_state(user_id).await?;
+
+    let mut text = tr!(
+        lang,
+        "admin.bookings_title",
+        chat_id = user_id,
+        balance = format_stars(user_state.balance),
+    );
+    text.push_str("\n\n");
+
+    if bookings.is_empty() {
+        text.push_str(&tr!(lang, "admin.bookings_empty"));
+    }
+
+    for booking in &bookings {
+        let name = consultants
+            .iter()
+            .find(|(a, _)| a.id == booking.assistant_id)
+            .map(|(a, _)| a.name.clone())
+            .unwrap_or_else(|| format!("#{}", booking.assistant_id));
+
+        let volume = match booking.message_quota {
+            Some(quota) => tr!(lang, "units.messages_short", count = quota),
+            None => tr!(lang, "units.minutes", count = booking.duration_minutes),
+        };
+
+        text.push_str(&tr!(
+            lang,
+            "admin.booking_line",
+            created = booking.created_at.format("%d.%m.%Y %H:%M"),
+            name = name,
+            volume = volume,
+            stars = format_stars(booking.total_price),
+            status = tr!(lang, booking.status_key()),
+            id = booking.id,
+        ));
+        text.push('\n');
+    }
+
+    bot.send_message(chat_id, text).await?;
+    Ok(())
+}
+
+async fn grant_minutes(bot: &Bot, state: &BotState, chat_id: ChatId, args: &str, lang: Locale) -> HandlerResult {
+    let Some([user_id, assistant_id, minutes]) = parse_numbers::<i64, 3>(args) else {
+        return send_usage(bot, chat_id, "grant", lang).await;
+    };
+    if minutes <= 0 || minutes > i32::MAX as i64 {
+        return send_usage(bot, chat_id, "grant", lang).await;
+    }
+    let user_id = ChatId(user_id);
+
+    let Some(assistant) = AIAssistant::find_by_id_with_price(state, assistant_id as i32).await else {
+        bot.send_message(chat_id, tr!(lang, "admin.consultant_not_found", id = assistant_id)).await?;
+        return Ok(());
+    };
+
+    if assistant.is_per_message() {
+        bot.send_message(chat_id, tr!(lang, "admin.grant_per_message", name = assistant.name)).await?;
+        return Ok(());
+    }
+
+    let booking = match admin::grant_minutes(bot, state, user_id, &assistant, minutes as u32).await {
+        Ok(booking) => booking,
+        Err(e) => {
+            tracing::error!(user_id = %user_id, error = %e, "could not save granted booking");
+            bot.send_message(chat_id, tr!(lang, "admin.db_error")).await?;
+            return Ok(());
+        }
+    };
+
+    tracing::info!(admin_id = %chat_id, chat_id = %user_id, assistant_id = assistant.id, minutes, "minutes granted");
+
+    bot.send_message(
+        chat_id,
+        tr!(lang, "admin.granted", id = booking.id, minutes = minutes, name = assistant.name, chat_id = user_id),
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn refund(bot: &Bot, state: &BotState, chat_id: ChatId, args: &str, lang: Locale) -> HandlerResult {
+    let booking_id = args.trim();
+    if booking_id.is_empty() || booking_id.contains(char::is_whitespace) {
+        return send_usage(bot, chat_id, "refund", lang).await;
+    }
+
+    let mut booking = match state.get_booking_by_id(booking_id).await {
+        Ok(Some(booking)) => booking,
+        Ok(None) => {
+            bot.send_message(chat_id, tr!(lang, "admin.booking_not_found", id = booking_id)).await?;
+            return Ok(());
+        }
+        Err(e) => {
+            tracing::error!(booking_id = %booking_id, error = %e, "could not find booking");
+            bot.send_message(chat_id, tr!(lang, "admin.db_error")).await?;
+            return Ok(());
+        }
+    };
+
+    if !booking.is_paid || booking.is_refunded {
+        bot.send_message(chat_id, tr!(lang, "admin.cannot_refund", id = booking.id)).await?;
+        return Ok(());
+    }
+
+    if let Err(e) = admin::refund(bot, state, &mut booking).await {
+        tracing::error!(booking_id = %booking.id, error = %e, "could not refund booking");
+        bot.send_message(chat_id, tr!(lang, "admin.refund_failed", error = e)).await?;
+        return Ok(());
+    }
+
+    tracing::info!(admin_id = %chat_id, chat_id = %booking.user_id, booking_id = %booking.id, "booking refunded by admin");
+
+    bot.send_message(
+        chat_id,
+        tr!(
+            lang,
+            "admin.refunded",
+            id = booking.id,
+            stars = format_stars(booking.amount_due()),
+            credit = format_stars(booking.credit_applied),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn list_admins(bot: &Bot, state: &BotState, chat_id: ChatId, lang: Locale) -> HandlerResult {
+    let admins = match admin::list_admins(state).await {
+        Ok(admins) => admins,
+        Err(e) => return send_db_error(bot, chat_id, e, lang).await,
+    };
+
+    let lines: Vec<String> = admins
+        .iter()
+        .map(|id| {
+            if admin::is_configured_admin(ChatId(*id)) {
+                format!("{} {}", id, tr!(lang, "admin.admin_configured"))
+            } else {
+                id.to_string()
+            }
+        })
+        .collect();
+
+    bot.send_message(chat_id, format!("{}\n\n{}", tr!(lang, "admin.admins_title"), lines.join("\n")))
+        .await?;
+    Ok(())
+}
+
+async fn add_admin(bot: &Bot, state: &BotState, chat_id: ChatId, args: &str, lang: Locale) -> HandlerResult {
+    let Some([new_admin]) = parse_numbers::<i64, 1>(args) else {
+        return send_usage(bot, chat_id, "addadmin", lang).await;
+    };
+    let new_admin = ChatId(new_admin);
+
+    if admin::is_admin(state, new_admin).await {
+        bot.send_message(chat_id, tr!(lang, "admin.admin_exists", chat_id = new_admin)).await?;
+        return Ok(());
+    }
+
+    match admin::add_admin(state, new_admin, chat_id).await {
+        Ok(_) => {
+            tracing::info!(admin_id = %chat_id, new_admin = %new_admin, "admin role granted");
+            bot.send_message(chat_id, tr!(lang, "admin.admin_added", chat_id = new_admin)).await?;
+        }
+        Err(e) => send_db_error(bot, chat_id, e, lang).await?,
+    }
+    Ok(())
+}
+
+async fn remove_admin(bot: &Bot, state: &BotState, chat_id: ChatId, args: &str, lang: Locale) -> HandlerResult {
+    let Some([removed]) = parse_numbers::<i64, 1>(args) else {
+        return send_usage(bot, chat_id, "removeadmin", lang).await;
+    };
+    let removed = ChatId(removed);
+
+    if admin::is_configured_admin(removed) {
+        bot.send_message(chat_id, tr!(lang, "admin.admin_configured_cannot_remove", chat_id = removed)).await?;
+        return Ok(());
+    }
+
+    match admin::remove_admin(state, removed).await {
+        Ok(true) => {
+            tracing::info!(admin_id = %chat_id, removed_admin = %removed, "admin role revoked");
+            bot.send_message(chat_id, tr!(lang, "admin.admin_removed", chat_id = removed)).await?;
+        }
+        Ok(false) => {
+            bot.send_message(chat_id, tr!(lang, "admin.admin_not_found", chat_id = removed)).await?;
+        }
+        Err(e) => send_db_error(bot, chat_id, e, lang).await?,
+    }
+    Ok(())
+}
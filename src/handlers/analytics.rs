@@ -0,0 +1,138 @@
+use teloxide::prelude::*;
+use teloxide::types::InputFile;
+use std::error::Error;
+use chrono::Utc;
+
+use crate::analytics::{self, Report, DEFAULT_DAYS, MAX_DAYS};
+use crate::bot_state::BotState;
+use crate::i18n::Locale;
+use crate::tr;
+use super::admin::{send_db_error, send_usage, HandlerResult};
+
+/// Период отчета: без аргумента — `DEFAULT_DAYS`
+fn parse_days(args: &str) -> Option<i32> {
+    let args = args.trim();
+    if args.is_empty() {
+        return Some(DEFAULT_DAYS);
+    }
+    args.parse().ok().filter(|days| (1..=MAX_DAYS).contains(days))
+}
+
+async fn load_report(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    args: &str,
+    command: &str,
+    lang: Locale,
+) -> Result<Option<Report>, Box<dyn Error + Send + Sync>> {
+    let Some(days) = parse_days(args) else {
+        send_usage(bot, chat_id, command, lang).await?;
+        return Ok(None);
+    };
+
+    match analytics::build_report(state, days).await {
+        Ok(report) => Ok(Some(report)),
+        Err(e) => {
+            send_db_error(bot, chat_id, e, lang).await?;
+            Ok(None)
+        }
+    }
+}
+
+fn render(report: &Report, lang: Locale) -> String {
+    let mut text = tr!(
+        lang,
+        "analytics.summary",
+        days = report.days,
+        revenue = report.revenue(),
+        refunded = report.refunded(),
+        created = report.bookings_created(),
+        paid = report.bookings_paid(),
+        abandoned = report.bookings_abandoned(),
+        conversion = format!("{:.1}", report.conversion_percent()),
+        sessions = report.sessions.sessions,
+        minutes = format!("{:.1}", report.sessions.avg_minutes),
+        messages = format!("{:.1}", report.sessions.avg_messages),
+    );
+
+    text.push_str("\n\n");
+    text.push_str(&tr!(lang, "analytics.consultants_title"));
+    for c in &report.consultants {
+        let rating = c.avg_rating
+            .map(|r| format!("{:.1}", r))
+            .unwrap_or_else(|| tr!(lang, "admin.no_rating"));
+        text.push('\n');
+        text.push_str(&tr!(
+            lang,
+            "analytics.consultant_line",
+            name = c.name,
+            revenue = c.revenue,
+            paid = c.paid_bookings,
+            sessions = c.sessions,
+            minutes = format!("{:.1}", c.avg_minutes),
+            rating = rating,
+        ));
+    }
+
+    text.push_str("\n\n");
+    text.push_str(&tr!(lang, "analytics.cohorts_title"));
+    if report.cohorts.is_empty() {
+        text.push('\n');
+        text.push_str(&tr!(lang, "analytics.cohorts_empty"));
+    }
+    for cohort in &report.cohorts {
+        let retention: Vec<String> = (0..cohort.retained.len())
+            .filter_map(|week| cohort.retention_percent(week).map(|p| format!("{}: {:.0}%", week + 1, p)))
+            .collect();
+        let retention = if retention.is_empty() { "—".to_string() } else { retention.join(" · ") };
+
+        text.push('\n');
+        text.push_str(&tr!(
+            lang,
+            "analytics.cohort_line",
+            week = cohort.week.format("%d.%m.%Y"),
+            users = cohort.users,
+            retention = retention,
+        ));
+    }
+
+    text
+}
+
+/// `/analytics [дней]`: сводка по выручке, оплатам, сессиям, консультантам и когортам
+pub async fn show_analytics(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    args: &str,
+    lang: Locale,
+) -> HandlerResult {
+    if let Some(report) = load_report(bot, state, chat_id, args, "analytics", lang).await? {
+        bot.send_message(chat_id, render(&report, lang)).await?;
+    }
+    Ok(())
+}
+
+/// `/analyticscsv [дней]`: те же показатели таблицами CSV в ZIP-архиве
+pub async fn export_analytics(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    args: &str,
+    lang: Locale,
+) -> HandlerResult {
+    let Some(report) = load_report(bot, state, chat_id, args, "analyticscsv", lang).await? else {
+        return Ok(());
+    };
+
+    let document = InputFile::memory(analytics::csv::archive(&report)?)
+        .file_name(format!("analytics_{}d_{}.zip", report.days, Utc::now().format("%Y-%m-%d")));
+
+    bot.send_document(chat_id, document)
+        .caption(tr!(lang, "analytics.csv_caption", days = report.days))
+        .await?;
+    tracing::info!(admin_id = %chat_id, days = report.days, "analytics exported");
+
+    Ok(())
+}
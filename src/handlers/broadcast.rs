@@ -0,0 +1,190 @@
+use teloxide::prelude::*;
+use teloxide::types::{ChatMemberUpdated, InlineKeyboardButton, InlineKeyboardMarkup, MessageId};
+
+use crate::admin;
+use crate::bot_state::BotState;
+use crate::broadcast::{self, Audience, Broadcast};
+use crate::i18n::Locale;
+use crate::tr;
+use super::admin::{send_db_error, send_usage, split_first, HandlerResult};
+
+/// `/broadcast <аудитория> <текст>`: создает черновик и просит подтвердить отправку
+pub async fn create_broadcast(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    args: &str,
+    lang: Locale,
+) -> HandlerResult {
+    let (audience, text) = split_first(args);
+    if audience.is_empty() || text.is_empty() {
+        return send_usage(bot, chat_id, "broadcast", lang).await;
+    }
+
+    let audience = match audience.parse::<Audience>() {
+        Ok(audience) => audience,
+        Err(e) => {
+            bot.send_message(chat_id, tr!(lang, "broadcast.invalid_audience", error = e)).await?;
+            return Ok(());
+        }
+    };
+
+    let broadcast = match Broadcast::create(state, chat_id, audience, text).await {
+        Ok(broadcast) => broadcast,
+        Err(e) => return send_db_error(bot, chat_id, e, lang).await,
+    };
+
+    tracing::info!(admin_id = %chat_id, broadcast_id = broadcast.id, audience = %audience, recipients = broadcast.total, "broadcast drafted");
+
+    if broadcast.total == 0 {
+        let _ = Broadcast::cancel(state, broadcast.id).await;
+        bot.send_message(chat_id, tr!(lang, "broadcast.no_recipients", audience = audience)).await?;
+        return Ok(());
+    }
+
+    // Показываем сам текст, чтобы администратор видел, что именно уйдет пользователям
+    bot.send_message(chat_id, text).await?;
+
+    let keyboard = InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(tr!(lang, "broadcast.confirm_button"), format!("bcast_send_{}", broadcast.id)),
+        InlineKeyboardButton::callback(tr!(lang, "broadcast.cancel_button"), format!("bcast_cancel_{}", broadcast.id)),
+    ]]);
+
+    bot.send_message(
+        chat_id,
+        tr!(lang, "broadcast.confirm", id = broadcast.id, audience = audience, total = broadcast.total),
+    )
+    .reply_markup(keyboard)
+    .await?;
+
+    Ok(())
+}
+
+/// Последние рассылки с ходом отправки
+pub async fn list_broadcasts(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    lang: Locale,
+) -> HandlerResult {
+    let broadcasts = match Broadcast::list_recent(state, 10).await {
+        Ok(broadcasts) => broadcasts,
+        Err(e) => return send_db_error(bot, chat_id, e, lang).await,
+    };
+
+    if broadcasts.is_empty() {
+        bot.send_message(chat_id, tr!(lang, "broadcast.list_empty")).await?;
+        return Ok(());
+    }
+
+    let lines: Vec<String> = broadcasts
+        .iter()
+        .map(|b| {
+            tr!(
+                lang,
+                "broadcast.list_line",
+                id = b.id,
+                created = b.created_at.format("%d.%m.%Y %H:%M"),
+                audience = b.audience,
+                status = tr!(lang, &format!("broadcast.status_{}", b.status.as_str())),
+                processed = b.processed(),
+                total = b.total,
+            )
+        })
+        .collect();
+
+    bot.send_message(chat_id, format!("{}\n\n{}", tr!(lang, "broadcast.list_title"), lines.join("\n")))
+        .await?;
+    Ok(())
+}
+
+/// `/cancelbroadcast <id>`
+pub async fn cancel_broadcast(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    args: &str,
+    lang: Locale,
+) -> HandlerResult {
+    let Ok(id) = args.trim().parse::<i32>() else {
+        return send_usage(bot, chat_id, "cancelbroadcast", lang).await;
+    };
+
+    match Broadcast::cancel(state, id).await {
+        Ok(true) => {
+            tracing::info!(admin_id = %chat_id, broadcast_id = id, "broadcast cancelled");
+            bot.send_message(chat_id, tr!(lang, "broadcast.cancelled", id = id)).await?;
+        }
+        Ok(false) => {
+            bot.send_message(chat_id, tr!(lang, "broadcast.cannot_cancel", id = id)).await?;
+        }
+        Err(e) => send_db_error(bot, chat_id, e, lang).await?,
+    }
+    Ok(())
+}
+
+/// Кнопки подтверждения черновика: "bcast_send_<id>" и "bcast_cancel_<id>"
+pub async fn handle_broadcast_callback(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    message_id: MessageId,
+    data: &str,
+    lang: Locale,
+) -> HandlerResult {
+    if !admin::is_admin(state, chat_id).await {
+        return Ok(());
+    }
+
+    let (send, id) = match (data.strip_prefix("bcast_send_"), data.strip_prefix("bcast_cancel_")) {
+        (Some(id), _) => (true, id),
+        (_, Some(id)) => (false, id),
+        _ => return Ok(()),
+    };
+    let Ok(id) = id.parse::<i32>() else {
+        return Ok(());
+    };
+
+    if !send {
+        if let Err(e) = Broadcast::cancel(state, id).await {
+            tracing::error!(broadcast_id = id, error = %e, "could not cancel broadcast");
+        }
+        bot.edit_message_text(chat_id, message_id, tr!(lang, "broadcast.cancelled", id = id)).await?;
+        return Ok(());
+    }
+
+    match Broadcast::enqueue(state, id, message_id).await {
+        Ok(true) => {
+            tracing::info!(admin_id = %chat_id, broadcast_id = id, "broadcast queued");
+            if let Ok(Some(broadcast)) = Broadcast::find(state, id).await {
+                bot.edit_message_text(chat_id, message_id, broadcast::progress_text(state, &broadcast).await)
+                    .await?;
+            }
+        }
+        Ok(false) => {
+            bot.edit_message_text(chat_id, message_id, tr!(lang, "broadcast.already_handled", id = id)).await?;
+        }
+        Err(e) => send_db_error(bot, chat_id, e, lang).await?,
+    }
+    Ok(())
+}
+
+/// Telegram сообщает, когда пользователь блокирует бота или снова его запускает
+pub async fn chat_member_handler(
+    update: ChatMemberUpdated,
+    state: BotState,
+) -> HandlerResult {
+    if !update.chat.is_private() {
+        return Ok(());
+    }
+
+    let blocked = update.new_chat_member.is_banned();
+    let chat_id = update.chat.id;
+
+    if let Err(e) = broadcast::set_user_blocked(&state, chat_id, blocked).await {
+        tracing::error!(chat_id = %chat_id, blocked, error = %e, "could not save blocked status");
+    }
+
+    tracing::info!(chat_id = %chat_id, blocked, "bot membership changed");
+    Ok(())
+}
@@ -1,16 +1,30 @@
 use teloxide::prelude::*;
-use teloxide::types::ParseMode;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId, ParseMode};
 use std::error::Error;
 use uuid::Uuid;
 use chrono::{Utc, Duration};
 
 use crate::bot_state::BotState;
-use crate::models::{AIAssistant, PaymentConfig, Booking, TimeSlot};
-use crate::handlers::payments::send_stars_invoice;
+use crate::i18n::{self, Locale};
+use crate::tr;
+use crate::settings;
+use crate::models::{AIAssistant, PaymentConfig, Booking, MessageBundle, SessionRating, TimeSlot};
+use crate::handlers::privacy::{handle_delete_me_callback, handle_keep_history_callback};
+use crate::handlers::payments::{activate_booking, refund_booking, send_stars_invoice, start_or_keep_booking};
+use crate::handlers::feedback::{handle_feedback_skip, handle_rating_callback};
+use crate::handlers::broadcast::handle_broadcast_callback;
+use crate::handlers::export::handle_export_callback;
+use crate::handlers::goals::handle_goal_callback;
+use crate::handlers::language::handle_language_callback;
+use crate::handlers::memories::handle_memory_callback;
+use crate::handlers::mood::handle_mood_callback;
+use crate::handlers::sessions::{
+    ask_end_session, ask_switch_consultant, end_session, switch_consultant, Settlement
+};
 use crate::handlers::utils::{
     escape_markdown_v2, make_ai_keyboard, 
     make_consultants_info_keyboard, format_consultant_info, make_back_to_consultants_keyboard,
-    make_time_slots_keyboard, send_ai_message
+    make_purchase_keyboard, format_assistant_price, make_unstarted_booking_keyboard, send_ai_message
 };
 
 pub async fn callback_handler(
@@ -23,77 +37,112 @@ pub async fn callback_handler(
         if let Some(ref message) = q.message {
             let chat_id = message.chat().id;
             let message_id = message.id();
+            let lang = i18n::locale_for(&state, chat_id, Some(&q.from)).await;
 
             match data {
                 data if data.starts_with("select_ai_") => {
                     let id_str = data.strip_prefix("select_ai_").unwrap();
                     if let Ok(id) = id_str.parse::<i32>() {
-                        let assistant = AIAssistant::find_by_id_with_price(&state, id).await
-                            .unwrap_or_else(|| {
-                                AIAssistant {
-                                    id: 1,
-                                    name: "Анна".to_string(),
-                                    model: "GigaChat-2-Max".to_string(),
-                                    description: "Интерактивный помощник".to_string(),
-                                    specialty: "Общение и поддержка".to_string(),
-                                    greeting: "Здравствуйте!".to_string(),
-                                    prompt: "Ты помощник.".to_string(),
-                                    price_per_minute: 0.1,
-                                }
-                            });
+                        let Some(assistant) = AIAssistant::find_localized(&state, id, lang).await else {
+                            bot.send_message(chat_id, tr!(lang, "consultants.not_found")).await?;
+                            return Ok(());
+                        };
                         
-                        let mut user_state = state.get_user_state(chat_id).await;
+                        let mut user_state = state.get_user_state(chat_id).await?;
+
+                        // Во время активной сессии выбор другого консультанта — это смена с пересчетом
+                        if let Some(session) = user_state.current_session.as_ref()
+                            .filter(|s| s.can_chat() && s.assistant_id != assistant.id)
+                        {
+                            ask_switch_consultant(&bot, chat_id, session, &assistant, lang).await?;
+                            return Ok(());
+                        }
+
                         user_state.current_assistant_id = assistant.id; // Сохраняем ID
                         
                         // Сохраняем выбор консультанта
                         if let Err(e) = state.save_user_state(chat_id, user_state).await {
-                            log::error!("Error saving user state: {}", e);
+                            tracing::error!(chat_id = %chat_id, error = %e, "could not save user state");
                         }
 
-                        // Показываем выбор времени сессии
+                        let purchase_prompt = if assistant.is_per_message() {
+                            tr!(lang, "consultants.choose_bundle")
+                        } else {
+                            tr!(lang, "consultants.choose_duration")
+                        };
+
+                        // Показываем выбор времени сессии или пакета сообщений
                         bot.edit_message_text(
                             chat_id,
                             message_id,
-                            format!(
-                                "✅ *Вы выбрали:* {}\n\n*Стиль общения:* {}\n*Цена:* {} Stars/мин\n\n{}\
-                                \n\nВыберите продолжительность сессии:",
-                                escape_markdown_v2(&assistant.name),
-                                escape_markdown_v2(&assistant.specialty),
-                                (assistant.price_per_minute * 100.0) as i32,
-                                escape_markdown_v2(&assistant.greeting)
+                            tr!(
+                                lang,
+                                "consultants.selected",
+                                name = escape_markdown_v2(&assistant.name),
+                                specialty = escape_markdown_v2(&assistant.specialty),
+                                price = escape_markdown_v2(&format_assistant_price(&assistant, lang)),
+                                greeting = escape_markdown_v2(&assistant.greeting),
+                                prompt = purchase_prompt,
                             ),
                         )
                         .parse_mode(ParseMode::MarkdownV2)
-                        .reply_markup(make_time_slots_keyboard(&state, &assistant).await)
+                        .reply_markup(make_purchase_keyboard(&state, &assistant, lang).await)
                         .await?;
                     }
                 }
 
+                data if data.starts_with("switch_ai_") => {
+                    let id_str = data.strip_prefix("switch_ai_").unwrap();
+                    if let Ok(id) = id_str.parse::<i32>() {
+                        match AIAssistant::find_localized(&state, id, lang).await {
+                            Some(assistant) => {
+                                let _ = bot.delete_message(chat_id, message_id).await;
+                                switch_consultant(&bot, &state, chat_id, &assistant, lang).await?;
+                            }
+                            None => {
+                                bot.send_message(chat_id, tr!(lang, "consultants.not_found")).await?;
+                            }
+                        }
+                    }
+                }
+
                 // Обработчик информации о консультанте
                 data if data.starts_with("consultant_info_") => {
                     let id_str = data.strip_prefix("consultant_info_").unwrap();
                     if let Ok(id) = id_str.parse::<i32>() {
-                        let assistant = AIAssistant::find_by_id_with_price(&state, id).await
-                            .unwrap_or_else(|| {
-                                AIAssistant {
-                                    id: 1,
-                                    name: "Анна".to_string(),
-                                    model: "GigaChat-2-Max".to_string(),
-                                    description: "Интерактивный помощник".to_string(),
-                                    specialty: "Общение и поддержка".to_string(),
-                                    greeting: "Здравствуйте!".to_string(),
-                                    prompt: "Ты помощник.".to_string(),
-                                    price_per_minute: 0.1,
-                                }
-                            });
+                        let Some(assistant) = AIAssistant::find_localized(&state, id, lang).await else {
+                            bot.send_message(chat_id, tr!(lang, "consultants.not_found")).await?;
+                            return Ok(());
+                        };
                         
+                        let mut info = format_consultant_info(&assistant, lang);
+
+                        // Несколько последних отзывов с текстом
+                        let reviews: Vec<String> = SessionRating::recent_for_assistant(&state, assistant.id, 10).await
+                            .into_iter()
+                            .filter_map(|r| r.comment.map(|c| (r.rating, c)))
+                            .take(3)
+                            .map(|(rating, comment)| format!(
+                                "{} _{}_",
+                                "⭐".repeat(rating.clamp(1, 5) as usize),
+                                escape_markdown_v2(&comment)
+                            ))
+                            .collect();
+
+                        if !reviews.is_empty() {
+                            info.push_str("\n\n");
+                            info.push_str(&tr!(lang, "consultants.reviews"));
+                            info.push('\n');
+                            info.push_str(&reviews.join("\n"));
+                        }
+
                         bot.edit_message_text(
                             chat_id,
                             message_id,
-                            format_consultant_info(&assistant),
+                            info,
                         )
                         .parse_mode(ParseMode::MarkdownV2)
-                        .reply_markup(make_back_to_consultants_keyboard())
+                        .reply_markup(make_back_to_consultants_keyboard(lang))
                         .await?;
                     }
                 }
@@ -103,35 +152,23 @@ pub async fn callback_handler(
                     bot.edit_message_text(
                         chat_id,
                         message_id,
-                        "👥 *Список консультантов*\n\n\
-Выберите консультанта чтобы увидеть подробную информацию:\n\n\
-Каждый консультант — это стиль общения ИИ с разным характером и ценой\\.\n\
-Это не психологи и не специалисты\\.",
+                        tr!(lang, "consultants.list"),
                     )
                     .parse_mode(ParseMode::MarkdownV2)
-                    .reply_markup(make_consultants_info_keyboard(&state).await)
+                    .reply_markup(make_consultants_info_keyboard(&state, lang).await)
                     .await?;
                 }
 
                 data if data.starts_with("time_slot_") => {
                     let slot_id = data.strip_prefix("time_slot_").unwrap().parse::<i32>().unwrap_or(0);
                     
-                    let user_state = state.get_user_state(chat_id).await;
+                    let user_state = state.get_user_state(chat_id).await?;
                     
                     // Находим консультанта по ID из текущего состояния
-                    let assistant = AIAssistant::find_by_id_with_price(&state, user_state.current_assistant_id).await
-                        .unwrap_or_else(|| {
-                            AIAssistant {
-                                id: 1,
-                                name: "Анна".to_string(),
-                                model: "GigaChat-2-Max".to_string(),
-                                description: "Интерактивный помощник".to_string(),
-                                specialty: "Общение и поддержка".to_string(),
-                                greeting: "Здравствуйте!".to_string(),
-                                prompt: "Ты помощник.".to_string(),
-                                price_per_minute: 0.1,
-                            }
-                        });
+                    let Some(assistant) = AIAssistant::find_localized(&state, user_state.current_assistant_id, lang).await else {
+                        bot.send_message(chat_id, tr!(lang, "consultants.not_found")).await?;
+                        return Ok(());
+                    };
                 
                     let time_slots = TimeSlot::get_all_active_slots(&state).await;
                     let selected_slot = time_slots.iter().find(|slot| slot.id == slot_id)
@@ -139,58 +176,36 @@ pub async fn callback_handler(
                 
                     let duration_minutes = selected_slot.duration_minutes as u32;
                     let total_price = selected_slot.calculate_price(assistant.price_per_minute);
-                    
-                    let booking_id = Uuid::new_v4().to_string();
-                    let invoice_payload = Uuid::new_v4().to_string();
-                    
-                    let booking = Booking {
-                        id: booking_id.clone(),
-                        user_id: chat_id,
-                        assistant_id: assistant.id, // Сохраняем ID консультанта
-                        duration_minutes,
-                        total_price,
-                        invoice_payload: invoice_payload.clone(),
-                        is_paid: false,
-                        is_completed: false,
-                        created_at: Utc::now(),
-                        payment_invoice_message_id: None,
-                        expires_at: Some(Utc::now() + Duration::minutes(5)), // 5 минут на оплату
+
+                    create_booking_with_invoice(
+                        &bot, &state, &payment_config, chat_id, message_id,
+                        &assistant, duration_minutes, total_price, None, lang,
+                    ).await?;
+                }
+
+                data if data.starts_with("message_bundle_") => {
+                    let bundle_id = data.strip_prefix("message_bundle_").unwrap().parse::<i32>().unwrap_or(0);
+
+                    let user_state = state.get_user_state(chat_id).await?;
+
+                    let Some(assistant) = AIAssistant::find_localized(&state, user_state.current_assistant_id, lang).await else {
+                        bot.send_message(chat_id, tr!(lang, "consultants.not_found")).await?;
+                        return Ok(());
                     };
-                    
-                    // Сохраняем бронирование
-                    if let Err(e) = state.save_booking(&booking).await {
-                        log::error!("Error saving booking: {}", e);
-                        bot.send_message(chat_id, "⚠️ Ошибка при создании сессии. Попробуйте еще раз.")
-                            .await?;
+
+                    let bundles = MessageBundle::get_all_active_bundles(&state).await;
+                    let Some(selected_bundle) = bundles.iter().find(|bundle| bundle.id == bundle_id).or(bundles.first()) else {
+                        bot.send_message(chat_id, tr!(lang, "consultants.no_bundles")).await?;
                         return Ok(());
-                    }
-                    
-                    log::info!("Booking created: {:?}", booking);
-
-                    match send_stars_invoice(&bot, chat_id, &booking, &assistant, &payment_config).await {
-                        Ok(invoice_message) => {
-                            let mut updated_booking = booking.clone();
-                            updated_booking.payment_invoice_message_id = Some(invoice_message.id);
-                            
-                            if let Err(e) = state.save_booking(&updated_booking).await {
-                                log::error!("Error updating booking with message ID: {}", e);
-                            }
-                            
-                            bot.delete_message(chat_id, message_id).await?;
-                            
-                            bot.send_message(
-                                chat_id,
-                                "⏰ *У вас есть 5 минут чтобы оплатить сессию*\n\nПосле истечения этого времени сессия будет автоматически отменена\\."
-                            )
-                            .parse_mode(ParseMode::MarkdownV2)
-                            .await?;
-                        }
-                        Err(e) => {
-                            log::error!("Failed to send invoice: {}", e);
-                            bot.send_message(chat_id, "⚠️ Ошибка при создании счета. Попробуйте еще раз.")
-                                .await?;
-                        }
-                    }
+                    };
+
+                    let message_quota = selected_bundle.messages_count as u32;
+                    let total_price = selected_bundle.calculate_price(assistant.price_per_message);
+
+                    create_booking_with_invoice(
+                        &bot, &state, &payment_config, chat_id, message_id,
+                        &assistant, 0, total_price, Some(message_quota), lang,
+                    ).await?;
                 }
 
                 // Обработчик возврата к выбору консультанта
@@ -198,10 +213,10 @@ pub async fn callback_handler(
                     bot.edit_message_text(
                         chat_id,
                         message_id,
-                        "👥 *Выберите консультанта:*\n\nКаждый консультант имеет свой стиль общения и индивидуальную цену\\.",
+                        tr!(lang, "consultants.choose"),
                     )
                     .parse_mode(ParseMode::MarkdownV2)
-                    .reply_markup(make_ai_keyboard(&state).await)
+                    .reply_markup(make_ai_keyboard(&state, lang).await)
                     .await?;
                 }
 
@@ -210,21 +225,21 @@ pub async fn callback_handler(
                     bot.edit_message_text(
                         chat_id,
                         message_id,
-                        "👥 *Выберите консультанта:*\n\nКаждый консультант имеет свой стиль общения и индивидуальную цену\\.",
+                        tr!(lang, "consultants.choose"),
                     )
                     .parse_mode(ParseMode::MarkdownV2)
-                    .reply_markup(make_ai_keyboard(&state).await)
+                    .reply_markup(make_ai_keyboard(&state, lang).await)
                     .await?;
                 }
 
                 "clear_history" => {
-                    let mut user_state = state.get_user_state(chat_id).await;
+                    let mut user_state = state.get_user_state(chat_id).await?;
                     user_state.conversation_history.remove(&chat_id);
                     
-                    bot.send_message(chat_id, "🗑️ История сессии очищена.")
+                    bot.send_message(chat_id, tr!(lang, "sessions.history_cleared"))
                         .await?;
                     if let Err(e) = state.save_user_state(chat_id, user_state).await {
-                        log::error!("Error saving user state: {}", e);
+                        tracing::error!(chat_id = %chat_id, error = %e, "could not save user state");
                     }
                 }
 
@@ -232,10 +247,10 @@ pub async fn callback_handler(
                     bot.edit_message_text(
                         chat_id,
                         message_id,
-                        "👥 *Выберите консультанта:*\n\nКаждый консультант имеет свой стиль общения и индивидуальную цену\\.",
+                        tr!(lang, "consultants.choose"),
                     )
                     .parse_mode(ParseMode::MarkdownV2)
-                    .reply_markup(make_ai_keyboard(&state).await)
+                    .reply_markup(make_ai_keyboard(&state, lang).await)
                     .await?;
                 }
                 
@@ -248,60 +263,45 @@ pub async fn callback_handler(
                         Ok(Some(booking)) => {
                             if booking.user_id == chat_id {
                                 // Находим консультанта по ID из бронирования
-                                let assistant = AIAssistant::find_by_id_with_price(&state, booking.assistant_id).await
-                                    .unwrap_or_else(|| {
-                                        AIAssistant {
-                                            id: 1,
-                                            name: "Анна".to_string(),
-                                            model: "GigaChat-2-Max".to_string(),
-                                            description: "Интерактивный помощник".to_string(),
-                                            specialty: "Общение и поддержка".to_string(),
-                                            greeting: "Здравствуйте!".to_string(),
-                                            prompt: "Ты помощник.".to_string(),
-                                            price_per_minute: 0.1,
-                                        }
-                                    });
+                                let Some(assistant) = AIAssistant::find_localized(&state, booking.assistant_id, lang).await else {
+                                    bot.send_message(chat_id, tr!(lang, "consultants.not_found")).await?;
+                                    return Ok(());
+                                };
                                 
-                                let status = if booking.is_paid {
-                                    if booking.is_completed {
-                                        "✅ Завершена"
-                                    } else {
-                                        "🟢 Активна"
-                                    }
-                                } else {
-                                    if booking.expires_at.map_or(false, |exp| exp > Utc::now()) {
-                                        "⏳ Ожидает оплаты"
-                                    } else {
-                                        "❌ Истекла"
-                                    }
+                                let status = booking.status_key();
+
+                                let volume = match booking.message_quota {
+                                    Some(quota) => tr!(lang, "booking.volume_messages", count = quota),
+                                    None => tr!(lang, "booking.volume_minutes", minutes = booking.duration_minutes),
                                 };
 
-                                let info_text = format!(
-                                    "📋 *Информация о сессии*\n\n\
-                                    *Консультант:* {}\n\
-                                    *Продолжительность:* {} мин\n\
-                                    *Стоимость:* {} Stars\n\
-                                    *Статус:* {}\n\
-                                    *ID сессии:* `{}`",
-                                    escape_markdown_v2(&assistant.name),
-                                    booking.duration_minutes,
-                                    (booking.total_price * 100.0) as i32,
-                                    escape_markdown_v2(status),
-                                    booking.id
+                                let info_text = tr!(
+                                    lang,
+                                    "booking.info",
+                                    name = escape_markdown_v2(&assistant.name),
+                                    volume = volume,
+                                    stars = (booking.total_price * 100.0) as i32,
+                                    status = escape_markdown_v2(&tr!(lang, status)),
+                                    id = booking.id,
                                 );
 
-                                bot.send_message(chat_id, info_text)
-                                    .parse_mode(ParseMode::MarkdownV2)
-                                    .await?;
+                                let mut info_message = bot.send_message(chat_id, info_text)
+                                    .parse_mode(ParseMode::MarkdownV2);
+
+                                if booking.is_unstarted() {
+                                    info_message = info_message.reply_markup(make_unstarted_booking_keyboard(&booking, lang));
+                                }
+
+                                info_message.await?;
                             }
                         }
                         Ok(None) => {
-                            bot.send_message(chat_id, "❌ Сессия не найдена")
+                            bot.send_message(chat_id, tr!(lang, "booking.not_found"))
                                 .await?;
                         }
                         Err(e) => {
-                            log::error!("Error finding booking: {}", e);
-                            bot.send_message(chat_id, "❌ Ошибка при поиске сессии")
+                            tracing::error!(chat_id = %chat_id, booking_id = %booking_id, error = %e, "could not find booking");
+                            bot.send_message(chat_id, tr!(lang, "booking.lookup_error"))
                                 .await?;
                         }
                     }
@@ -310,27 +310,168 @@ pub async fn callback_handler(
                 data if data.starts_with("temp_") => {
                     let temp_str = data.strip_prefix("temp_").unwrap();
                     if let Ok(temp) = temp_str.parse::<f32>() {
-                        let mut user_state = state.get_user_state(chat_id).await;
+                        let mut user_state = state.get_user_state(chat_id).await?;
                         user_state.user_temperatures.insert(chat_id, temp);
                         
                         let level = match temp {
-                            x if x < 0.2 => "Низкая",
-                            x if x < 0.5 => "Средняя",
-                            _ => "Высокая",
+                            x if x < 0.2 => "sessions.empathy_low",
+                            x if x < 0.5 => "sessions.empathy_medium",
+                            _ => "sessions.empathy_high",
                         };
                         
                         bot.send_message(
                             chat_id, 
-                            format!("✅ Уровень эмпатии установлен: {} ({:.1})", level, temp)
+                            tr!(lang, "sessions.empathy_set", level = tr!(lang, level), value = format!("{:.1}", temp))
                         ).await?;
                         if let Err(e) = state.save_user_state(chat_id, user_state).await {
-                            log::error!("Error saving user state: {}", e);
+                            tracing::error!(chat_id = %chat_id, error = %e, "could not save user state");
+                        }
+                    }
+                }
+
+                data if data.starts_with("start_booking_") => {
+                    let booking_id = data.strip_prefix("start_booking_").unwrap();
+
+                    let booking = match state.get_booking_by_id(booking_id).await {
+                        Ok(Some(booking)) if booking.user_id == chat_id && booking.is_unstarted() => booking,
+                        Ok(_) => {
+                            bot.send_message(chat_id, tr!(lang, "booking.cannot_start")).await?;
+                            return Ok(());
+                        }
+                        Err(e) => {
+                            tracing::error!(chat_id = %chat_id, booking_id = %booking_id, error = %e, "could not find booking");
+                            bot.send_message(chat_id, tr!(lang, "booking.lookup_error")).await?;
+                            return Ok(());
+                        }
+                    };
+
+                    let user_state = state.get_user_state(chat_id).await?;
+                    if user_state.current_session.as_ref().is_some_and(|s| s.can_chat()) {
+                        // Текущую сессию нужно сначала завершить
+                        bot.send_message(chat_id, tr!(lang, "booking.already_running"))
+                        .parse_mode(ParseMode::MarkdownV2)
+                        .reply_markup(InlineKeyboardMarkup::new(vec![
+                            vec![InlineKeyboardButton::callback(tr!(lang, "booking.switch_button"), format!("switch_booking_{}", booking.id))],
+                            vec![InlineKeyboardButton::callback(tr!(lang, "booking.keep_current_button"), "cancel_selection")],
+                        ]))
+                        .await?;
+                        return Ok(());
+                    }
+
+                    let _ = bot.delete_message(chat_id, message_id).await;
+                    activate_booking(&bot, &state, &booking, lang).await?;
+                }
+
+                data if data.starts_with("switch_booking_") => {
+                    let booking_id = data.strip_prefix("switch_booking_").unwrap();
+
+                    match state.get_booking_by_id(booking_id).await {
+                        Ok(Some(booking)) if booking.user_id == chat_id && booking.is_unstarted() => {
+                            let _ = bot.delete_message(chat_id, message_id).await;
+                            end_session(&bot, &state, chat_id, Settlement::Credit, lang).await?;
+                            activate_booking(&bot, &state, &booking, lang).await?;
+                        }
+                        Ok(_) => {
+                            bot.send_message(chat_id, tr!(lang, "booking.cannot_start")).await?;
+                        }
+                        Err(e) => {
+                            tracing::error!(chat_id = %chat_id, booking_id = %booking_id, error = %e, "could not find booking");
+                            bot.send_message(chat_id, tr!(lang, "booking.lookup_error")).await?;
                         }
                     }
                 }
 
+                data if data.starts_with("refund_booking_") => {
+                    let booking_id = data.strip_prefix("refund_booking_").unwrap();
+
+                    match state.get_booking_by_id(booking_id).await {
+                        Ok(Some(mut booking)) if booking.user_id == chat_id && booking.is_unstarted() => {
+                            if let Err(e) = refund_booking(&bot, &state, &mut booking).await {
+                                tracing::error!(chat_id = %chat_id, booking_id = %booking.id, error = %e, "could not refund booking");
+                                bot.send_message(chat_id, tr!(lang, "booking.refund_failed"))
+                                    .await?;
+                                return Ok(());
+                            }
+
+                            bot.edit_message_text(
+                                chat_id,
+                                message_id,
+                                tr!(lang, "booking.refunded", stars = (booking.amount_due() * 100.0) as i32),
+                            )
+                            .await?;
+                        }
+                        Ok(_) => {
+                            bot.send_message(chat_id, tr!(lang, "booking.cannot_refund")).await?;
+                        }
+                        Err(e) => {
+                            tracing::error!(chat_id = %chat_id, booking_id = %booking_id, error = %e, "could not find booking");
+                            bot.send_message(chat_id, tr!(lang, "booking.lookup_error")).await?;
+                        }
+                    }
+                }
+
+                data if data.starts_with("rate_") => {
+                    handle_rating_callback(&bot, &state, chat_id, message_id, data, lang).await?;
+                }
+
+                "feedback_skip" => {
+                    handle_feedback_skip(&bot, &state, chat_id, message_id, lang).await?;
+                }
+
+                data if data.starts_with("deleteme_") => {
+                    handle_delete_me_callback(&bot, &state, chat_id, message_id, data, lang).await?;
+                }
+
+                data if data.starts_with("keephistory_") => {
+                    handle_keep_history_callback(&bot, &state, chat_id, message_id, data, lang).await?;
+                }
+
+                data if data.starts_with("bcast_") => {
+                    handle_broadcast_callback(&bot, &state, chat_id, message_id, data, lang).await?;
+                }
+
+                data if data.starts_with("lang_") => {
+                    handle_language_callback(&bot, &state, chat_id, message_id, data).await?;
+                }
+
+                data if data.starts_with("export_") => {
+                    handle_export_callback(&bot, &state, chat_id, data, lang).await?;
+                }
+
+                data if data.starts_with("goal_") => {
+                    handle_goal_callback(&bot, &state, chat_id, message_id, data, lang).await?;
+                }
+
+                data if data.starts_with("mood_") => {
+                    handle_mood_callback(&bot, &state, chat_id, message_id, data, lang).await?;
+                }
+
+                data if data.starts_with("forget_") => {
+                    handle_memory_callback(&bot, &state, chat_id, message_id, data, lang).await?;
+                }
+
+                "end_session" => {
+                    ask_end_session(&bot, &state, chat_id, lang).await?;
+                }
+
+                "end_session_credit" | "end_session_refund" => {
+                    let settlement = if data == "end_session_refund" {
+                        Settlement::Refund
+                    } else {
+                        Settlement::Credit
+                    };
+
+                    let _ = bot.delete_message(chat_id, message_id).await;
+                    end_session(&bot, &state, chat_id, settlement, lang).await?;
+                }
+
+                "end_session_cancel" => {
+                    bot.edit_message_text(chat_id, message_id, tr!(lang, "session.continues"))
+                        .await?;
+                }
+
                 "cancel_selection" => {
-                    bot.edit_message_text(chat_id, message_id, "❌ Выбор отменен.")
+                    bot.edit_message_text(chat_id, message_id, tr!(lang, "consultants.selection_cancelled"))
                         .await?;
                 }
 
@@ -341,3 +482,124 @@ pub async fn callback_handler(
     
     Ok(())
 }
+
+/// Возвращает на баланс часть, зарезервированную под бронь, которую не удалось сохранить
+async fn release_credit(state: &BotState, booking: &Booking) {
+    if booking.credit_reserved
+        && let Err(e) = state.add_balance(booking.user_id, booking.credit_applied).await
+    {
+        tracing::error!(chat_id = %booking.user_id, booking_id = %booking.id, error = %e, "could not release reserved balance");
+    }
+}
+
+/// Создает бронирование и отправляет счет на оплату в Telegram Stars
+#[allow(clippy::too_many_arguments)]
+async fn create_booking_with_invoice(
+    bot: &Bot,
+    state: &BotState,
+    payment_config: &PaymentConfig,
+    chat_id: ChatId,
+    message_id: MessageId,
+    assistant: &AIAssistant,
+    duration_minutes: u32,
+    total_price: f64,
+    message_quota: Option<u32>,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let booking_id = Uuid::new_v4().to_string();
+    let invoice_payload = Uuid::new_v4().to_string();
+
+    // Остаток прошлых сессий идет в счет оплаты
+    let balance = state.get_user_state(chat_id).await?.balance;
+    let credit_applied = balance.min(total_price);
+
+    let mut booking = Booking {
+        id: booking_id.clone(),
+        user_id: chat_id,
+        assistant_id: assistant.id, // Сохраняем ID консультанта
+        duration_minutes,
+        total_price,
+        invoice_payload: invoice_payload.clone(),
+        is_paid: false,
+        is_completed: false,
+        created_at: Utc::now(),
+        payment_invoice_message_id: None,
+        expires_at: Some(Utc::now() + Duration::minutes(settings::config().payments.invoice_expiry_minutes)),
+        message_quota,
+        credit_applied,
+        telegram_payment_charge_id: None,
+        is_refunded: false,
+        started_at: None,
+        credit_reserved: false,
+    };
+
+    // Часть с баланса списываем сразу, чтобы ее нельзя было потратить дважды
+    if credit_applied > 0.0 {
+        match state.reserve_credit(chat_id, credit_applied).await {
+            Ok(true) => booking.credit_reserved = true,
+            Ok(false) => {
+                bot.send_message(chat_id, tr!(lang, "booking.balance_changed")).await?;
+                return Ok(());
+            }
+            Err(e) => {
+                tracing::error!(chat_id = %chat_id, booking_id = %booking.id, error = %e, "could not reserve balance for booking");
+                bot.send_message(chat_id, tr!(lang, "booking.create_failed")).await?;
+                return Ok(());
+            }
+        }
+    }
+
+    // Баланса хватает на всю сессию — счет не нужен (меньше 1 Star не выставить)
+    if booking.amount_due() < 0.01 {
+        booking.is_paid = true;
+        booking.expires_at = None;
+
+        if let Err(e) = state.save_booking(&booking).await {
+            tracing::error!(chat_id = %chat_id, booking_id = %booking.id, error = %e, "could not save booking paid from balance");
+            release_credit(state, &booking).await;
+            bot.send_message(chat_id, tr!(lang, "booking.create_failed"))
+                .await?;
+            return Ok(());
+        }
+
+        tracing::info!(chat_id = %chat_id, booking_id = %booking.id, "booking paid from balance");
+        bot.delete_message(chat_id, message_id).await?;
+        start_or_keep_booking(bot, state, &booking, lang).await?;
+        return Ok(());
+    }
+
+    // Сохраняем бронирование
+    if let Err(e) = state.save_booking(&booking).await {
+        tracing::error!(chat_id = %chat_id, booking_id = %booking.id, error = %e, "could not save booking");
+        release_credit(state, &booking).await;
+        bot.send_message(chat_id, tr!(lang, "booking.create_failed"))
+            .await?;
+        return Ok(());
+    }
+
+    tracing::info!(chat_id = %chat_id, booking_id = %booking.id, total_price = booking.total_price, "booking created");
+
+    match send_stars_invoice(bot, chat_id, &booking, assistant, payment_config, lang).await {
+        Ok(invoice_message) => {
+            let mut updated_booking = booking.clone();
+            updated_booking.payment_invoice_message_id = Some(invoice_message.id);
+
+            if let Err(e) = state.save_booking(&updated_booking).await {
+                tracing::error!(chat_id = %chat_id, booking_id = %booking.id, error = %e, "could not save invoice message of booking");
+            }
+
+            bot.delete_message(chat_id, message_id).await?;
+
+            bot.send_message(chat_id, tr!(lang, "booking.pay_within", minutes = settings::config().payments.invoice_expiry_minutes))
+            .parse_mode(ParseMode::MarkdownV2)
+            .await?;
+        }
+        Err(e) => {
+            tracing::error!(chat_id = %chat_id, booking_id = %booking.id, error = %e, "could not send invoice");
+            bot.send_message(chat_id, tr!(lang, "booking.invoice_failed"))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
@@ -3,7 +3,14 @@ use teloxide::{prelude::*};
 use std::error::Error;
 
 use crate::bot_state::BotState;
-use crate::models::AIAssistant;
+use crate::i18n::{self, Locale};
+use crate::tr;
+use crate::handlers::export::show_export_options;
+use crate::handlers::goals::show_goals;
+use crate::handlers::language::show_language;
+use crate::handlers::mood::show_mood;
+use crate::handlers::memories::{ask_forget_all, show_memories};
+use crate::handlers::privacy::{ask_delete_me, send_user_data, show_keep_history};
 use crate::handlers::utils::{
     main_menu_keyboard,
     make_ai_keyboard, make_consultants_info_keyboard, show_user_sessions
@@ -17,12 +24,23 @@ pub async fn command_handler(
     cmd: Command,
     state: BotState,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let lang = i18n::locale_for(&state, msg.chat.id, msg.from.as_ref()).await;
+
     match cmd {
-        Command::Start => handle_start(bot, msg, state).await?,
-        Command::Help => handle_help(bot, msg).await?,
-        Command::Persona => handle_persona(bot, msg, state).await?,
+        Command::Start => handle_start(bot, msg, lang).await?,
+        Command::Help => handle_help(bot, msg, lang).await?,
+        Command::Persona => handle_persona(bot, msg, state, lang).await?,
         Command::MySessions => handle_my_sessions(bot, msg, state).await?,
-        Command::Settings => handle_consultants_list(bot, msg, state).await?, // Изменено на список консультантов
+        Command::Settings => handle_consultants_list(bot, msg, state, lang).await?, // Изменено на список консультантов
+        Command::Goals => show_goals(&bot, &state, msg.chat.id, lang).await?,
+        Command::Mood => show_mood(&bot, &state, msg.chat.id, lang).await?,
+        Command::Export => show_export_options(&bot, msg.chat.id, lang).await?,
+        Command::Memories => show_memories(&bot, &state, msg.chat.id, lang).await?,
+        Command::Forget => ask_forget_all(&bot, msg.chat.id, lang).await?,
+        Command::MyData => send_user_data(&bot, &state, msg.chat.id, lang).await?,
+        Command::DeleteMe => ask_delete_me(&bot, &state, msg.chat.id, lang).await?,
+        Command::KeepHistory => show_keep_history(&bot, &state, msg.chat.id, lang).await?,
+        Command::Language => show_language(&bot, &state, msg.chat.id, lang).await?,
     }
     Ok(())
 }
@@ -30,49 +48,11 @@ pub async fn command_handler(
 async fn handle_start(
     bot: Bot,
     msg: Message,
-    state: BotState
+    lang: Locale,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let user_state = state.get_user_state(msg.chat.id).await;
-    
-    // Находим консультанта по ID из состояния пользователя
-    let _current_assistant = AIAssistant::find_by_id_with_price(&state, user_state.current_assistant_id).await
-        .unwrap_or_else(|| {
-            AIAssistant {
-                id: 1,
-                name: "Анна".to_string(),
-                model: "GigaChat-2-Max".to_string(),
-                description: "Интерактивный помощник".to_string(),
-                specialty: "Общение и поддержка".to_string(),
-                greeting: "Здравствуйте!".to_string(),
-                prompt: "Ты помощник.".to_string(),
-                price_per_minute: 0.1,
-            }
-        });
-
-    let start_text = "👋 *Добро пожаловать в ListenerBot\\!*\n\n\
-        🧠 *Кто я?*\n\
-        Я — ИИ\\-ассистент для эмоциональной поддержки\\.\n\
-        Я не являюсь психологом, психотерапевтом или медицинским специалистом\\.\n\n\
-        📋 *Команды:*\n\
-        /start – начать работу\n\
-        /persona – выбрать консультанта \\(стиль общения\\)\n\
-        /mysessions – ваши оплаченные сессии\n\
-        /settings – список консультантов\n\n\
-        🛠️ *Как это работает:*\n\
-        1\\. Выберите консультанта \\(стиль общения\\)\n\
-        2\\. Оплатите время общения через Telegram Stars\n\
-        3\\. Общайтесь с ИИ в течение оплаченного времени\n\
-        4\\. Можно продлевать сессию\n\n\
-        🔐 *Конфиденциальность:*\n\
-        • Сообщения не передаются третьим лицам\n\
-        • Анонимность\n\
-        • Никаких реальных специалистов в проекте нет\n\n\
-        ⚠️ *Важно:*\n\
-        Ответы носят информационный и поддерживающий характер и не заменяют профессиональную помощь\\.";
-
-    bot.send_message(msg.chat.id, start_text)
+    bot.send_message(msg.chat.id, tr!(lang, "start.text"))
         .parse_mode(ParseMode::MarkdownV2)
-        .reply_markup(main_menu_keyboard())
+        .reply_markup(main_menu_keyboard(lang))
         .await?;
 
     Ok(())
@@ -80,22 +60,10 @@ async fn handle_start(
 
 async fn handle_help(
     bot: Bot,
-    msg: Message
+    msg: Message,
+    lang: Locale,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
-    bot.send_message(
-        msg.chat.id,
-        "🫂 *Помощь по боту*\n\n\
-        /start - начать работу\n\
-        /persona - выбрать консультанта\n\
-        /mysessions - мои сессии\n\
-        /settings - список консультантов\n\n\
-        *Как это работает:*\n\
-        1\\. Выберите консультанта\n\
-        2\\. Оплатите время через Telegram Stars\n\
-        3\\. Общайтесь с ИИ в течение оплаченного времени\n\
-        4\\. Можно продлить при необходимости\n\n\
-        ⚠️ Ответы носят информационный характер и не являются консультацией специалиста\\."
-    )
+    bot.send_message(msg.chat.id, tr!(lang, "help.text"))
     .parse_mode(ParseMode::MarkdownV2)
     .await?;
 
@@ -105,16 +73,12 @@ async fn handle_help(
 async fn handle_persona(
     bot: Bot,
     msg: Message,
-    state: BotState
+    state: BotState,
+    lang: Locale,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let keyboard = make_ai_keyboard(&state).await;
+    let keyboard = make_ai_keyboard(&state, lang).await;
 
-    bot.send_message(
-        msg.chat.id,
-        "👥 *Выберите консультанта*\n\n\
-Каждый консультант — это стиль общения ИИ с разным характером и ценой\\.\n\
-Это не психологи и не специалисты\\."
-    )
+    bot.send_message(msg.chat.id, tr!(lang, "consultants.persona"))
     .parse_mode(ParseMode::MarkdownV2)
     .reply_markup(keyboard)
     .await?;
@@ -135,17 +99,12 @@ async fn handle_my_sessions(
 async fn handle_consultants_list(
     bot: Bot,
     msg: Message,
-    state: BotState
+    state: BotState,
+    lang: Locale,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let keyboard = make_consultants_info_keyboard(&state).await;
+    let keyboard = make_consultants_info_keyboard(&state, lang).await;
 
-    bot.send_message(
-        msg.chat.id,
-        "👥 *Список консультантов*\n\n\
-Выберите консультанта чтобы увидеть подробную информацию:\n\n\
-Каждый консультант — это стиль общения ИИ с разным характером и ценой\\.\n\
-Это не психологи и не специалисты\\."
-    )
+    bot.send_message(msg.chat.id, tr!(lang, "consultants.list"))
     .parse_mode(ParseMode::MarkdownV2)
     .reply_markup(keyboard)
     .await?;
@@ -0,0 +1,256 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, InputFile};
+use std::error::Error;
+use std::io::{Cursor, Write};
+use chrono::{DateTime, Utc};
+use zip::write::SimpleFileOptions;
+
+use crate::bot_state::BotState;
+use crate::i18n::Locale;
+use crate::tr;
+use crate::llm::config::ChatMessage;
+use crate::models::{AIAssistant, SessionArchive};
+
+/// Формат выгрузки истории
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Text,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn from_callback(data: &str) -> Option<Self> {
+        match data {
+            "export_md" => Some(ExportFormat::Markdown),
+            "export_txt" => Some(ExportFormat::Text),
+            "export_html" => Some(ExportFormat::Html),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Text => "txt",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+/// Сессия, подготовленная к выгрузке
+struct ExportedSession {
+    assistant_name: String,
+    started_at: DateTime<Utc>,
+    /// Реплики без системных сообщений и служебных вызовов инструментов
+    lines: Vec<(bool, String)>,
+}
+
+/// Ответы консультанта хранятся экранированными для MarkdownV2 — убираем экранирование
+fn unescape_markdown_v2(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch == '\\' && chars.peek().is_some_and(|next| next.is_ascii_punctuation()) {
+            continue;
+        }
+        out.push(ch);
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+async fn prepare_session(
+    state: &BotState,
+    assistant_id: i32,
+    started_at: DateTime<Utc>,
+    history: &[ChatMessage],
+    lang: Locale,
+) -> ExportedSession {
+    let assistant_name = AIAssistant::find_localized(state, assistant_id, lang).await
+        .map(|a| a.name)
+        .unwrap_or_else(|| tr!(lang, "common.consultant"));
+
+    let lines = history
+        .iter()
+        .filter(|m| m.tool_calls.is_none())
+        .filter_map(|m| match (m.role.as_str(), &m.content) {
+            ("user", Some(content)) => Some((true, content.clone())),
+            ("assistant", Some(content)) => Some((false, unescape_markdown_v2(content))),
+            _ => None,
+        })
+        .collect();
+
+    ExportedSession { assistant_name, started_at, lines }
+}
+
+fn render(session: &ExportedSession, format: ExportFormat, lang: Locale) -> String {
+    let date = session.started_at.format("%d.%m.%Y %H:%M UTC").to_string();
+    let you = tr!(lang, "export.you");
+    let speaker = |is_user: bool| if is_user { you.as_str() } else { session.assistant_name.as_str() };
+    let title = tr!(lang, "export.session_title", name = session.assistant_name);
+
+    match format {
+        ExportFormat::Markdown => {
+            let mut out = format!("# {}\n\n_{}_\n\n", title, date);
+            for (is_user, text) in &session.lines {
+                out.push_str(&format!("**{}:**\n\n{}\n\n---\n\n", speaker(*is_user), text));
+            }
+            out
+        }
+        ExportFormat::Text => {
+            let mut out = format!("{}\n{}\n\n", title, date);
+            for (is_user, text) in &session.lines {
+                out.push_str(&format!("{}:\n{}\n\n", speaker(*is_user), text));
+            }
+            out
+        }
+        ExportFormat::Html => {
+            let mut out = format!(
+                "<!DOCTYPE html>\n<html lang=\"{lang}\">\n<head>\n<meta charset=\"utf-8\">\n\
+                <title>{title}</title>\n\
+                <style>body{{font-family:sans-serif;max-width:720px;margin:2em auto;line-height:1.5}}\
+                .msg{{margin:1em 0;padding:.75em 1em;border-radius:8px;white-space:pre-wrap}}\
+                .user{{background:#e8f0fe}}.assistant{{background:#f1f3f4}}</style>\n\
+                </head>\n<body>\n<h1>{title}</h1>\n<p><em>{date}</em></p>\n",
+                lang = lang.code(),
+                title = escape_html(&title),
+                date = date,
+            );
+            for (is_user, text) in &session.lines {
+                out.push_str(&format!(
+                    "<div class=\"msg {}\"><strong>{}:</strong>\n{}</div>\n",
+                    if *is_user { "user" } else { "assistant" },
+                    escape_html(speaker(*is_user)),
+                    escape_html(text)
+                ));
+            }
+            out.push_str("</body>\n</html>\n");
+            out
+        }
+    }
+}
+
+fn file_name(session: &ExportedSession, format: ExportFormat) -> String {
+    format!("session_{}.{}", session.started_at.format("%Y-%m-%d_%H-%M"), format.extension())
+}
+
+/// Предлагает выбрать формат выгрузки
+pub async fn show_export_options(
+    bot: &Bot,
+    chat_id: ChatId,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    bot.send_message(chat_id, tr!(lang, "export.options"))
+    .reply_markup(InlineKeyboardMarkup::new(vec![
+        vec![
+            InlineKeyboardButton::callback(tr!(lang, "export.markdown_button"), "export_md"),
+            InlineKeyboardButton::callback(tr!(lang, "export.text_button"), "export_txt"),
+            InlineKeyboardButton::callback(tr!(lang, "export.html_button"), "export_html"),
+        ],
+        vec![InlineKeyboardButton::callback(tr!(lang, "export.all_button"), "export_all")],
+    ]))
+    .await?;
+
+    Ok(())
+}
+
+/// Выгружает текущую или последнюю сессию в выбранном формате
+async fn export_last_session(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    format: ExportFormat,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let user_state = state.get_user_state(chat_id).await?;
+
+    let session = match user_state.current_session.filter(|s| !s.history.is_empty()) {
+        Some(session) => Some(prepare_session(state, session.assistant_id, session.session_start, &session.history, lang).await),
+        None => match SessionArchive::list_for_user(state, chat_id).await.pop() {
+            Some(archive) => Some(prepare_session(state, archive.assistant_id, archive.started_at, &archive.history, lang).await),
+            None => None,
+        },
+    };
+
+    let Some(session) = session.filter(|s| !s.lines.is_empty()) else {
+        bot.send_message(chat_id, tr!(lang, "export.nothing")).await?;
+        return Ok(());
+    };
+
+    let document = InputFile::memory(render(&session, format, lang).into_bytes())
+        .file_name(file_name(&session, format));
+
+    bot.send_document(chat_id, document).await?;
+    tracing::info!(chat_id = %chat_id, format = format.extension(), "last session exported");
+
+    Ok(())
+}
+
+/// Выгружает все сохраненные сессии ZIP-архивом в формате Markdown
+async fn export_all_sessions(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut sessions = Vec::new();
+    for archive in SessionArchive::list_for_user(state, chat_id).await {
+        sessions.push(prepare_session(state, archive.assistant_id, archive.started_at, &archive.history, lang).await);
+    }
+
+    // Текущая сессия может быть еще не в архиве
+    let user_state = state.get_user_state(chat_id).await?;
+    if let Some(current) = user_state.current_session.filter(|s| !s.history.is_empty())
+        && !sessions.iter().any(|s| s.started_at == current.session_start)
+    {
+        sessions.push(prepare_session(state, current.assistant_id, current.session_start, &current.history, lang).await);
+    }
+
+    sessions.retain(|s| !s.lines.is_empty());
+    if sessions.is_empty() {
+        bot.send_message(chat_id, tr!(lang, "export.nothing")).await?;
+        return Ok(());
+    }
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (index, session) in sessions.iter().enumerate() {
+        zip.start_file(format!("{:03}_{}", index + 1, file_name(session, ExportFormat::Markdown)), options)?;
+        zip.write_all(render(session, ExportFormat::Markdown, lang).as_bytes())?;
+    }
+    let archive = zip.finish()?.into_inner();
+
+    let document = InputFile::memory(archive)
+        .file_name(format!("sessions_{}.zip", Utc::now().format("%Y-%m-%d")));
+
+    bot.send_document(chat_id, document).await?;
+    tracing::info!(chat_id = %chat_id, sessions = sessions.len(), "sessions exported as archive");
+
+    Ok(())
+}
+
+/// Обрабатывает кнопки выгрузки
+pub async fn handle_export_callback(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    data: &str,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if data == "export_all" {
+        return export_all_sessions(bot, state, chat_id, lang).await;
+    }
+
+    match ExportFormat::from_callback(data) {
+        Some(format) => export_last_session(bot, state, chat_id, format, lang).await,
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,173 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId, ParseMode};
+use std::error::Error;
+
+use crate::bot_state::BotState;
+use crate::i18n::{self, Locale};
+use crate::tr;
+use crate::models::{AIAssistant, PendingInput, SessionRating, UserSession};
+use crate::handlers::utils::escape_markdown_v2;
+
+/// Максимальная длина текстового отзыва
+const MAX_COMMENT_LENGTH: usize = 1000;
+
+/// Клавиатура оценки сессии от 1 до 5 звезд
+pub fn make_rating_keyboard(booking_id: &str, lang: Locale) -> InlineKeyboardMarkup {
+    let stars = (1..=5)
+        .map(|n| InlineKeyboardButton::callback(format!("{}⭐", n), format!("rate_{}_{}", n, booking_id)))
+        .collect();
+
+    InlineKeyboardMarkup::new(vec![
+        stars,
+        vec![InlineKeyboardButton::callback(tr!(lang, "common.skip"), "feedback_skip")],
+    ])
+}
+
+/// Предлагает оценить только что завершенную сессию
+pub async fn ask_session_rating(
+    bot: &Bot,
+    state: &BotState,
+    session: &UserSession,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // Оценка привязывается к брони; старые сессии без брони не оцениваем
+    let Some(booking_id) = &session.booking_id else {
+        return Ok(());
+    };
+
+    // Вызывается и из фоновой задачи, поэтому язык берем из сохраненного состояния
+    let lang = i18n::user_locale(state, session.chat_id).await;
+
+    let assistant_name = AIAssistant::find_localized(state, session.assistant_id, lang).await
+        .map(|a| a.name)
+        .unwrap_or_else(|| tr!(lang, "feedback.fallback_name"));
+
+    bot.send_message(
+        session.chat_id,
+        tr!(lang, "feedback.ask", name = escape_markdown_v2(&assistant_name)),
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .reply_markup(make_rating_keyboard(booking_id, lang))
+    .await?;
+
+    Ok(())
+}
+
+/// Обрабатывает нажатие на звезду: сохраняет оценку и предлагает оставить отзыв
+pub async fn handle_rating_callback(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    message_id: MessageId,
+    data: &str,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let Some((rating, booking_id)) = data
+        .strip_prefix("rate_")
+        .and_then(|rest| rest.split_once('_'))
+        .and_then(|(n, id)| n.parse::<i16>().ok().map(|n| (n, id.to_string())))
+        .filter(|(n, _)| (1..=5).contains(n))
+    else {
+        return Ok(());
+    };
+
+    // Оценить можно только свою завершенную сессию
+    let booking = match state.get_booking_by_id(&booking_id).await {
+        Ok(Some(booking)) if booking.user_id == chat_id && booking.is_paid => booking,
+        Ok(_) => {
+            bot.send_message(chat_id, tr!(lang, "booking.not_found")).await?;
+            return Ok(());
+        }
+        Err(e) => {
+            tracing::error!(chat_id = %chat_id, error = %e, "could not find booking");
+            bot.send_message(chat_id, tr!(lang, "booking.lookup_error")).await?;
+            return Ok(());
+        }
+    };
+    if !booking.is_completed || booking.is_refunded {
+        bot.send_message(chat_id, tr!(lang, "feedback.not_completed")).await?;
+        return Ok(());
+    }
+
+    // Сессия могла быть переведена на другого консультанта — оцениваем того, кто ее завершал
+    let user_state = state.get_user_state(chat_id).await?;
+    let assistant_id = user_state.current_session
+        .as_ref()
+        .filter(|s| s.booking_id.as_deref() == Some(booking.id.as_str()))
+        .map(|s| s.assistant_id)
+        .unwrap_or(booking.assistant_id);
+
+    if let Err(e) = SessionRating::save_rating(state, chat_id, assistant_id, &booking.id, rating).await {
+        tracing::error!(chat_id = %chat_id, booking_id = %booking.id, error = %e, "could not save rating");
+        bot.send_message(chat_id, tr!(lang, "feedback.save_failed")).await?;
+        return Ok(());
+    }
+
+    tracing::info!(chat_id = %chat_id, booking_id = %booking.id, rating, "session rated");
+
+    let mut user_state = user_state;
+    user_state.pending_input = Some(PendingInput::FeedbackComment { booking_id: booking.id.clone() });
+    if let Err(e) = state.save_user_state(chat_id, user_state).await {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not save user state");
+    }
+
+    bot.edit_message_text(
+        chat_id,
+        message_id,
+        tr!(lang, "feedback.thanks_rating", stars = "⭐".repeat(rating as usize)),
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(tr!(lang, "common.skip"), "feedback_skip"),
+    ]]))
+    .await?;
+
+    Ok(())
+}
+
+/// Пользователь отказался оставлять отзыв
+pub async fn handle_feedback_skip(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    message_id: MessageId,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut user_state = state.get_user_state(chat_id).await?;
+    if matches!(user_state.pending_input, Some(PendingInput::FeedbackComment { .. })) {
+        user_state.pending_input = None;
+        if let Err(e) = state.save_user_state(chat_id, user_state).await {
+            tracing::error!(chat_id = %chat_id, error = %e, "could not save user state");
+        }
+    }
+
+    bot.edit_message_text(chat_id, message_id, tr!(lang, "feedback.skipped"))
+        .await?;
+
+    Ok(())
+}
+
+/// Сохраняет текстовый отзыв, отправленный после оценки
+pub async fn save_feedback_comment(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    booking_id: &str,
+    text: &str,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let comment: String = text.chars().take(MAX_COMMENT_LENGTH).collect();
+
+    if let Err(e) = SessionRating::save_comment(state, chat_id, booking_id, &comment).await {
+        tracing::error!(chat_id = %chat_id, booking_id = %booking_id, error = %e, "could not save feedback comment");
+    }
+
+    let mut user_state = state.get_user_state(chat_id).await?;
+    user_state.pending_input = None;
+    if let Err(e) = state.save_user_state(chat_id, user_state).await {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not save user state");
+    }
+
+    bot.send_message(chat_id, tr!(lang, "feedback.thanks_comment")).await?;
+
+    Ok(())
+}
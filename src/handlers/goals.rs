@@ -0,0 +1,146 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId, ParseMode};
+use std::error::Error;
+
+use crate::bot_state::BotState;
+use crate::i18n::Locale;
+use crate::tr;
+use crate::models::{Goal, PendingInput};
+use crate::handlers::utils::escape_markdown_v2;
+
+/// Сколько целей и задач показываем в списке
+const GOALS_PAGE_SIZE: usize = 20;
+/// Максимальная длина названия цели
+const MAX_GOAL_TITLE_LENGTH: usize = 200;
+
+fn format_goals(goals: &[Goal], lang: Locale) -> String {
+    if goals.is_empty() {
+        return tr!(lang, "goals.empty");
+    }
+
+    let done = goals.iter().filter(|g| g.is_done).count();
+    let mut text = format!("{}\n\n", tr!(lang, "goals.title", done = done, total = goals.len()));
+
+    for goal in goals.iter().take(GOALS_PAGE_SIZE) {
+        let mark = if goal.is_done { "✅" } else { "⬜" };
+        let indent = if goal.parent_id.is_some() { "    " } else { "" };
+        let title = escape_markdown_v2(&goal.title);
+        let title = if goal.is_done { format!("~{}~", title) } else { title };
+
+        text.push_str(&format!("{}{} {}\n", indent, mark, title));
+    }
+
+    text.push('\n');
+    text.push_str(&tr!(lang, "goals.hint"));
+    text
+}
+
+fn make_goals_keyboard(goals: &[Goal], lang: Locale) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = goals
+        .iter()
+        .take(GOALS_PAGE_SIZE)
+        .map(|goal| {
+            let mark = if goal.is_done { "✅" } else { "⬜" };
+            let prefix = if goal.parent_id.is_some() { "  ↳ " } else { "" };
+            let title: String = goal.title.chars().take(40).collect();
+            vec![InlineKeyboardButton::callback(
+                format!("{}{} {}", prefix, mark, title),
+                format!("goal_toggle_{}", goal.id),
+            )]
+        })
+        .collect();
+
+    keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "goals.add_button"), "goal_add")]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Показывает цели и задачи пользователя
+pub async fn show_goals(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let goals = Goal::list_for_user(state, chat_id).await;
+
+    bot.send_message(chat_id, format_goals(&goals, lang))
+        .parse_mode(ParseMode::MarkdownV2)
+        .reply_markup(make_goals_keyboard(&goals, lang))
+        .await?;
+
+    Ok(())
+}
+
+/// Обрабатывает отметку выполнения и добавление цели
+pub async fn handle_goal_callback(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    message_id: MessageId,
+    data: &str,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if data == "goal_add" {
+        let mut user_state = state.get_user_state(chat_id).await?;
+        user_state.pending_input = Some(PendingInput::NewGoal);
+        if let Err(e) = state.save_user_state(chat_id, user_state).await {
+            tracing::error!(chat_id = %chat_id, error = %e, "could not save user state");
+        }
+
+        bot.send_message(chat_id, tr!(lang, "goals.ask_title")).await?;
+        return Ok(());
+    }
+
+    let Some(id) = data.strip_prefix("goal_toggle_").and_then(|id| id.parse::<i32>().ok()) else {
+        return Ok(());
+    };
+
+    match Goal::toggle(state, chat_id, id).await {
+        Ok(Some(goal)) => {
+            tracing::info!(chat_id = %chat_id, goal_id = goal.id, done = goal.is_done, "goal status changed");
+        }
+        Ok(None) => {
+            bot.send_message(chat_id, tr!(lang, "goals.not_found")).await?;
+            return Ok(());
+        }
+        Err(e) => {
+            tracing::error!(chat_id = %chat_id, goal_id = id, error = %e, "could not toggle goal");
+            bot.send_message(chat_id, tr!(lang, "goals.update_failed")).await?;
+            return Ok(());
+        }
+    }
+
+    let goals = Goal::list_for_user(state, chat_id).await;
+    bot.edit_message_text(chat_id, message_id, format_goals(&goals, lang))
+        .parse_mode(ParseMode::MarkdownV2)
+        .reply_markup(make_goals_keyboard(&goals, lang))
+        .await?;
+
+    Ok(())
+}
+
+/// Сохраняет цель, введенную пользователем вручную
+pub async fn save_new_goal(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    text: &str,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let title: String = text.trim().chars().take(MAX_GOAL_TITLE_LENGTH).collect();
+
+    let mut user_state = state.get_user_state(chat_id).await?;
+    user_state.pending_input = None;
+    if let Err(e) = state.save_user_state(chat_id, user_state).await {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not save user state");
+    }
+
+    if let Err(e) = Goal::create(state, chat_id, &title).await {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not save goal");
+        bot.send_message(chat_id, tr!(lang, "goals.save_failed")).await?;
+        return Ok(());
+    }
+
+    show_goals(bot, state, chat_id, lang).await
+}
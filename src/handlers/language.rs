@@ -0,0 +1,79 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId};
+use std::error::Error;
+
+use crate::bot_state::BotState;
+use crate::i18n::Locale;
+use crate::tr;
+use crate::handlers::utils::main_menu_keyboard;
+
+fn language_keyboard(lang: Locale) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = Locale::ALL
+        .iter()
+        .map(|locale| {
+            vec![InlineKeyboardButton::callback(
+                locale.native_name(),
+                format!("lang_{}", locale.code()),
+            )]
+        })
+        .collect();
+
+    keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "language.auto_button"), "lang_auto")]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Показывает текущий язык интерфейса и варианты выбора
+pub async fn show_language(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let user_state = state.get_user_state(chat_id).await?;
+
+    let current = match user_state.locale {
+        Some(locale) => locale.native_name().to_string(),
+        None => format!("{} ({})", lang.native_name(), tr!(lang, "language.auto")),
+    };
+
+    bot.send_message(chat_id, tr!(lang, "language.text", current = current))
+        .reply_markup(language_keyboard(lang))
+        .await?;
+
+    Ok(())
+}
+
+/// Сохраняет выбранный язык; "lang_auto" возвращает язык клиента Telegram
+pub async fn handle_language_callback(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    message_id: MessageId,
+    data: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let Some(code) = data.strip_prefix("lang_") else {
+        return Ok(());
+    };
+
+    let mut user_state = state.get_user_state(chat_id).await?;
+    user_state.locale = Locale::from_code(code);
+    let lang = user_state.locale();
+
+    if let Err(e) = state.save_user_state(chat_id, user_state).await {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not save user language");
+        bot.send_message(chat_id, tr!(lang, "common.save_failed")).await?;
+        return Ok(());
+    }
+
+    tracing::info!(chat_id = %chat_id, locale = lang.code(), "language switched");
+
+    let _ = bot.delete_message(chat_id, message_id).await;
+
+    // Reply-клавиатуру главного меню нужно прислать заново, чтобы обновить подписи кнопок
+    bot.send_message(chat_id, tr!(lang, "language.changed", language = lang.native_name()))
+        .reply_markup(main_menu_keyboard(lang))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId, ParseMode};
+use std::error::Error;
+
+use crate::bot_state::BotState;
+use crate::i18n::Locale;
+use crate::tr;
+use crate::models::UserMemory;
+use crate::models::memory::MemoryKind;
+use crate::handlers::utils::escape_markdown_v2;
+
+/// Сколько воспоминаний показываем в списке
+const MEMORIES_PAGE_SIZE: usize = 10;
+
+fn format_memories(memories: &[UserMemory], lang: Locale) -> String {
+    if memories.is_empty() {
+        return tr!(lang, "memories.empty");
+    }
+
+    let mut text = format!("{}\n\n", tr!(lang, "memories.title"));
+
+    for (index, memory) in memories.iter().take(MEMORIES_PAGE_SIZE).enumerate() {
+        let icon = match memory.kind {
+            MemoryKind::Fact => "📌",
+            MemoryKind::Summary => "📝",
+        };
+        text.push_str(&format!(
+            "{}\\. {} {} _\\({}\\)_\n",
+            index + 1,
+            icon,
+            escape_markdown_v2(&memory.content),
+            memory.created_at.format("%d\\.%m\\.%Y")
+        ));
+    }
+
+    if memories.len() > MEMORIES_PAGE_SIZE {
+        text.push_str(&format!(
+            "\n{}\n",
+            tr!(lang, "memories.more", count = memories.len() - MEMORIES_PAGE_SIZE)
+        ));
+    }
+
+    text.push('\n');
+    text.push_str(&tr!(lang, "memories.hint"));
+    text
+}
+
+fn make_memories_keyboard(memories: &[UserMemory], lang: Locale) -> InlineKeyboardMarkup {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = memories
+        .iter()
+        .take(MEMORIES_PAGE_SIZE)
+        .enumerate()
+        .map(|(index, memory)| {
+            InlineKeyboardButton::callback(format!("🗑 {}", index + 1), format!("forget_memory_{}", memory.id))
+        })
+        .collect::<Vec<_>>()
+        .chunks(5)
+        .map(|row| row.to_vec())
+        .collect();
+
+    if !memories.is_empty() {
+        keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "memories.forget_all_button"), "forget_all_ask")]);
+    }
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Показывает пользователю сохраненные воспоминания
+pub async fn show_memories(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let memories = UserMemory::list_for_user(state, chat_id).await;
+
+    bot.send_message(chat_id, format_memories(&memories, lang))
+        .parse_mode(ParseMode::MarkdownV2)
+        .reply_markup(make_memories_keyboard(&memories, lang))
+        .await?;
+
+    Ok(())
+}
+
+/// Просит подтвердить удаление всей памяти
+pub async fn ask_forget_all(
+    bot: &Bot,
+    chat_id: ChatId,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    bot.send_message(chat_id, tr!(lang, "memories.forget_all_confirm"))
+    .parse_mode(ParseMode::MarkdownV2)
+    .reply_markup(InlineKeyboardMarkup::new(vec![
+        vec![InlineKeyboardButton::callback(tr!(lang, "memories.confirm_button"), "forget_all_confirm")],
+        vec![InlineKeyboardButton::callback(tr!(lang, "memories.cancel_button"), "forget_cancel")],
+    ]))
+    .await?;
+
+    Ok(())
+}
+
+/// Обрабатывает кнопки удаления воспоминаний
+pub async fn handle_memory_callback(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    message_id: MessageId,
+    data: &str,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match data {
+        "forget_all_ask" => ask_forget_all(bot, chat_id, lang).await?,
+        "forget_all_confirm" => {
+            match UserMemory::delete_all(state, chat_id).await {
+                Ok(count) => {
+                    tracing::info!(chat_id = %chat_id, count, "memories erased");
+                    bot.edit_message_text(chat_id, message_id, tr!(lang, "memories.erased")).await?;
+                }
+                Err(e) => {
+                    tracing::error!(chat_id = %chat_id, error = %e, "could not delete memories");
+                    bot.send_message(chat_id, tr!(lang, "memories.erase_failed")).await?;
+                }
+            }
+        }
+        "forget_cancel" => {
+            bot.edit_message_text(chat_id, message_id, tr!(lang, "memories.kept")).await?;
+        }
+        data => {
+            let Some(id) = data.strip_prefix("forget_memory_").and_then(|id| id.parse::<i32>().ok()) else {
+                return Ok(());
+            };
+
+            if let Err(e) = UserMemory::delete(state, chat_id, id).await {
+                tracing::error!(chat_id = %chat_id, memory_id = id, error = %e, "could not delete memory");
+                bot.send_message(chat_id, tr!(lang, "memories.delete_failed")).await?;
+                return Ok(());
+            }
+
+            let memories = UserMemory::list_for_user(state, chat_id).await;
+            bot.edit_message_text(chat_id, message_id, format_memories(&memories, lang))
+                .parse_mode(ParseMode::MarkdownV2)
+                .reply_markup(make_memories_keyboard(&memories, lang))
+                .await?;
+        }
+    }
+
+    Ok(())
+}
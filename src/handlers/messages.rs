@@ -3,15 +3,24 @@ use teloxide::types::ParseMode;
 use std::error::Error;
 
 use crate::bot_state::BotState;
-use crate::llm;
+use crate::i18n::{self, Locale};
+use crate::tr;
+use crate::llm::tools::{self, ToolContext};
+use crate::memory;
+use crate::moderation;
+use crate::safety;
 use crate::llm::config::ChatMessage;
-use crate::models::{AIAssistant, PaymentConfig};
+use crate::models::{AIAssistant, Goal, MoodEntry, PaymentConfig, PendingInput};
+use crate::handlers::feedback::{ask_session_rating, save_feedback_comment};
+use crate::handlers::goals::save_new_goal;
+use crate::handlers::mood::{save_checkin_time, save_mood_note};
+use crate::handlers::sessions::finalize_session_in_background;
 use crate::handlers::utils::{
-    escape_markdown_v2, main_menu_keyboard, 
+    escape_markdown_v2, main_menu_keyboard, MenuItem,
     make_ai_keyboard, make_consultants_info_keyboard, 
-    send_ai_message, show_user_sessions
+    send_ai_message, show_user_sessions, build_system_prompt
 };
-use chrono::Utc;
+use chrono::{Duration, Utc};
 
 pub async fn message_handler(
     bot: Bot,
@@ -19,115 +28,143 @@ pub async fn message_handler(
     state: BotState,
     _payment_config: PaymentConfig,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let lang = i18n::locale_for(&state, msg.chat.id, msg.from.as_ref()).await;
+
     if let Some(text) = msg.text() {
         // Пропускаем команды - они уже обработаны в command_handler
         if text.starts_with('/') {
             return Ok(());
         }
 
-        match text {
-            "👥 Выбрать консультанта" => {
-                let keyboard = make_ai_keyboard(&state).await;
-                bot.send_message(
-                    msg.chat.id,
-                    "👥 *Выберите консультанта:*\n\nКаждый консультант имеет свой стиль общения и индивидуальную цену\\.",
-                )
+        match MenuItem::from_text(text) {
+            Some(MenuItem::ChooseConsultant) => {
+                let keyboard = make_ai_keyboard(&state, lang).await;
+                bot.send_message(msg.chat.id, tr!(lang, "consultants.choose"))
                 .parse_mode(ParseMode::MarkdownV2)
                 .reply_markup(keyboard)
                 .await?;
             }
-            "💰 Мои сессии" => {
+            Some(MenuItem::MySessions) => {
                 show_user_sessions(&bot, msg.chat.id, &state).await?;
             }
-            "ℹ️ Список консультантов" => {
-                let keyboard = make_consultants_info_keyboard(&state).await;
-                bot.send_message(
-                    msg.chat.id,
-                    "👥 *Список консультантов*\n\n\
-Выберите консультанта чтобы увидеть подробную информацию:\n\n\
-Каждый консультант — это стиль общения ИИ с разным характером и ценой\\.\n\
-Это не психологи и не специалисты\\.",
-                )
+            Some(MenuItem::Consultants) => {
+                let keyboard = make_consultants_info_keyboard(&state, lang).await;
+                bot.send_message(msg.chat.id, tr!(lang, "consultants.list"))
                 .parse_mode(ParseMode::MarkdownV2)
                 .reply_markup(keyboard)
                 .await?;
             }
-            "ℹ️ О боте" => {
-                bot.send_message(
-                    msg.chat.id,
-                    "🫂 *О боте*\n\n\
-                    Это AI\\-бот для общения и эмоциональной поддержки\n\n\
-                    *Возможности:*\n\
-                    • Выбор из нескольких консультантов\n\
-                    • Оплата сессий через Telegram Stars\n\
-                    • Контроль времени сессии\n\
-                    • Полная конфиденциальность\n\n\
-                    Используйте меню для навигации\\.",
-                )
+            Some(MenuItem::About) => {
+                bot.send_message(msg.chat.id, tr!(lang, "about.text"))
                 .parse_mode(ParseMode::MarkdownV2)
                 .await?;
             }
-            _ => {
-                let user_state = state.get_user_state(msg.chat.id).await;
-                
-                // Находим консультанта по ID из состояния пользователя
-                let current_assistant = AIAssistant::find_by_id_with_price(&state, user_state.current_assistant_id).await
-                    .unwrap_or_else(|| {
-                        AIAssistant {
-                            id: 1,
-                            name: "Анна".to_string(),
-                            model: "GigaChat-2-Max".to_string(),
-                            description: "Интерактивный помощник".to_string(),
-                            specialty: "Общение и поддержка".to_string(),
-                            greeting: "Здравствуйте!".to_string(),
-                            prompt: "Ты помощник.".to_string(),
-                            price_per_minute: 0.1,
+            None => {
+                let mut user_state = state.get_user_state(msg.chat.id).await?;
+
+                // Проверка безопасности выполняется до любой другой обработки сообщения,
+                // в том числе для текста заметок, целей и отзывов
+                let safety_finding = safety::screen_message(text).await;
+                if let Some(finding) = &safety_finding {
+                    let language_code = msg.from.as_ref().and_then(|user| user.language_code.as_deref());
+                    bot.send_message(msg.chat.id, safety::crisis_message(language_code)).await?;
+
+                    let session = user_state.current_session.as_ref().filter(|s| s.can_chat());
+                    if let Err(e) = safety::flag_for_review(
+                        &state,
+                        msg.chat.id,
+                        session.and_then(|s| s.booking_id.as_deref()),
+                        session.map(|s| s.assistant_id),
+                        finding,
+                        text,
+                    ).await {
+                        tracing::error!(chat_id = %msg.chat.id, error = %e, "could not save safety flag");
+                    }
+                }
+
+                // Затем обрабатываем ожидаемый ввод (например, отзыв после оценки)
+                match &user_state.pending_input {
+                    Some(pending) if pending.is_expired() => {
+                        // На вопрос дневника долго не отвечали — это сообщение уже для консультанта
+                        user_state.pending_input = None;
+                        if let Err(e) = state.save_user_state(msg.chat.id, user_state.clone()).await {
+                            tracing::error!(chat_id = %msg.chat.id, error = %e, "could not clear expired pending input");
                         }
-                    });
+                    }
+                    Some(PendingInput::FeedbackComment { booking_id }) => {
+                        save_feedback_comment(&bot, &state, msg.chat.id, booking_id, text, lang).await?;
+                        return Ok(());
+                    }
+                    Some(PendingInput::NewGoal) => {
+                        save_new_goal(&bot, &state, msg.chat.id, text, lang).await?;
+                        return Ok(());
+                    }
+                    Some(PendingInput::MoodNote { entry_id, .. }) => {
+                        save_mood_note(&bot, &state, msg.chat.id, *entry_id, text, lang).await?;
+                        return Ok(());
+                    }
+                    Some(PendingInput::MoodCheckinTime { .. }) => {
+                        save_checkin_time(&bot, &state, msg.chat.id, text, lang).await?;
+                        return Ok(());
+                    }
+                    None => {}
+                }
                 
                 // Проверяем активность сессии
-                let can_chat = if let Some(session) = &user_state.current_session {
-                    session.is_active && Utc::now() < session.paid_until
-                } else {
-                    false
-                };
+                let can_chat = user_state.current_session
+                    .as_ref()
+                    .is_some_and(|session| session.can_chat());
+
+                // Без активной сессии ограничиваемся кризисным сообщением
+                if safety_finding.is_some() && !can_chat {
+                    return Ok(());
+                }
 
                 if !can_chat {
                     // Предлагаем выбрать консультанта для начала сессии
-                    bot.send_message(
-                        msg.chat.id,
-                        format!(
-                            "💬 *Чтобы начать сессию, необходимо выбрать консультанта:*\n\n"
-                        ),
-                    )
+                    bot.send_message(msg.chat.id, tr!(lang, "consultants.choose_to_start"))
                     .parse_mode(ParseMode::MarkdownV2)
-                    .reply_markup(make_ai_keyboard(&state).await)
+                    .reply_markup(make_ai_keyboard(&state, lang).await)
                     .await?;
                     return Ok(());
                 }
 
+                // Находим консультанта по ID из состояния пользователя
+                let Some(current_assistant) = AIAssistant::find_localized(&state, user_state.current_assistant_id, lang).await else {
+                    bot.send_message(msg.chat.id, tr!(lang, "consultants.not_found")).await?;
+                    return Ok(());
+                };
+
                 // Показываем индикатор набора текста
                 let _ = bot.send_chat_action(msg.chat.id, teloxide::types::ChatAction::Typing).await;
 
                 // ОБНОВЛЯЕМ СЕССИЮ В user_state
-                let mut user_state = state.get_user_state(msg.chat.id).await;
+                let mut user_state = state.get_user_state(msg.chat.id).await?;
                 if let Some(session) = &mut user_state.current_session {
                     if session.history.is_empty() {
-                        let telegram_prompt = format!(
-                            "{}\n\n\
-                            - Используй *жирный текст* для выделения важных моментов (одну звездочку вместо двух)\n\
-                            - Используй _курсив_ для акцентов\n\
-                            - Используй эмодзи для выразительности\n\
-                            - Если нужно показать код, используй обратные кавычки: `код`\n\
-                            - Для списков используй маркеры • или цифры\n\
-                            - Используй MarkdownV2 для форматирования текста\n\
-                            Твои будут отображаться в Telegram, поэтому форматируй их соответствующим образом",
-                            current_assistant.prompt
-                        );
-                        
+                        // В начале сессии подмешиваем воспоминания из прошлых разговоров
+                        let mut system_prompt = build_system_prompt(&current_assistant, lang);
+                        let memories = memory::recall(&state, msg.chat.id, text).await;
+                        if let Some(memory_block) = memory::memory_prompt(&memories) {
+                            system_prompt = format!("{}\n\n{}", system_prompt, memory_block);
+                            tracing::info!(chat_id = %msg.chat.id, memories = memories.len(), "memories recalled");
+                        }
+
+                        // Открытые цели, чтобы консультант мог спросить о прогрессе
+                        let goals = Goal::list_for_user(&state, msg.chat.id).await;
+                        if let Some(goals_block) = Goal::prompt_block(&goals) {
+                            system_prompt = format!("{}\n\n{}", system_prompt, goals_block);
+                        }
+
+                        // Дневник настроения между сессиями
+                        let moods = MoodEntry::list_since(&state, msg.chat.id, Utc::now() - Duration::days(7)).await;
+                        if let Some(mood_block) = MoodEntry::prompt_block(&moods) {
+                            system_prompt = format!("{}\n\n{}", system_prompt, mood_block);
+                        }
+
                         session.history.push(ChatMessage {
                             role: "system".to_string(),
-                            content: Some(telegram_prompt),
+                            content: Some(system_prompt),
                             tool_calls: None,
                             tool_call_id: None,
                             name: None
@@ -143,21 +180,60 @@ pub async fn message_handler(
                         name: None
                     });
 
-                    log::info!("📝 Message added to history. Total messages: {}", session.history.len());
+                    tracing::info!(chat_id = %msg.chat.id, history_len = session.history.len(), "message added to history");
 
                     // Копия истории для LLM
-                    let messages = session.history.clone();
+                    let mut messages = session.history.clone();
 
-                    // Отправка в LLM
-                    let response = llm::chat(
-                        messages,
-                        current_assistant.model.clone(),
-                        0.1
+                    if let Some(finding) = &safety_finding {
+                        session.flagged_for_review = true;
+                        messages.push(safety::assistant_guidance(finding.category));
+
+                        // Время, пока пользователь читает кризисный ответ, не списываем (один раз за сессию)
+                        if safety::config().suspend_billing
+                            && session.apply_crisis_pause(safety::config().pause_minutes)
+                        {
+                            tracing::info!(chat_id = %msg.chat.id, pause_minutes = safety::config().pause_minutes, "crisis pause applied");
+                        }
+                    }
+
+                    // Отправка в LLM с возможностью вызова встроенных инструментов
+                    let tool_context = ToolContext {
+                        state: &state,
+                        chat_id: msg.chat.id,
+                        paid_until: session.paid_until,
+                        remaining_messages: session.remaining_messages(),
+                    };
+                    let sent_count = messages.len();
+                    let response = tools::chat_with_tools(
+                        &mut messages,
+                        &current_assistant.model,
+                        0.1,
+                        &tool_context,
                     ).await?;
+
+                    // Вызовы инструментов и их результаты сохраняем в истории
+                    session.history.extend(messages[sent_count..].iter().cloned());
                     
                     if let Some(ai_response) = response.content {
+                        // Проверяем ответ на соответствие правилам до отправки пользователю
+                        let moderated = moderation::moderate_reply(
+                            &messages,
+                            &current_assistant.model,
+                            0.1,
+                            ai_response,
+                        ).await;
+                        for (violation, action) in &moderated.violations {
+                            if let Err(e) = moderation::log_violation(
+                                &state, msg.chat.id, current_assistant.id, violation, *action,
+                            ).await {
+                                tracing::error!(chat_id = %msg.chat.id, error = %e, "could not save moderation violation");
+                            }
+                        }
+                        let ai_response = moderated.text;
+
                         // ДОБАВЛЯЕМ ПРОВЕРКУ И КОРРЕКЦИЮ ФОРМАТИРОВАНИЯ
-                        let cleaned_response = clean_telegram_markdown(&ai_response);
+                        let cleaned_response = clean_telegram_markdown(&ai_response, lang);
                         
                         session.history.push(ChatMessage {
                             role: "assistant".to_string(),
@@ -167,59 +243,86 @@ pub async fn message_handler(
                             name: None
                         });
 
-                        session.messages_exchanged += 1;
+                        // Кризисный ответ не расходует пакет сообщений — один раз за сессию
+                        let free_reply = safety_finding.is_some()
+                            && safety::config().suspend_billing
+                            && session.apply_crisis_free_message();
+                        if !free_reply {
+                            session.messages_exchanged += 1;
+                        }
 
                         if Utc::now() > session.paid_until {
                             session.is_active = false;
-                            bot.send_message(
-                                msg.chat.id,
-                                "⏰ *Время сессии истекло*\n\nЧтобы продолжить, оплатите новое время сессии\\.",
-                            )
+                            finalize_session_in_background(&state, session);
+                            bot.send_message(msg.chat.id, tr!(lang, "session.expired_pay"))
                             .parse_mode(ParseMode::MarkdownV2)
                             .await?;
                         }
 
-                        send_ai_message(&bot, msg.chat.id, &current_assistant.name, &cleaned_response).await?;
+                        // Для пакета сообщений показываем остаток под ответом
+                        let remaining_messages = session.remaining_messages();
+                        let reply = match remaining_messages {
+                            Some(remaining) => format!(
+                                "{}\n\n{}",
+                                cleaned_response,
+                                tr!(lang, "session.remaining_messages", count = remaining)
+                            ),
+                            None => cleaned_response,
+                        };
 
-                        log::info!("💬 Response sent. Messages exchanged: {}", session.messages_exchanged);
+                        send_ai_message(&bot, msg.chat.id, &current_assistant.name, &reply).await?;
+
+                        if remaining_messages == Some(0) {
+                            session.is_active = false;
+
+                            match state.find_booking_for_session(session).await {
+                                Ok(Some(booking)) if !booking.is_completed => {
+                                    if let Err(e) = state.mark_booking_completed(&booking.id).await {
+                                        tracing::error!(chat_id = %msg.chat.id, booking_id = %booking.id, error = %e, "could not mark booking as completed");
+                                    }
+                                }
+                                Ok(_) => {}
+                                Err(e) => tracing::error!(chat_id = %msg.chat.id, error = %e, "could not find booking for session"),
+                            }
+
+                            bot.send_message(msg.chat.id, tr!(lang, "session.bundle_exhausted"))
+                            .parse_mode(ParseMode::MarkdownV2)
+                            .await?;
+
+                            finalize_session_in_background(&state, session);
+                            ask_session_rating(&bot, &state, session).await?;
+                        }
+
+                        tracing::info!(chat_id = %msg.chat.id, messages_exchanged = session.messages_exchanged, "response sent");
                     } else {
-                        log::error!("❌ LLM вернул пустой ответ");
-                        bot.send_message(
-                            msg.chat.id,
-                            "Извините, произошла ошибка. Пожалуйста, попробуйте еще раз.",
-                        )
-                        .await?;
+                        tracing::error!(chat_id = %msg.chat.id, "LLM returned an empty response");
+                        bot.send_message(msg.chat.id, tr!(lang, "common.error_retry")).await?;
                     }
 
                     // Сохраняем user_state
                     if let Err(e) = state.save_user_state(msg.chat.id, user_state).await {
-                        log::error!("❌ Error saving user state: {}", e);
+                        tracing::error!(chat_id = %msg.chat.id, error = %e, "could not save user state");
                     } else {
-                        log::info!("💾 User state saved successfully with updated history");
+                        tracing::info!(chat_id = %msg.chat.id, "user state saved with updated history");
                     }
                 } else {
-                    log::error!("❌ No active session found for user {}", msg.chat.id);
-                    bot.send_message(
-                        msg.chat.id,
-                        "⚠️ Сессия не найдена\\. Пожалуйста, начните новую сессию\\.",
-                    )
+                    tracing::error!(chat_id = %msg.chat.id, "no active session found");
+                    bot.send_message(msg.chat.id, tr!(lang, "session.not_found"))
+                    .parse_mode(ParseMode::MarkdownV2)
                     .await?;
                 }
             }
         }
     } else {
-        bot.send_message(
-            msg.chat.id,
-            "👋 Напишите свой вопрос, консультант подключится и начнет с вами диалог.",
-        )
-        .reply_markup(main_menu_keyboard())
+        bot.send_message(msg.chat.id, tr!(lang, "common.write_question"))
+        .reply_markup(main_menu_keyboard(lang))
         .await?;
     }
     Ok(())
 }
 
 /// Функция для очистки и корректировки Markdown для Telegram
-fn clean_telegram_markdown(text: &str) -> String {
+fn clean_telegram_markdown(text: &str, lang: Locale) -> String {
     let mut cleaned = text.to_string();
     
     // Заменяем HTML-теги на Markdown
@@ -255,7 +358,8 @@ fn clean_telegram_markdown(text: &str) -> String {
     // Обрезаем слишком длинные сообщения (Telegram ограничение ~4096 символов)
     if result.len() > 3800 {
         result = result.chars().take(3800).collect();
-        result.push_str("\n\n\\[Сообщение было сокращено\\]");
+        result.push_str("\n\n");
+        result.push_str(&tr!(lang, "session.truncated"));
     }
     
     result
@@ -1,9 +1,22 @@
+pub mod admin;
+pub mod analytics;
+pub mod broadcast;
 pub mod commands;
 pub mod messages;
 pub mod callbacks;
 pub mod payments;
+pub mod privacy;
+pub mod export;
+pub mod feedback;
+pub mod goals;
+pub mod language;
+pub mod memories;
+pub mod mood;
+pub mod sessions;
 pub mod utils;
 
+pub use admin::admin_command_handler;
+pub use broadcast::chat_member_handler;
 pub use commands::command_handler;
 pub use messages::message_handler;
 pub use callbacks::callback_handler;
@@ -11,14 +24,22 @@ pub use payments::{pre_checkout_handler, successful_payment_handler};
 
 use chrono::Utc;
 use crate::bot_state::BotState;
+use crate::health;
+use crate::i18n;
+use crate::tr;
+use crate::models::{MoodCheckin, Reminder};
+use crate::retention;
+use crate::settings;
 use teloxide::prelude::*;
 use teloxide::{Bot, prelude::Requester};
 
-pub async fn check_sessions_task(state: BotState) {
-    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
+pub async fn check_sessions_task(bot: Bot, state: BotState) {
+    let period = tokio::time::Duration::from_secs(settings::config().timeouts.session_check_interval_seconds);
+    let mut interval = tokio::time::interval(period);
     
     loop {
         interval.tick().await;
+        health::heartbeat("check_sessions", period);
         
         let now = Utc::now();
         let user_states = state.get_all_user_states().await;
@@ -26,7 +47,7 @@ pub async fn check_sessions_task(state: BotState) {
         // Очищаем просроченные брони
         if let Ok(deleted_count) = state.cleanup_expired_bookings().await {
             if deleted_count > 0 {
-                log::info!("🧹 Cleaned up {} expired bookings in background", deleted_count);
+                tracing::info!(bookings = deleted_count, "expired bookings cleaned up");
             }
         }
         
@@ -34,37 +55,135 @@ pub async fn check_sessions_task(state: BotState) {
             if let Some(session) = &user_state.current_session {
                 // Проверяем истечение времени сессии
                 if session.is_active && now > session.paid_until {
-                    let mut updated_state = user_state.clone();
-                    if let Some(sess) = &mut updated_state.current_session {
-                        sess.is_active = false;
-                    }
-                    
                     // ПОМЕЧАЕМ БРОНЬ КАК ЗАВЕРШЕННУЮ
                     match state.find_booking_for_session(&session).await {
                         Ok(Some(booking)) => {
                             if !booking.is_completed {
                                 if let Err(e) = state.mark_booking_completed(&booking.id).await {
-                                    log::error!("Error marking booking as completed: {}", e);
+                                    tracing::error!(chat_id = %chat_id, booking_id = %booking.id, error = %e, "could not mark booking as completed");
                                 } else {
-                                    log::info!("✅ Session expired, booking {} marked as completed", booking.id);
+                                    tracing::info!(chat_id = %chat_id, booking_id = %booking.id, "session expired, booking completed");
                                 }
                             }
                         }
                         Ok(None) => {
-                            log::warn!("No booking found for expired session");
+                            tracing::warn!(chat_id = %chat_id, "no booking found for expired session");
                         }
                         Err(e) => {
-                            log::error!("Error finding booking for session: {}", e);
+                            tracing::error!(chat_id = %chat_id, error = %e, "could not find booking for session");
                         }
                     }
                     
-                    if let Err(e) = state.save_user_state(chat_id, updated_state).await {
-                        log::error!("Error saving session state: {}", e);
+                    if let Err(e) = state.deactivate_session(chat_id).await {
+                        tracing::error!(chat_id = %chat_id, error = %e, "could not deactivate expired session");
                     }
                     
-                    log::info!("Session expired for user {}", chat_id);
+                    tracing::info!(chat_id = %chat_id, "session expired");
+
+                    let lang = user_state.locale();
+                    if let Err(e) = bot.send_message(chat_id, tr!(lang, "session.expired")).await {
+                        tracing::warn!(chat_id = %chat_id, error = %e, "could not notify user about expired session");
+                    }
+                    sessions::finalize_session_in_background(&state, session);
+                    if let Err(e) = feedback::ask_session_rating(&bot, &state, session).await {
+                        tracing::warn!(chat_id = %chat_id, error = %e, "could not ask user for rating");
+                    }
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Удаляет переписку и данные неактивных пользователей по истечении сроков хранения
+pub async fn retention_task(state: BotState) {
+    let config = retention::config();
+    if !config.enabled {
+        tracing::info!("data retention policy is disabled");
+        return;
+    }
+
+    let period = tokio::time::Duration::from_secs(config.check_interval_minutes.max(1) * 60);
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+        health::heartbeat("retention", period);
+
+        match retention::enforce(&state, config.dry_run).await {
+            Ok(report) if config.dry_run || !report.is_empty() => report.trace(),
+            Ok(_) => {}
+            Err(e) => tracing::error!(error = %e, "could not enforce data retention policy"),
+        }
+    }
+}
+
+/// Отправляет пользователям напоминания, поставленные консультантами
+pub async fn reminders_task(bot: Bot, state: BotState) {
+    let period = tokio::time::Duration::from_secs(settings::config().timeouts.reminders_interval_seconds);
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+        health::heartbeat("reminders", period);
+
+        let reminders = match Reminder::due(&state).await {
+            Ok(reminders) => reminders,
+            Err(e) => {
+                tracing::error!(error = %e, "could not fetch due reminders");
+                continue;
+            }
+        };
+
+        for reminder in reminders {
+            let lang = i18n::user_locale(&state, ChatId(reminder.chat_id)).await;
+            let text = tr!(lang, "reminders.notification", text = reminder.text);
+            if let Err(e) = bot.send_message(ChatId(reminder.chat_id), text).await {
+                // Попробуем еще раз при следующей проверке
+                tracing::warn!(chat_id = reminder.chat_id, reminder_id = reminder.id, error = %e, "could not send reminder");
+                continue;
+            }
+
+            if let Err(e) = Reminder::mark_sent(&state, reminder.id).await {
+                tracing::error!(chat_id = reminder.chat_id, reminder_id = reminder.id, error = %e, "could not mark reminder as sent");
+            }
+        }
+    }
+}
+
+/// Рассылает ежедневные опросы о настроении в локальное время пользователей
+pub async fn mood_checkins_task(bot: Bot, state: BotState) {
+    let period = tokio::time::Duration::from_secs(60);
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+        health::heartbeat("mood_checkins", period);
+
+        let checkins = match MoodCheckin::all_enabled(&state).await {
+            Ok(checkins) => checkins,
+            Err(e) => {
+                tracing::error!(error = %e, "could not fetch mood check-ins");
+                continue;
+            }
+        };
+
+        for checkin in checkins.iter().filter(|c| c.is_due()) {
+            mood::send_checkin(&bot, &state, checkin).await;
+        }
+    }
+}
+
+/// Отправляет рассылки из очереди; прерванная перезапуском рассылка продолжается с того же места
+pub async fn broadcast_task(bot: Bot, state: BotState) {
+    let period = tokio::time::Duration::from_secs(crate::broadcast::config().poll_interval_seconds.max(1));
+    let mut interval = tokio::time::interval(period);
+
+    loop {
+        interval.tick().await;
+        health::heartbeat("broadcast", period);
+
+        if let Err(e) = crate::broadcast::process_next(&bot, &state, || health::heartbeat("broadcast", period)).await {
+            tracing::error!(error = %e, "could not process broadcast queue");
+        }
+    }
+}
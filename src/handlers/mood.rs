@@ -0,0 +1,441 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, MessageId, ParseMode};
+use std::error::Error;
+use chrono::{Datelike, Duration, FixedOffset, NaiveDate, Utc};
+
+use crate::bot_state::BotState;
+use crate::i18n::{self, Locale};
+use crate::tr;
+use crate::models::{MoodCheckin, MoodEntry, PendingInput};
+use crate::handlers::utils::escape_markdown_v2;
+
+/// Часовой пояс по умолчанию для опроса (Москва)
+const DEFAULT_UTC_OFFSET_MINUTES: i32 = 180;
+/// Максимальная длина комментария к настроению
+const MAX_NOTE_LENGTH: usize = 500;
+
+/// Период графика настроения
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoodPeriod {
+    Week,
+    Month,
+}
+
+/// Клавиатура выбора оценки настроения
+pub fn make_mood_score_keyboard() -> InlineKeyboardMarkup {
+    let row = |range: std::ops::RangeInclusive<i16>| {
+        range
+            .map(|n| InlineKeyboardButton::callback(n.to_string(), format!("mood_score_{}", n)))
+            .collect::<Vec<_>>()
+    };
+
+    InlineKeyboardMarkup::new(vec![row(1..=5), row(6..=10)])
+}
+
+fn make_mood_keyboard(period: MoodPeriod, lang: Locale) -> InlineKeyboardMarkup {
+    let period_button = match period {
+        MoodPeriod::Week => InlineKeyboardButton::callback(tr!(lang, "mood.month_button"), "mood_month"),
+        MoodPeriod::Month => InlineKeyboardButton::callback(tr!(lang, "mood.week_button"), "mood_week"),
+    };
+
+    InlineKeyboardMarkup::new(vec![
+        vec![InlineKeyboardButton::callback(tr!(lang, "mood.log_button"), "mood_log")],
+        vec![period_button],
+        vec![InlineKeyboardButton::callback(tr!(lang, "mood.checkin_button"), "mood_checkin")],
+    ])
+}
+
+fn make_checkin_cancel_keyboard(lang: Locale) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(tr!(lang, "common.cancel"), "mood_checkin_cancel"),
+    ]])
+}
+
+fn bar(score: f64) -> String {
+    let filled = score.round().clamp(0.0, 10.0) as usize;
+    format!("{}{}", "█".repeat(filled), "░".repeat(10 - filled))
+}
+
+fn average(entries: &[&MoodEntry]) -> Option<f64> {
+    if entries.is_empty() {
+        None
+    } else {
+        Some(entries.iter().map(|e| e.score as f64).sum::<f64>() / entries.len() as f64)
+    }
+}
+
+/// Текстовый график настроения: по дням за неделю или по неделям за месяц
+fn format_mood_chart(entries: &[MoodEntry], offset: FixedOffset, period: MoodPeriod, lang: Locale) -> String {
+    let today = Utc::now().with_timezone(&offset).date_naive();
+    let local_date = |entry: &MoodEntry| entry.created_at.with_timezone(&offset).date_naive();
+    let weekdays = tr!(lang, "mood.weekdays");
+    let weekdays: Vec<&str> = weekdays.split(',').collect();
+
+    let (title, buckets): (&str, Vec<(String, NaiveDate, NaiveDate)>) = match period {
+        MoodPeriod::Week => (
+            "mood.title_week",
+            (0..7)
+                .rev()
+                .map(|days| {
+                    let day = today - Duration::days(days);
+                    let label = format!(
+                        "{} {}",
+                        weekdays.get(day.weekday().num_days_from_monday() as usize).unwrap_or(&""),
+                        day.format("%d.%m")
+                    );
+                    (label, day, day)
+                })
+                .collect(),
+        ),
+        MoodPeriod::Month => (
+            "mood.title_month",
+            (0..4)
+                .rev()
+                .map(|weeks| {
+                    let end = today - Duration::days(weeks * 7);
+                    let start = end - Duration::days(6);
+                    let label = format!("{}–{}", start.format("%d.%m"), end.format("%d.%m"));
+                    (label, start, end)
+                })
+                .collect(),
+        ),
+    };
+
+    let mut chart = String::new();
+    for (label, start, end) in &buckets {
+        let bucket: Vec<&MoodEntry> = entries
+            .iter()
+            .filter(|e| (*start..=*end).contains(&local_date(e)))
+            .collect();
+
+        match average(&bucket) {
+            Some(avg) => chart.push_str(&format!("{:<11} {} {:.1}\n", label, bar(avg), avg)),
+            None => chart.push_str(&format!("{:<11} {} —\n", label, "·".repeat(10))),
+        }
+    }
+
+    let all: Vec<&MoodEntry> = entries.iter().collect();
+    let summary = match average(&all) {
+        Some(avg) => tr!(lang, "mood.summary", average = format!("{:.1}", avg), count = entries.len()),
+        None => tr!(lang, "mood.no_entries"),
+    };
+
+    format!(
+        "{}\n\n```\n{}```\n{}",
+        tr!(lang, title),
+        chart,
+        escape_markdown_v2(&summary)
+    )
+}
+
+async fn mood_chart_text(state: &BotState, chat_id: ChatId, period: MoodPeriod, lang: Locale) -> String {
+    let days = match period {
+        MoodPeriod::Week => 7,
+        MoodPeriod::Month => 28,
+    };
+
+    let offset_minutes = MoodCheckin::get(state, chat_id).await
+        .map(|c| c.utc_offset_minutes)
+        .unwrap_or(DEFAULT_UTC_OFFSET_MINUTES);
+    let offset = FixedOffset::east_opt(offset_minutes * 60).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+
+    let entries = MoodEntry::list_since(state, chat_id, Utc::now() - Duration::days(days)).await;
+    format_mood_chart(&entries, offset, period, lang)
+}
+
+/// Показывает график настроения
+pub async fn show_mood(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    bot.send_message(chat_id, mood_chart_text(state, chat_id, MoodPeriod::Week, lang).await)
+        .parse_mode(ParseMode::MarkdownV2)
+        .reply_markup(make_mood_keyboard(MoodPeriod::Week, lang))
+        .await?;
+
+    Ok(())
+}
+
+async fn set_pending_input(
+    state: &BotState,
+    chat_id: ChatId,
+    pending: Option<PendingInput>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut user_state = state.get_user_state(chat_id).await?;
+    user_state.pending_input = pending;
+    state.save_user_state(chat_id, user_state).await?;
+    Ok(())
+}
+
+/// Обрабатывает кнопки дневника настроения
+pub async fn handle_mood_callback(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    message_id: MessageId,
+    data: &str,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match data {
+        "mood_week" | "mood_month" => {
+            let period = if data == "mood_week" { MoodPeriod::Week } else { MoodPeriod::Month };
+            bot.edit_message_text(chat_id, message_id, mood_chart_text(state, chat_id, period, lang).await)
+                .parse_mode(ParseMode::MarkdownV2)
+                .reply_markup(make_mood_keyboard(period, lang))
+                .await?;
+        }
+        "mood_log" => {
+            bot.send_message(chat_id, tr!(lang, "mood.ask_score"))
+                .reply_markup(make_mood_score_keyboard())
+                .await?;
+        }
+        "mood_note_skip" => {
+            set_pending_input(state, chat_id, None).await?;
+            bot.edit_message_text(chat_id, message_id, tr!(lang, "mood.saved")).await?;
+        }
+        "mood_checkin_cancel" => {
+            set_pending_input(state, chat_id, None).await?;
+            bot.edit_message_text(chat_id, message_id, tr!(lang, "mood.input_cancelled")).await?;
+        }
+        "mood_checkin" => {
+            let text = match MoodCheckin::get(state, chat_id).await.filter(|c| c.is_enabled) {
+                Some(checkin) => tr!(lang, "mood.checkin_enabled", time = checkin.describe()),
+                None => tr!(lang, "mood.checkin_disabled"),
+            };
+
+            bot.send_message(chat_id, text)
+                .reply_markup(InlineKeyboardMarkup::new(vec![
+                    vec![InlineKeyboardButton::callback(tr!(lang, "mood.choose_time_button"), "mood_checkin_set")],
+                    vec![InlineKeyboardButton::callback(tr!(lang, "mood.disable_button"), "mood_checkin_off")],
+                ]))
+                .await?;
+        }
+        "mood_checkin_set" => {
+            set_pending_input(state, chat_id, Some(PendingInput::MoodCheckinTime { requested_at: Some(Utc::now()) })).await?;
+            bot.send_message(chat_id, tr!(lang, "mood.ask_time"))
+                .reply_markup(make_checkin_cancel_keyboard(lang))
+                .await?;
+        }
+        "mood_checkin_off" => {
+            if let Err(e) = MoodCheckin::disable(state, chat_id).await {
+                tracing::error!(chat_id = %chat_id, error = %e, "could not disable mood check-in");
+            }
+            bot.edit_message_text(chat_id, message_id, tr!(lang, "mood.checkin_off")).await?;
+        }
+        data => {
+            let Some(score) = data.strip_prefix("mood_score_").and_then(|n| n.parse::<i16>().ok()) else {
+                return Ok(());
+            };
+
+            let entry = match MoodEntry::create(state, chat_id, score, None).await {
+                Ok(entry) => entry,
+                Err(e) => {
+                    tracing::error!(chat_id = %chat_id, error = %e, "could not save mood entry");
+                    bot.send_message(chat_id, tr!(lang, "mood.save_failed")).await?;
+                    return Ok(());
+                }
+            };
+
+            tracing::info!(chat_id = %chat_id, score = entry.score, "mood logged");
+            let pending = PendingInput::MoodNote { entry_id: entry.id, requested_at: Some(Utc::now()) };
+            set_pending_input(state, chat_id, Some(pending)).await?;
+
+            bot.edit_message_text(
+                chat_id,
+                message_id,
+                tr!(lang, "mood.recorded", score = entry.score),
+            )
+            .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+                InlineKeyboardButton::callback(tr!(lang, "common.skip"), "mood_note_skip"),
+            ]]))
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Сохраняет комментарий к записи о настроении
+pub async fn save_mood_note(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    entry_id: i32,
+    text: &str,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let note: String = text.trim().chars().take(MAX_NOTE_LENGTH).collect();
+
+    if let Err(e) = MoodEntry::set_note(state, chat_id, entry_id, &note).await {
+        tracing::error!(chat_id = %chat_id, entry_id, error = %e, "could not save mood note");
+    }
+    set_pending_input(state, chat_id, None).await?;
+
+    bot.send_message(chat_id, tr!(lang, "mood.note_saved")).await?;
+
+    Ok(())
+}
+
+/// Разбирает время и смещение часового пояса: "21:00 +3", "9 -5", "08:30 UTC+5:30"
+fn parse_checkin_time(text: &str) -> Option<(i32, Option<i32>)> {
+    let mut parts = text.split_whitespace();
+
+    let time = parts.next()?;
+    let (hours, minutes) = match time.split_once(':') {
+        Some((h, m)) => (h.parse::<i32>().ok()?, m.parse::<i32>().ok()?),
+        None => (time.parse::<i32>().ok()?, 0),
+    };
+    if !(0..24).contains(&hours) || !(0..60).contains(&minutes) {
+        return None;
+    }
+
+    let offset = match parts.next() {
+        Some(offset) => {
+            let offset = offset.trim_start_matches("UTC").trim_start_matches("utc");
+            let (sign, rest) = match offset.strip_prefix('-') {
+                Some(rest) => (-1, rest),
+                None => (1, offset.strip_prefix('+').unwrap_or(offset)),
+            };
+            let (h, m) = match rest.split_once(':') {
+                Some((h, m)) => (h.parse::<i32>().ok()?, m.parse::<i32>().ok()?),
+                None => (rest.parse::<i32>().ok()?, 0),
+            };
+            let offset = sign * (h * 60 + m);
+            if !(-12 * 60..=14 * 60).contains(&offset) {
+                return None;
+            }
+            Some(offset)
+        }
+        None => None,
+    };
+
+    Some((hours * 60 + minutes, offset))
+}
+
+/// Сохраняет время ежедневного опроса
+pub async fn save_checkin_time(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    text: &str,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let Some((local_minute, offset)) = parse_checkin_time(text) else {
+        bot.send_message(chat_id, tr!(lang, "mood.parse_failed"))
+            .reply_markup(make_checkin_cancel_keyboard(lang))
+            .await?;
+        return Ok(());
+    };
+
+    let offset = match offset {
+        Some(offset) => offset,
+        None => MoodCheckin::get(state, chat_id).await
+            .map(|c| c.utc_offset_minutes)
+            .unwrap_or(DEFAULT_UTC_OFFSET_MINUTES),
+    };
+
+    set_pending_input(state, chat_id, None).await?;
+
+    let mut checkin = MoodCheckin {
+        chat_id: chat_id.0,
+        local_minute,
+        utc_offset_minutes: offset,
+        is_enabled: true,
+        last_sent_on: None,
+    };
+    // Если сегодняшнее время уже прошло, первый опрос придет завтра
+    if checkin.is_due() {
+        checkin.last_sent_on = Some(checkin.local_now().date_naive());
+    }
+
+    if let Err(e) = checkin.enable(state).await {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not save mood check-in");
+        bot.send_message(chat_id, tr!(lang, "common.save_failed")).await?;
+        return Ok(());
+    }
+
+    bot.send_message(chat_id, tr!(lang, "mood.checkin_set", time = checkin.describe())).await?;
+
+    Ok(())
+}
+
+/// Отправляет ежедневный опрос о настроении
+pub async fn send_checkin(bot: &Bot, state: &BotState, checkin: &MoodCheckin) {
+    let chat_id = ChatId(checkin.chat_id);
+    let lang = i18n::user_locale(state, chat_id).await;
+
+    if let Err(e) = bot
+        .send_message(chat_id, tr!(lang, "mood.checkin_question"))
+        .reply_markup(make_mood_score_keyboard())
+        .await
+    {
+        // Не отмечаем отправку: опрос повторится при следующей проверке
+        tracing::warn!(chat_id = %chat_id, error = %e, "could not send mood check-in");
+        return;
+    }
+
+    if let Err(e) = MoodCheckin::mark_sent(state, checkin.chat_id, checkin.local_now().date_naive()).await {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not mark mood check-in as sent");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: i16, days_ago: i64) -> MoodEntry {
+        MoodEntry {
+            id: 1,
+            chat_id: 1,
+            score,
+            note: None,
+            created_at: Utc::now() - Duration::days(days_ago),
+        }
+    }
+
+    fn utc() -> FixedOffset {
+        FixedOffset::east_opt(0).unwrap()
+    }
+
+    #[test]
+    fn parses_checkin_time_with_and_without_offset() {
+        assert_eq!(parse_checkin_time("21:00 +3"), Some((21 * 60, Some(180))));
+        assert_eq!(parse_checkin_time("9 -5"), Some((9 * 60, Some(-300))));
+        assert_eq!(parse_checkin_time("08:30 UTC+5:30"), Some((8 * 60 + 30, Some(330))));
+        assert_eq!(parse_checkin_time("7:15"), Some((7 * 60 + 15, None)));
+    }
+
+    #[test]
+    fn rejects_invalid_checkin_time() {
+        assert_eq!(parse_checkin_time(""), None);
+        assert_eq!(parse_checkin_time("24:00"), None);
+        assert_eq!(parse_checkin_time("12:60"), None);
+        assert_eq!(parse_checkin_time("вечером"), None);
+        assert_eq!(parse_checkin_time("21:00 +15"), None);
+        assert_eq!(parse_checkin_time("21:00 -13"), None);
+    }
+
+    #[test]
+    fn weekly_chart_averages_entries_by_day() {
+        let entries = vec![entry(8, 0), entry(6, 0), entry(3, 2)];
+        let chart = format_mood_chart(&entries, utc(), MoodPeriod::Week, Locale::En);
+
+        let rows: Vec<&str> = chart.lines().filter(|l| l.contains('█') || l.contains('·')).collect();
+        assert_eq!(rows.len(), 7);
+        assert!(rows[6].ends_with("███████░░░ 7.0"), "{}", rows[6]);
+        assert!(rows[4].ends_with("███░░░░░░░ 3.0"), "{}", rows[4]);
+        assert!(rows[5].ends_with(" —"), "{}", rows[5]);
+        assert!(chart.contains(&escape_markdown_v2(&tr!(Locale::En, "mood.summary", average = "5.7", count = 3))));
+    }
+
+    #[test]
+    fn monthly_chart_has_four_weeks_and_empty_summary() {
+        let chart = format_mood_chart(&[], utc(), MoodPeriod::Month, Locale::Ru);
+
+        assert_eq!(chart.lines().filter(|l| l.contains('·')).count(), 4);
+        assert!(chart.starts_with(&tr!(Locale::Ru, "mood.title_month")));
+        assert!(chart.ends_with(&escape_markdown_v2(&tr!(Locale::Ru, "mood.no_entries"))));
+    }
+}
@@ -1,11 +1,16 @@
 use teloxide::prelude::*;
-use teloxide::types::{LabeledPrice, ParseMode};
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, LabeledPrice, ParseMode};
 use std::error::Error;
 use chrono::{Utc, Duration};
 
+use crate::analytics;
 use crate::bot_state::BotState;
-use crate::models::{PaymentConfig, Booking, AIAssistant, UserSession};
-use crate::handlers::utils::{escape_markdown_v2, send_ai_message};
+use crate::metrics;
+use crate::i18n::{self, Locale};
+use crate::tr;
+use crate::models::{PaymentConfig, Booking, AIAssistant, SessionArchive, UserSession};
+use crate::models::message_bundle::MESSAGE_BUNDLE_VALIDITY_DAYS;
+use crate::handlers::utils::{escape_markdown_v2, make_session_management_keyboard, send_ai_message};
 
 pub async fn send_stars_invoice(
     bot: &Bot,
@@ -13,26 +18,47 @@ pub async fn send_stars_invoice(
     booking: &Booking,
     assistant: &AIAssistant,
     payment_config: &PaymentConfig,
+    lang: Locale,
 ) -> Result<Message, Box<dyn Error + Send + Sync>> {
-    let total_price_stars = (booking.total_price * 100.0) as i32; // Конвертируем в Stars (1 USD = 100 Stars)
+    let total_price_stars = (booking.amount_due() * 100.0) as i32; // Конвертируем в Stars (1 USD = 100 Stars)
 
-    let description = format!(
-        "Сессия с консультантом\nКонсультант: {}\nДлительность: {} минут\n⭐ Стоимость: {} Stars",
-        assistant.name,
-        booking.duration_minutes,
-        total_price_stars
+    let (volume, volume_short) = match booking.message_quota {
+        Some(quota) => (
+            tr!(lang, "payment.volume_messages", count = quota),
+            tr!(lang, "units.messages_short", count = quota),
+        ),
+        None => (
+            tr!(lang, "payment.volume_minutes", minutes = booking.duration_minutes),
+            tr!(lang, "units.minutes", count = booking.duration_minutes),
+        ),
+    };
+
+    let mut description = tr!(
+        lang,
+        "payment.description",
+        name = assistant.name,
+        volume = volume,
+        stars = total_price_stars,
     );
+    if booking.credit_applied > 0.0 {
+        description.push('\n');
+        description.push_str(&tr!(lang, "payment.from_balance", stars = (booking.credit_applied * 100.0) as i32));
+    }
 
-    let title = format!("Сессия с консультантом {}", assistant.name);
+    let title = tr!(lang, "payment.title", name = assistant.name);
 
     let prices = vec![LabeledPrice {
-        label: format!("Сессия {} ({} мин)", assistant.name, booking.duration_minutes),
+        label: tr!(lang, "payment.label", name = assistant.name, volume = volume_short),
         amount: total_price_stars as u32
     }];
 
-    log::info!("🔄 Sending Stars invoice for booking {} to chat {}", booking.id, chat_id);
-    log::info!("Invoice payload: {}", booking.invoice_payload);
-    log::info!("Prices {:?}", prices);
+    tracing::info!(
+        chat_id = %chat_id,
+        booking_id = %booking.id,
+        payload = %booking.invoice_payload,
+        prices = ?prices,
+        "sending stars invoice"
+    );
 
     let invoice = bot
         .send_invoice(
@@ -51,7 +77,8 @@ pub async fn send_stars_invoice(
         .send()
         .await?;
 
-    log::info!("✅ Stars invoice sent successfully for booking {}", booking.id);
+    tracing::info!(chat_id = %chat_id, booking_id = %booking.id, "stars invoice sent");
+    metrics::invoice_sent();
 
     Ok(invoice)
 }
@@ -66,138 +93,85 @@ pub async fn successful_payment_handler(
     if let Some(successful_payment) = msg.successful_payment() {
         let chat_id = msg.chat.id;
         let invoice_payload = &successful_payment.invoice_payload;
+        let lang = i18n::locale_for(&state, chat_id, msg.from.as_ref()).await;
 
-        log::info!("🎉 Payment received! Currency: {}, Total: {}", 
-            successful_payment.currency, 
-            successful_payment.total_amount);
+        tracing::info!(
+            chat_id = %chat_id,
+            currency = %successful_payment.currency,
+            total = successful_payment.total_amount,
+            "payment received"
+        );
 
         // Находим бронирование в отдельной таблице
         let booking = match state.get_booking_by_payload(invoice_payload).await {
             Ok(Some(booking)) => {
-                log::info!("✅ Found booking: {}", booking.id);
-                log::info!("Booking details: assistant_id={}, duration={}min, is_paid={}", 
-                    booking.assistant_id, booking.duration_minutes, booking.is_paid);
+                tracing::info!(
+                    booking_id = %booking.id,
+                    assistant_id = booking.assistant_id,
+                    duration_minutes = booking.duration_minutes,
+                    is_paid = booking.is_paid,
+                    "booking found for payment"
+                );
                 booking
             },
             Ok(None) => {
-                log::warn!("❌ No booking found for payload: {}", invoice_payload);
+                tracing::warn!(chat_id = %chat_id, payload = %invoice_payload, "no booking found for payment");
                 
                 // Попробуем найти по всем бронированиям пользователя
                 if let Ok(user_bookings) = state.get_user_bookings(chat_id).await {
-                    log::info!("User bookings count: {}", user_bookings.len());
+                    tracing::info!(chat_id = %chat_id, bookings = user_bookings.len(), "user bookings");
                     for b in user_bookings {
-                        log::info!("  Booking: id={}, payload={}, paid={}", b.id, b.invoice_payload, b.is_paid);
+                        tracing::info!(booking_id = %b.id, payload = %b.invoice_payload, is_paid = b.is_paid, "user booking");
                     }
                 }
                 
-                bot.send_message(chat_id, "⚠️ Бронирование не найдено. Свяжитесь с поддержкой.")
+                bot.send_message(chat_id, tr!(lang, "payment.booking_not_found"))
                     .await?;
                 return Ok(());
             }
             Err(e) => {
-                log::error!("❌ Error finding booking: {}", e);
-                bot.send_message(chat_id, "⚠️ Ошибка при поиске бронирования. Свяжитесь с поддержкой.")
+                tracing::error!(chat_id = %chat_id, error = %e, "could not find booking for payment");
+                bot.send_message(chat_id, tr!(lang, "payment.lookup_error"))
                     .await?;
                 return Ok(());
             }
         };
         
         if booking.is_paid {
-            log::warn!("⚠️ Booking already paid: {}", booking.id);
-            bot.send_message(chat_id, "ℹ️ Это бронирование уже было оплачено ранее.")
+            tracing::warn!(chat_id = %chat_id, booking_id = %booking.id, "booking already paid");
+            bot.send_message(chat_id, tr!(lang, "payment.already_paid"))
                 .await?;
             return Ok(());
         }
         
-        log::info!("🔄 Activating booking: {}", booking.id);
+        tracing::info!(chat_id = %chat_id, booking_id = %booking.id, "activating booking");
         
         // Обновляем бронирование
         let mut updated_booking = booking.clone();
         updated_booking.is_paid = true;
         updated_booking.is_completed = false;
         updated_booking.expires_at = None; // Убираем срок истечения для оплаченных броней
+        updated_booking.telegram_payment_charge_id = Some(successful_payment.telegram_payment_charge_id.0.clone());
         
         if let Err(e) = state.save_booking(&updated_booking).await {
-            log::error!("❌ Error updating booking: {}", e);
-            bot.send_message(chat_id, "⚠️ Ошибка при обновлении статуса бронирования. Свяжитесь с поддержкой.")
+            tracing::error!(chat_id = %chat_id, booking_id = %booking.id, error = %e, "could not mark booking as paid");
+            bot.send_message(chat_id, tr!(lang, "payment.update_error"))
                 .await?;
             return Ok(());
         }
         
-        log::info!("✅ Booking updated successfully: {}", updated_booking.id);
-        
-        // Получаем консультанта по ID из бронирования
-        let assistant = AIAssistant::find_by_id_with_price(&state, booking.assistant_id).await
-            .unwrap_or_else(|| {
-                AIAssistant {
-                    id: 1,
-                    name: "Анна".to_string(),
-                    model: "GigaChat-2-Max".to_string(),
-                    description: "Интерактивный помощник".to_string(),
-                    specialty: "Общение и поддержка".to_string(),
-                    greeting: "Здравствуйте!".to_string(),
-                    prompt: "Ты помощник.".to_string(),
-                    price_per_minute: 0.1,
-                }
-            });
+        tracing::info!(chat_id = %chat_id, booking_id = %updated_booking.id, "booking marked as paid");
+        metrics::invoice_paid();
 
-        let mut user_state = state.get_user_state(chat_id).await;
-        
-        // Создаем активную сессию
-        let session = UserSession {
-            chat_id,
-            assistant_id: booking.assistant_id, // Сохраняем ID консультанта
-            session_start: Utc::now(),
-            paid_until: Utc::now() + Duration::minutes(booking.duration_minutes as i64),
-            total_price: booking.total_price,
-            messages_exchanged: 0,
-            is_active: true,
-            history: Vec::new(),
-            scheduled_start: None,
-        };
-        user_state.current_session = Some(session);
-        
-        let message_text = format!(
-            "✅ *Оплата прошла успешно\\!*\n\n\
-            *Сессия началась*\n\
-            *Консультант:* {}\n\
-            *Доступное время:* {} мин\n\
-            *Стоимость:* {} Stars\n\n\
-            Теперь вы можете общаться с консультантом\\.",
-            escape_markdown_v2(&assistant.name),
-            booking.duration_minutes,
-            (booking.total_price * 100.0) as i32
-        );
-        
-        bot.send_message(chat_id, &message_text)
-            .parse_mode(ParseMode::MarkdownV2)
-            .await?;
-        
-        send_ai_message(&bot, chat_id, &assistant.name, &escape_markdown_v2(&assistant.greeting)).await?;
-        
-        log::info!("🎯 New active session created for user {}", chat_id);
-        
-        // Удаляем сообщение с инвойсом если есть
-        if let Some(invoice_msg_id) = booking.payment_invoice_message_id {
-            match bot.delete_message(chat_id, invoice_msg_id).await {
-                Ok(_) => log::info!("🗑️ Deleted invoice message"),
-                Err(e) => log::warn!("⚠️ Could not delete invoice message: {}", e),
-            }
-        }
-        
-        // Сохраняем состояние пользователя
-        if let Err(e) = state.save_user_state(chat_id, user_state).await {
-            log::error!("❌ Error saving user state: {}", e);
-            bot.send_message(chat_id, "⚠️ Ошибка при сохранении состояния. Сессия может работать некорректно.")
-                .await?;
-        } else {
-            log::info!("💾 User state saved successfully for chat {}", chat_id);
-        }
+        bot.send_message(chat_id, tr!(lang, "payment.success")).await?;
+
+        start_or_keep_booking(&bot, &state, &updated_booking, lang).await?;
         
-        log::info!("🎊 PAYMENT PROCESSING COMPLETED SUCCESSFULLY!");
+        tracing::info!(chat_id = %chat_id, "payment processed");
         
     } else {
-        bot.send_message( msg.chat.id, "⚠️ Не удалось обработать данные оплаты. Свяжитесь с поддержкой.")
+        let lang = i18n::user_locale(&state, msg.chat.id).await;
+        bot.send_message( msg.chat.id, tr!(lang, "payment.invalid"))
             .await?;
     }
     
@@ -210,35 +184,240 @@ pub async fn pre_checkout_handler(
     state: BotState,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     let invoice_payload = &q.invoice_payload;
+    let lang = i18n::locale_for(&state, ChatId(q.from.id.0 as i64), Some(&q.from)).await;
     
     match state.get_booking_by_payload(invoice_payload).await {
         Ok(Some(booking)) => {
             if booking.is_paid {
-                log::warn!("Booking already paid: {}", booking.id);
+                tracing::warn!(booking_id = %booking.id, "pre-checkout for a booking already paid");
                 bot.answer_pre_checkout_query(q.id, false)
-                    .error_message("Бронирование уже оплачено".to_string())
+                    .error_message(tr!(lang, "payment.precheckout_already_paid"))
                     .await?;
             } else {
-                log::info!("✅ Confirming pre-checkout for booking: {}", booking.id);
+                tracing::info!(booking_id = %booking.id, "confirming pre-checkout");
                 match bot.answer_pre_checkout_query(q.id, true).await {
-                    Ok(_) => log::info!("✅ Pre-checkout confirmed"),
-                    Err(e) => log::error!("❌ Error confirming pre-checkout: {}", e),
+                    Ok(_) => tracing::info!("pre-checkout confirmed"),
+                    Err(e) => tracing::error!(error = %e, "could not confirm pre-checkout"),
                 }
             }
         }
         Ok(None) => {
-            log::warn!("❌ Booking not found for payload: {}", invoice_payload);
+            tracing::warn!(payload = %invoice_payload, "no booking found for pre-checkout");
             bot.answer_pre_checkout_query(q.id, false)
-                .error_message("Бронирование не найдено".to_string())
+                .error_message(tr!(lang, "payment.precheckout_not_found"))
                 .await?;
         }
         Err(e) => {
-            log::error!("❌ Error finding booking: {}", e);
+            tracing::error!(error = %e, "could not find booking for pre-checkout");
             bot.answer_pre_checkout_query(q.id, false)
-                .error_message("Ошибка при проверке бронирования".to_string())
+                .error_message(tr!(lang, "payment.precheckout_error"))
                 .await?;
         }
     }
     
     Ok(())
 }
+
+/// Возвращает пользователю Stars, оплаченные по бронированию
+pub async fn refund_booking_payment(
+    bot: &Bot,
+    booking: &Booking,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let charge_id = booking.telegram_payment_charge_id.clone()
+        .ok_or("Booking has no payment to refund")?;
+
+    bot.refund_star_payment(UserId(booking.user_id.0 as u64), charge_id.into()).await?;
+
+    tracing::info!(chat_id = %booking.user_id, booking_id = %booking.id, "booking refunded");
+    Ok(())
+}
+
+/// Возвращает оплату брони: Stars через Telegram, часть с баланса — на баланс
+pub async fn refund_booking(
+    bot: &Bot,
+    state: &BotState,
+    booking: &mut Booking,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // Бронь, полностью оплаченная с баланса, возвращается только на баланс
+    if booking.telegram_payment_charge_id.is_some() {
+        refund_booking_payment(bot, booking).await?;
+    }
+
+    booking.is_refunded = true;
+    booking.is_completed = true;
+    if let Err(e) = state.save_booking(booking).await {
+        tracing::error!(chat_id = %booking.user_id, booking_id = %booking.id, error = %e, "could not save refunded booking");
+    }
+
+    // Часть, оплаченная с баланса, возвращается на баланс, если ее успели списать
+    let credit = booking.credit_to_return();
+    if credit > 0.0
+        && let Err(e) = state.add_balance(booking.user_id, credit).await
+    {
+        tracing::error!(chat_id = %booking.user_id, booking_id = %booking.id, error = %e, "could not return balance credit");
+    }
+
+    Ok(())
+}
+
+/// Запускает оплаченную бронь, а если идет другая сессия — сохраняет бронь,
+/// пока пользователь сам ее не начнет
+pub async fn start_or_keep_booking(
+    bot: &Bot,
+    state: &BotState,
+    booking: &Booking,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = booking.user_id;
+    let user_state = state.get_user_state(chat_id).await?;
+    if !user_state.current_session.as_ref().is_some_and(|s| s.can_chat()) {
+        return activate_booking(bot, state, booking, lang).await;
+    }
+
+    bot.send_message(chat_id, tr!(lang, "payment.saved_for_later"))
+    .parse_mode(ParseMode::MarkdownV2)
+    .reply_markup(InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(tr!(lang, "payment.start_now_button"), format!("start_booking_{}", booking.id)),
+    ]]))
+    .await?;
+
+    if let Some(invoice_msg_id) = booking.payment_invoice_message_id {
+        let _ = bot.delete_message(chat_id, invoice_msg_id).await;
+    }
+
+    Ok(())
+}
+
+/// Запускает сессию по оплаченному бронированию
+pub async fn activate_booking(
+    bot: &Bot,
+    state: &BotState,
+    booking: &Booking,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chat_id = booking.user_id;
+
+    // Получаем консультанта по ID из бронирования
+    let Some(assistant) = AIAssistant::find_localized(state, booking.assistant_id, lang).await else {
+        bot.send_message(chat_id, tr!(lang, "consultants.not_found")).await?;
+        return Ok(());
+    };
+
+    // Брони, созданные до резервирования баланса, списывают свою часть при запуске.
+    // Если списать не удалось, сессию не начинаем: бронь остается в «Моих сессиях»
+    let mut started_booking = booking.clone();
+    if !booking.credit_reserved && booking.credit_applied > 0.0 {
+        match state.reserve_credit(chat_id, booking.credit_applied).await {
+            Ok(true) => started_booking.credit_reserved = true,
+            Ok(false) => {
+                tracing::warn!(chat_id = %chat_id, booking_id = %booking.id, "balance no longer covers booking credit");
+                bot.send_message(
+                    chat_id,
+                    tr!(lang, "payment.credit_not_covered", stars = (booking.credit_applied * 100.0) as i32),
+                )
+                .await?;
+                return Ok(());
+            }
+            Err(e) => {
+                tracing::error!(chat_id = %chat_id, booking_id = %booking.id, error = %e, "could not charge booking credit");
+                bot.send_message(chat_id, tr!(lang, "payment.credit_error")).await?;
+                return Ok(());
+            }
+        }
+    }
+
+    started_booking.started_at = Some(Utc::now());
+    if let Err(e) = state.save_booking(&started_booking).await {
+        tracing::error!(chat_id = %chat_id, booking_id = %booking.id, error = %e, "could not mark booking as started");
+    }
+
+    let mut user_state = state.get_user_state(chat_id).await?;
+    user_state.current_assistant_id = booking.assistant_id;
+    user_state.pending_input = None;
+
+    // Пакет сообщений действует ограниченное число дней, сессия по времени — оплаченные минуты
+    let paid_until = match booking.message_quota {
+        Some(_) => Utc::now() + Duration::days(MESSAGE_BUNDLE_VALIDITY_DAYS),
+        None => Utc::now() + Duration::minutes(booking.duration_minutes as i64),
+    };
+
+    // Цена единицы (минуты или сообщения) нужна для пересчета остатка
+    let unit_price = match booking.message_quota {
+        Some(quota) if quota > 0 => Some(booking.total_price / quota as f64),
+        None if booking.duration_minutes > 0 => Some(booking.total_price / booking.duration_minutes as f64),
+        _ => None,
+    };
+
+    // Создаем активную сессию
+    let session = UserSession {
+        chat_id,
+        assistant_id: booking.assistant_id, // Сохраняем ID консультанта
+        session_start: Utc::now(),
+        paid_until,
+        total_price: booking.total_price,
+        messages_exchanged: 0,
+        is_active: true,
+        history: Vec::new(),
+        scheduled_start: None,
+        message_quota: booking.message_quota,
+        booking_id: Some(booking.id.clone()),
+        unit_price,
+        flagged_for_review: false,
+        crisis_pause_applied: false,
+        crisis_pause_minutes: 0,
+    };
+    // Предыдущая сессия заменяется новой — ее история остается в архиве
+    if let Some(previous) = &user_state.current_session
+        && let Err(e) = SessionArchive::save(state, previous).await
+    {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not archive previous session");
+    }
+    if let Some(previous) = &user_state.current_session
+        && let Err(e) = analytics::record_session(state, previous).await
+    {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not record previous session stats");
+    }
+
+    user_state.current_session = Some(session);
+
+    let available = match booking.message_quota {
+        Some(quota) => tr!(lang, "session.available_messages", count = quota, days = MESSAGE_BUNDLE_VALIDITY_DAYS),
+        None => tr!(lang, "session.available_minutes", minutes = booking.duration_minutes),
+    };
+
+    let message_text = tr!(
+        lang,
+        "session.started",
+        name = escape_markdown_v2(&assistant.name),
+        available = available,
+        stars = (booking.total_price * 100.0) as i32,
+    );
+
+    bot.send_message(chat_id, &message_text)
+        .parse_mode(ParseMode::MarkdownV2)
+        .reply_markup(make_session_management_keyboard(&user_state))
+        .await?;
+
+    send_ai_message(bot, chat_id, &assistant.name, &escape_markdown_v2(&assistant.greeting)).await?;
+
+    tracing::info!(chat_id = %chat_id, booking_id = %booking.id, "session started");
+
+    // Удаляем сообщение с инвойсом если есть
+    if let Some(invoice_msg_id) = booking.payment_invoice_message_id {
+        match bot.delete_message(chat_id, invoice_msg_id).await {
+            Ok(_) => tracing::info!(chat_id = %chat_id, "invoice message deleted"),
+            Err(e) => tracing::warn!(chat_id = %chat_id, error = %e, "could not delete invoice message"),
+        }
+    }
+
+    // Сохраняем состояние пользователя
+    if let Err(e) = state.save_user_state(chat_id, user_state).await {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not save user state");
+        bot.send_message(chat_id, tr!(lang, "session.state_save_failed"))
+            .await?;
+    } else {
+        tracing::info!(chat_id = %chat_id, "user state saved");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, InputFile, MessageId};
+use std::error::Error;
+use chrono::Utc;
+
+use crate::bot_state::BotState;
+use crate::i18n::Locale;
+use crate::tr;
+use crate::privacy;
+use crate::retention;
+
+/// Отправляет пользователю JSON со всеми хранящимися о нем данными
+pub async fn send_user_data(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let data = match privacy::export_user_data(state, chat_id).await {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!(chat_id = %chat_id, error = %e, "could not export user data");
+            bot.send_message(chat_id, tr!(lang, "privacy.collect_failed")).await?;
+            return Ok(());
+        }
+    };
+
+    let document = InputFile::memory(serde_json::to_vec_pretty(&data)?)
+        .file_name(format!("my_data_{}.json", Utc::now().format("%Y-%m-%d")));
+
+    bot.send_document(chat_id, document)
+        .caption(tr!(lang, "privacy.data_caption"))
+        .await?;
+
+    tracing::info!(chat_id = %chat_id, "user data exported");
+
+    Ok(())
+}
+
+/// Просит подтвердить удаление данных
+pub async fn ask_delete_me(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let user_state = state.get_user_state(chat_id).await?;
+
+    if user_state.current_session.as_ref().is_some_and(|s| s.can_chat()) {
+        bot.send_message(chat_id, tr!(lang, "privacy.active_session")).await?;
+        return Ok(());
+    }
+
+    let balance_warning = if user_state.balance > 0.0 {
+        format!("\n\n{}", tr!(lang, "privacy.balance_warning", stars = (user_state.balance * 100.0) as i32))
+    } else {
+        String::new()
+    };
+
+    bot.send_message(
+        chat_id,
+        tr!(lang, "privacy.delete_confirm", balance_warning = balance_warning),
+    )
+    .reply_markup(InlineKeyboardMarkup::new(vec![
+        vec![InlineKeyboardButton::callback(tr!(lang, "privacy.confirm_button"), "deleteme_confirm")],
+        vec![InlineKeyboardButton::callback(tr!(lang, "privacy.cancel_button"), "deleteme_cancel")],
+    ]))
+    .await?;
+
+    Ok(())
+}
+
+/// Обрабатывает подтверждение удаления данных
+pub async fn handle_delete_me_callback(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    message_id: MessageId,
+    data: &str,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if data != "deleteme_confirm" {
+        bot.edit_message_text(chat_id, message_id, tr!(lang, "privacy.delete_cancelled")).await?;
+        return Ok(());
+    }
+
+    // Сессия могла начаться, пока пользователь подтверждал удаление
+    let user_state = state.get_user_state(chat_id).await?;
+    if user_state.current_session.as_ref().is_some_and(|s| s.can_chat()) {
+        bot.edit_message_text(chat_id, message_id, tr!(lang, "privacy.finish_session_first")).await?;
+        return Ok(());
+    }
+
+    match privacy::erase_user_data(state, chat_id).await {
+        Ok(report) => {
+            let payments_note = if report.retained_payments > 0 {
+                format!("\n\n{}", tr!(lang, "privacy.payments_retained", count = report.retained_payments))
+            } else {
+                String::new()
+            };
+
+            bot.edit_message_text(
+                chat_id,
+                message_id,
+                format!("{}{}", tr!(lang, "privacy.deleted"), payments_note),
+            )
+            .await?;
+        }
+        Err(e) => {
+            tracing::error!(chat_id = %chat_id, error = %e, "could not erase user data");
+            bot.send_message(chat_id, tr!(lang, "privacy.delete_failed")).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn describe_days(days: i32, lang: Locale) -> String {
+    if days > 0 { tr!(lang, "keephistory.days", days = days) } else { tr!(lang, "keephistory.forever") }
+}
+
+fn keep_history_text(keep: bool, lang: Locale) -> String {
+    let config = retention::config();
+    tr!(
+        lang,
+        "keephistory.text",
+        standard = describe_days(config.transcript_days, lang),
+        extended = describe_days(config.extended_transcript_days, lang),
+        current = tr!(lang, if keep { "keephistory.current_extended" } else { "keephistory.current_standard" }),
+    )
+}
+
+fn keep_history_keyboard(keep: bool, lang: Locale) -> InlineKeyboardMarkup {
+    let button = if keep {
+        InlineKeyboardButton::callback(tr!(lang, "keephistory.standard_button"), "keephistory_off")
+    } else {
+        InlineKeyboardButton::callback(tr!(lang, "keephistory.extend_button"), "keephistory_on")
+    };
+    InlineKeyboardMarkup::new(vec![vec![button]])
+}
+
+/// Показывает срок хранения переписки и позволяет продлить его
+pub async fn show_keep_history(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let keep = retention::keeps_history(state, chat_id).await;
+
+    bot.send_message(chat_id, keep_history_text(keep, lang))
+        .reply_markup(keep_history_keyboard(keep, lang))
+        .await?;
+
+    Ok(())
+}
+
+/// Обрабатывает переключение срока хранения
+pub async fn handle_keep_history_callback(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    message_id: MessageId,
+    data: &str,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let keep = data == "keephistory_on";
+
+    if let Err(e) = retention::set_keep_history(state, chat_id, keep).await {
+        tracing::error!(chat_id = %chat_id, keep, error = %e, "could not save keep_history");
+        bot.send_message(chat_id, tr!(lang, "common.save_failed")).await?;
+        return Ok(());
+    }
+
+    tracing::info!(chat_id = %chat_id, keep_history = keep, "history retention preference changed");
+
+    bot.edit_message_text(chat_id, message_id, keep_history_text(keep, lang))
+        .reply_markup(keep_history_keyboard(keep, lang))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,317 @@
+use teloxide::prelude::*;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, ParseMode};
+use std::error::Error;
+use chrono::Utc;
+
+use crate::bot_state::BotState;
+use crate::i18n::Locale;
+use crate::tr;
+use crate::llm::config::ChatMessage;
+use crate::analytics;
+use crate::memory;
+use crate::models::{AIAssistant, Booking, SessionArchive, UserSession, UserState};
+use crate::models::session::SwitchQuote;
+use crate::handlers::feedback::ask_session_rating;
+use crate::handlers::payments::refund_booking_payment;
+use crate::handlers::utils::{build_system_prompt, escape_markdown_v2, format_assistant_price, send_ai_message};
+
+/// Что сделать с неиспользованной частью сессии
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Settlement {
+    /// Зачислить остаток на баланс пользователя
+    Credit,
+    /// Вернуть Stars (только если сессия не использовалась)
+    Refund,
+}
+
+/// Вернуть оплату можно только за сессию, в которой еще не было сообщений
+pub fn refund_available(session: &UserSession, booking: Option<&Booking>) -> bool {
+    session.messages_exchanged == 0
+        && booking.is_some_and(|b| b.telegram_payment_charge_id.is_some() && !b.is_refunded)
+}
+
+/// Клавиатура подтверждения завершения сессии
+pub fn make_end_session_keyboard(session: &UserSession, booking: Option<&Booking>, lang: Locale) -> InlineKeyboardMarkup {
+    let mut keyboard = Vec::new();
+
+    if refund_available(session, booking) {
+        keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "session.end_refund_button"), "end_session_refund")]);
+    }
+
+    keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "session.end_credit_button"), "end_session_credit")]);
+    keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "session.continue_button"), "end_session_cancel")]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Предлагает пользователю варианты завершения текущей сессии
+pub async fn ask_end_session(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let user_state = state.get_user_state(chat_id).await?;
+
+    let Some(session) = user_state.current_session.as_ref().filter(|s| s.is_active) else {
+        bot.send_message(chat_id, tr!(lang, "common.no_active_session")).await?;
+        return Ok(());
+    };
+
+    let booking = state.find_booking_for_session(session).await.unwrap_or_else(|e| {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not find booking for session");
+        None
+    });
+
+    let text = tr!(lang, "session.end_confirm", stars = (session.unused_value() * 100.0) as i32);
+
+    bot.send_message(chat_id, text)
+        .parse_mode(ParseMode::MarkdownV2)
+        .reply_markup(make_end_session_keyboard(session, booking.as_ref(), lang))
+        .await?;
+
+    Ok(())
+}
+
+/// Сохраняет историю завершенной сессии в архив и обновляет память о пользователе в фоне
+pub fn finalize_session_in_background(state: &BotState, session: &UserSession) {
+    let archive_state = state.clone();
+    let archive_session = session.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = SessionArchive::save(&archive_state, &archive_session).await {
+            tracing::error!(chat_id = %archive_session.chat_id, error = %e, "could not archive session");
+        }
+        if let Err(e) = analytics::record_session(&archive_state, &archive_session).await {
+            tracing::error!(chat_id = %archive_session.chat_id, error = %e, "could not record session stats");
+        }
+    });
+
+    memory::remember_session_in_background(state, session);
+}
+
+/// Зачисляет сумму на баланс и обновляет его в состоянии, которое покажем пользователю
+async fn credit_balance(state: &BotState, chat_id: ChatId, user_state: &mut UserState, amount: f64) {
+    if amount <= 0.0 {
+        return;
+    }
+    match state.add_balance(chat_id, amount).await {
+        Ok(balance) => user_state.balance = balance,
+        Err(e) => tracing::error!(chat_id = %chat_id, error = %e, "could not credit balance"),
+    }
+}
+
+/// Завершает текущую сессию: закрывает бронь, рассчитывает остаток и отправляет итог
+pub async fn end_session(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    settlement: Settlement,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut user_state = state.get_user_state(chat_id).await?;
+
+    let Some(session) = user_state.current_session.as_mut().filter(|s| s.is_active) else {
+        bot.send_message(chat_id, tr!(lang, "common.no_active_session")).await?;
+        return Ok(());
+    };
+
+    let unused_value = session.unused_value();
+    let used_minutes = (Utc::now() - session.session_start).num_minutes().max(0);
+    let messages_exchanged = session.messages_exchanged;
+    let assistant_id = session.assistant_id;
+
+    let mut booking = state.find_booking_for_session(session).await.unwrap_or_else(|e| {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not find booking for session");
+        None
+    });
+
+    let mut settlement_line = String::new();
+
+    if settlement == Settlement::Refund && refund_available(session, booking.as_ref()) {
+        // refund_available гарантирует наличие брони с идентификатором платежа
+        let b = booking.as_mut().unwrap();
+
+        match refund_booking_payment(bot, b).await {
+            Ok(()) => {
+                b.is_refunded = true;
+                // Часть, оплаченная с баланса, возвращается на баланс
+                credit_balance(state, chat_id, &mut user_state, b.credit_to_return()).await;
+                settlement_line = tr!(lang, "session.refunded_line", stars = (b.amount_due() * 100.0) as i32);
+            }
+            Err(e) => {
+                tracing::error!(chat_id = %chat_id, booking_id = %b.id, error = %e, "could not refund booking");
+            }
+        }
+    }
+
+    // Если возврат не выполнялся или не удался — зачисляем остаток на баланс
+    if settlement_line.is_empty() {
+        credit_balance(state, chat_id, &mut user_state, unused_value).await;
+        settlement_line = tr!(lang, "session.credited_line", stars = (unused_value * 100.0) as i32);
+    }
+
+    if let Some(session) = user_state.current_session.as_mut() {
+        session.is_active = false;
+    }
+    let finished_session = user_state.current_session.clone();
+
+    if let Some(b) = booking.as_mut() {
+        b.is_completed = true;
+        if let Err(e) = state.save_booking(b).await {
+            tracing::error!(chat_id = %chat_id, booking_id = %b.id, error = %e, "could not complete booking");
+        }
+    }
+
+    let assistant_name = AIAssistant::find_localized(state, assistant_id, lang).await
+        .map(|a| a.name)
+        .unwrap_or_else(|| tr!(lang, "common.consultant"));
+
+    let summary = tr!(
+        lang,
+        "session.summary",
+        name = escape_markdown_v2(&assistant_name),
+        minutes = used_minutes,
+        messages = messages_exchanged,
+        settlement = escape_markdown_v2(&settlement_line),
+        balance = (user_state.balance * 100.0) as i32,
+    );
+
+    if let Err(e) = state.save_user_state(chat_id, user_state).await {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not save user state");
+    }
+
+    bot.send_message(chat_id, summary)
+        .parse_mode(ParseMode::MarkdownV2)
+        .await?;
+
+    tracing::info!(chat_id = %chat_id, "session ended by user");
+
+    if let Some(session) = finished_session {
+        finalize_session_in_background(state, &session);
+        ask_session_rating(bot, state, &session).await?;
+    }
+
+    Ok(())
+}
+
+/// Описание остатка сессии в единицах нового консультанта
+fn format_switch_units(quote: &SwitchQuote, lang: Locale) -> String {
+    if quote.per_message {
+        tr!(lang, "units.messages_short", count = quote.units)
+    } else {
+        tr!(lang, "units.minutes", count = quote.units)
+    }
+}
+
+/// Предлагает перенести текущую сессию на другого консультанта
+pub async fn ask_switch_consultant(
+    bot: &Bot,
+    chat_id: ChatId,
+    session: &UserSession,
+    assistant: &AIAssistant,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let quote = session.quote_switch(assistant);
+
+    if quote.units == 0 {
+        bot.send_message(
+            chat_id,
+            tr!(
+                lang,
+                "session.switch_insufficient",
+                stars = (quote.remaining_value * 100.0) as i32,
+                name = escape_markdown_v2(&assistant.name),
+                price = escape_markdown_v2(&format_assistant_price(assistant, lang)),
+            ),
+        )
+        .parse_mode(ParseMode::MarkdownV2)
+        .await?;
+        return Ok(());
+    }
+
+    let text = tr!(
+        lang,
+        "session.switch_confirm",
+        name = escape_markdown_v2(&assistant.name),
+        stars = (quote.remaining_value * 100.0) as i32,
+        price = escape_markdown_v2(&format_assistant_price(assistant, lang)),
+        units = escape_markdown_v2(&format_switch_units(&quote, lang)),
+    );
+
+    bot.send_message(chat_id, text)
+        .parse_mode(ParseMode::MarkdownV2)
+        .reply_markup(InlineKeyboardMarkup::new(vec![
+            vec![InlineKeyboardButton::callback(tr!(lang, "session.switch_button"), format!("switch_ai_{}", assistant.id))],
+            vec![InlineKeyboardButton::callback(tr!(lang, "session.keep_consultant_button"), "cancel_selection")],
+        ]))
+        .await?;
+
+    Ok(())
+}
+
+/// Переводит активную сессию на другого консультанта с пересчетом остатка
+pub async fn switch_consultant(
+    bot: &Bot,
+    state: &BotState,
+    chat_id: ChatId,
+    assistant: &AIAssistant,
+    lang: Locale,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut user_state = state.get_user_state(chat_id).await?;
+
+    let Some(session) = user_state.current_session.as_mut().filter(|s| s.can_chat()) else {
+        bot.send_message(chat_id, tr!(lang, "common.no_active_session")).await?;
+        return Ok(());
+    };
+
+    let previous_name = AIAssistant::find_localized(state, session.assistant_id, lang).await
+        .map(|a| a.name)
+        .unwrap_or_else(|| tr!(lang, "prompt.previous_consultant"));
+
+    let quote = session.quote_switch(assistant);
+    if quote.units == 0 {
+        bot.send_message(chat_id, tr!(lang, "session.switch_insufficient_short")).await?;
+        return Ok(());
+    }
+
+    session.apply_switch(assistant, quote);
+
+    // Меняем системный промпт и отмечаем передачу разговора в истории
+    if let Some(system) = session.history.first_mut().filter(|m| m.role == "system") {
+        system.content = Some(build_system_prompt(assistant, lang));
+    }
+    if !session.history.is_empty() {
+        session.history.push(ChatMessage {
+            role: "system".to_string(),
+            content: Some(tr!(lang, "prompt.handoff", previous = previous_name, name = assistant.name)),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        });
+    }
+
+    user_state.current_assistant_id = assistant.id;
+
+    if let Err(e) = state.save_user_state(chat_id, user_state).await {
+        tracing::error!(chat_id = %chat_id, error = %e, "could not save user state");
+    }
+
+    tracing::info!(chat_id = %chat_id, assistant_id = assistant.id, "consultant switched");
+
+    bot.send_message(
+        chat_id,
+        tr!(
+            lang,
+            "session.switched",
+            name = escape_markdown_v2(&assistant.name),
+            units = escape_markdown_v2(&format_switch_units(&quote, lang)),
+        ),
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .await?;
+
+    send_ai_message(bot, chat_id, &assistant.name, &escape_markdown_v2(&assistant.greeting)).await?;
+
+    Ok(())
+}
@@ -1,9 +1,10 @@
 use teloxide::prelude::*;
 use teloxide::types::{InlineKeyboardButton, InlineKeyboardMarkup, KeyboardButton, KeyboardMarkup, ParseMode, ReplyMarkup};
-use chrono::Utc;
 
 use crate::bot_state::BotState;
-use crate::models::{AIAssistant, TimeSlot, UserState};
+use crate::i18n::{self, Locale};
+use crate::tr;
+use crate::models::{AIAssistant, BillingMode, Booking, MessageBundle, TimeSlot, UserState};
 
 /// Экранирование MarkdownV2
 pub fn escape_markdown_v2(text: &str) -> String {
@@ -19,22 +20,64 @@ pub fn escape_markdown_v2(text: &str) -> String {
     out
 }
 
+/// Кнопки главного меню
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    ChooseConsultant,
+    MySessions,
+    Consultants,
+    About,
+}
+
+impl MenuItem {
+    pub const ALL: [MenuItem; 4] = [
+        MenuItem::ChooseConsultant,
+        MenuItem::MySessions,
+        MenuItem::Consultants,
+        MenuItem::About,
+    ];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            MenuItem::ChooseConsultant => "menu.choose_consultant",
+            MenuItem::MySessions => "menu.my_sessions",
+            MenuItem::Consultants => "menu.consultants",
+            MenuItem::About => "menu.about",
+        }
+    }
+
+    /// Кнопка по тексту сообщения на любом языке (клавиатура могла остаться от прежнего языка)
+    pub fn from_text(text: &str) -> Option<Self> {
+        let key = i18n::key_for_text(text, &Self::ALL.map(|item| item.key()))?;
+        Self::ALL.into_iter().find(|item| item.key() == key)
+    }
+
+    fn button(&self, lang: Locale) -> KeyboardButton {
+        KeyboardButton::new(tr!(lang, self.key()))
+    }
+}
+
 /// Главное меню
-pub fn main_menu_keyboard() -> ReplyMarkup {
+pub fn main_menu_keyboard(lang: Locale) -> ReplyMarkup {
     ReplyMarkup::Keyboard(
         KeyboardMarkup::new(vec![
-            vec![KeyboardButton::new("👥 Выбрать консультанта")],
-            vec![KeyboardButton::new("💰 Мои сессии")],
-            vec![KeyboardButton::new("ℹ️ Список консультантов"), KeyboardButton::new("ℹ️ О боте")],
+            vec![MenuItem::ChooseConsultant.button(lang)],
+            vec![MenuItem::MySessions.button(lang)],
+            vec![MenuItem::Consultants.button(lang), MenuItem::About.button(lang)],
         ])
         .resize_keyboard()
         .one_time_keyboard()
     )
 }
 
+/// Системный промпт консультанта с правилами оформления ответов для Telegram
+pub fn build_system_prompt(assistant: &AIAssistant, lang: Locale) -> String {
+    format!("{}\n\n{}", assistant.prompt, tr!(lang, "prompt.formatting"))
+}
+
 /// Клавиатура выбора AI-персоны
-pub async fn make_ai_keyboard(state: &BotState) -> InlineKeyboardMarkup {
-    let assistants = AIAssistant::get_all_assistants(state).await;
+pub async fn make_ai_keyboard(state: &BotState, lang: Locale) -> InlineKeyboardMarkup {
+    let assistants = AIAssistant::get_all_localized(state, lang).await;
     let mut keyboard = Vec::new();
 
     for assistant in assistants {
@@ -44,95 +87,154 @@ pub async fn make_ai_keyboard(state: &BotState) -> InlineKeyboardMarkup {
         )]);
     }
 
-    keyboard.push(vec![InlineKeyboardButton::callback("❌ Отмена", "cancel_selection")]);
+    keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "common.cancel"), "cancel_selection")]);
 
     InlineKeyboardMarkup::new(keyboard)
 }
 
 /// Клавиатура с информацией о консультантах
-pub async fn make_consultants_info_keyboard(state: &BotState) -> InlineKeyboardMarkup {
-    let assistants = AIAssistant::get_all_assistants(state).await;
+pub async fn make_consultants_info_keyboard(state: &BotState, lang: Locale) -> InlineKeyboardMarkup {
+    let assistants = AIAssistant::get_all_localized(state, lang).await;
     let mut keyboard = Vec::new();
 
     for assistant in assistants {
         keyboard.push(vec![InlineKeyboardButton::callback(
-            format!("ℹ️ {} - {}", assistant.name, assistant.specialty),
+            match assistant.rating {
+                Some(rating) => format!("ℹ️ {} - {} ⭐{:.1}", assistant.name, assistant.specialty, rating),
+                None => format!("ℹ️ {} - {}", assistant.name, assistant.specialty),
+            },
             format!("consultant_info_{}", assistant.id), // Используем ID
         )]);
     }
 
-    keyboard.push(vec![InlineKeyboardButton::callback("👥 Выбрать консультанта", "change_consultant_from_list")]);
+    keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "menu.choose_consultant"), "change_consultant_from_list")]);
 
     InlineKeyboardMarkup::new(keyboard)
 }
 
 /// Клавиатура выбора времени сессии
-pub async fn make_time_slots_keyboard(state: &BotState, assistant: &AIAssistant) -> InlineKeyboardMarkup {
+pub async fn make_time_slots_keyboard(state: &BotState, assistant: &AIAssistant, lang: Locale) -> InlineKeyboardMarkup {
     let time_slots = TimeSlot::get_all_active_slots(state).await;
     let mut keyboard = Vec::new();
 
     for slot in time_slots {
-        let button_text = slot.format_price(assistant.price_per_minute);
+        let button_text = slot.format_price(assistant.price_per_minute, lang);
         keyboard.push(vec![InlineKeyboardButton::callback(
             button_text,
             format!("time_slot_{}", slot.id),
         )]);
     }
 
-    keyboard.push(vec![InlineKeyboardButton::callback("◀️ Назад к выбору консультанта", "back_to_consultant_selection")]);
-    keyboard.push(vec![InlineKeyboardButton::callback("❌ Отмена", "cancel_selection")]);
+    keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "consultants.back_to_selection"), "back_to_consultant_selection")]);
+    keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "common.cancel"), "cancel_selection")]);
+
+    InlineKeyboardMarkup::new(keyboard)
+}
+
+/// Клавиатура выбора пакета сообщений
+pub async fn make_message_bundles_keyboard(state: &BotState, assistant: &AIAssistant, lang: Locale) -> InlineKeyboardMarkup {
+    let bundles = MessageBundle::get_all_active_bundles(state).await;
+    let mut keyboard = Vec::new();
+
+    for bundle in bundles {
+        keyboard.push(vec![InlineKeyboardButton::callback(
+            bundle.format_price(assistant.price_per_message, lang),
+            format!("message_bundle_{}", bundle.id),
+        )]);
+    }
+
+    keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "consultants.back_to_selection"), "back_to_consultant_selection")]);
+    keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "common.cancel"), "cancel_selection")]);
 
     InlineKeyboardMarkup::new(keyboard)
 }
 
+/// Клавиатура оплаты для консультанта в зависимости от способа тарификации
+pub async fn make_purchase_keyboard(state: &BotState, assistant: &AIAssistant, lang: Locale) -> InlineKeyboardMarkup {
+    match assistant.billing_mode {
+        BillingMode::PerMinute => make_time_slots_keyboard(state, assistant, lang).await,
+        BillingMode::PerMessage => make_message_bundles_keyboard(state, assistant, lang).await,
+    }
+}
+
+/// Цена консультанта в Stars с единицей тарификации
+pub fn format_assistant_price(assistant: &AIAssistant, lang: Locale) -> String {
+    match assistant.billing_mode {
+        BillingMode::PerMinute => tr!(lang, "units.price_per_minute", price = (assistant.price_per_minute * 100.0) as i32),
+        BillingMode::PerMessage => tr!(lang, "units.price_per_message", price = (assistant.price_per_message * 100.0) as i32),
+    }
+}
+
 /// Формат информации об AI-персоне
 pub fn format_ai_info(assistant: &AIAssistant) -> String {
-    format!("{} - {}", escape_markdown_v2(&assistant.name), escape_markdown_v2(&assistant.specialty))
+    let mut info = format!("{} - {}", escape_markdown_v2(&assistant.name), escape_markdown_v2(&assistant.specialty));
+    if let Some(rating) = assistant.rating {
+        info.push_str(&format!(" ⭐{:.1}", rating));
+    }
+    info
+}
+
+/// Рейтинг консультанта для отображения
+pub fn format_rating(assistant: &AIAssistant, lang: Locale) -> String {
+    match assistant.rating {
+        Some(rating) => tr!(lang, "consultants.rating", rating = format!("{:.1}", rating), count = assistant.ratings_count),
+        None => tr!(lang, "consultants.no_rating"),
+    }
 }
 
 /// Форматирование информации о консультанте для отображения
-pub fn format_consultant_info(assistant: &AIAssistant) -> String {
-    format!(
-        "👤 *{}*\n\n\
-        *Описание:* {}\n\
-        *Специализация:* {}\n\
-        *Цена:* {} Stars/мин",
-        escape_markdown_v2(&assistant.name),
-        escape_markdown_v2(&assistant.description),
-        escape_markdown_v2(&assistant.specialty),
-        (assistant.price_per_minute * 100.0) as i32,
+pub fn format_consultant_info(assistant: &AIAssistant, lang: Locale) -> String {
+    tr!(
+        lang,
+        "consultants.info",
+        name = escape_markdown_v2(&assistant.name),
+        description = escape_markdown_v2(&assistant.description),
+        specialty = escape_markdown_v2(&assistant.specialty),
+        price = escape_markdown_v2(&format_assistant_price(assistant, lang)),
+        rating = escape_markdown_v2(&format_rating(assistant, lang)),
     )
 }
 
 // Клавиатура для возврата к списку консультантов
-pub fn make_back_to_consultants_keyboard() -> InlineKeyboardMarkup {
+pub fn make_back_to_consultants_keyboard(lang: Locale) -> InlineKeyboardMarkup {
     InlineKeyboardMarkup::new(vec![
-        vec![InlineKeyboardButton::callback("◀️ Назад к списку консультантов", "back_to_consultants_list")],
-        vec![InlineKeyboardButton::callback("👥 Выбрать консультанта", "change_consultant_from_list")],
+        vec![InlineKeyboardButton::callback(tr!(lang, "consultants.back_to_list"), "back_to_consultants_list")],
+        vec![InlineKeyboardButton::callback(tr!(lang, "menu.choose_consultant"), "change_consultant_from_list")],
     ])
 }
 
+/// Кнопки для оплаченной, но еще не начатой брони
+pub fn make_unstarted_booking_keyboard(booking: &Booking, lang: Locale) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(tr!(lang, "sessions.start_button"), format!("start_booking_{}", booking.id)),
+        InlineKeyboardButton::callback(tr!(lang, "sessions.refund_button"), format!("refund_booking_{}", booking.id)),
+    ]])
+}
+
 pub fn make_session_management_keyboard(user_state: &UserState) -> InlineKeyboardMarkup {
+    let lang = user_state.locale();
     let mut keyboard = Vec::new();
     
     // Показываем кнопку "Отменить" для всех броней
     if let Some(session) = &user_state.current_session {
-        if session.is_active && Utc::now() < session.paid_until {
+        if session.can_chat() {
             keyboard.push(vec![
-                InlineKeyboardButton::callback("❌ Завершить сессию", "end_session"),
+                InlineKeyboardButton::callback(tr!(lang, "sessions.end_button"), "end_session"),
             ]);
         }
     }
     
-    keyboard.push(vec![InlineKeyboardButton::callback("💬 Новая сессия", "new_session")]);
+    keyboard.push(vec![InlineKeyboardButton::callback(tr!(lang, "sessions.new_button"), "new_session")]);
     
     InlineKeyboardMarkup::new(keyboard)
 }
 
 /// Получить температуру/креативность пользователя
 pub async fn get_user_temperature(chat_id: ChatId, state: &BotState) -> f32 {
-    let user_state = state.get_user_state(chat_id).await;
-    user_state.user_temperatures.get(&chat_id).copied().unwrap_or(0.3)
+    state.get_user_state(chat_id).await
+        .ok()
+        .and_then(|user_state| user_state.user_temperatures.get(&chat_id).copied())
+        .unwrap_or(0.3)
 }
 
 pub async fn show_user_sessions(bot: &Bot, chat_id: ChatId, state: &BotState) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -142,43 +244,57 @@ pub async fn show_user_sessions(bot: &Bot, chat_id: ChatId, state: &BotState) ->
         Err(_) => Vec::new(),
     };
 
-    let sessions_text = if user_bookings.is_empty() {
-        "💰 *Ваши сессии*\n\nУ вас пока нет активных сессий\\.".to_string()
-    } else {
-        "💰 *Ваши сессии*\n\nВыберите сессию для просмотра информации:".to_string()
-    };
+    let user_state = state.get_user_state(chat_id).await?;
+    let lang = user_state.locale();
+
+    let mut sessions_text = format!(
+        "{}\n\n{}",
+        tr!(lang, "sessions.title"),
+        if user_bookings.is_empty() { tr!(lang, "sessions.empty") } else { tr!(lang, "sessions.choose") }
+    );
+
+    if user_state.balance > 0.0 {
+        sessions_text.push_str("\n\n");
+        sessions_text.push_str(&tr!(lang, "sessions.balance", stars = (user_state.balance * 100.0) as i32));
+    }
 
     // Создаем клавиатуру с кнопками
     let mut keyboard: Vec<Vec<InlineKeyboardButton>> = Vec::new();
 
+    if user_state.current_session.as_ref().is_some_and(|s| s.can_chat()) {
+        keyboard.push(vec![
+            InlineKeyboardButton::callback(tr!(lang, "sessions.end_button"), "end_session")
+        ]);
+    }
+
     for booking in &user_bookings {
         // Находим консультанта по ID из бронирования
-        let assistant = AIAssistant::find_by_id_with_price(&state, booking.assistant_id).await
-            .unwrap_or_else(|| {
-                AIAssistant {
-                    id: 1,
-                    name: "Анна".to_string(),
-                    model: "GigaChat-2-Max".to_string(),
-                    description: "Интерактивный помощник".to_string(),
-                    specialty: "Общение и поддержка".to_string(),
-                    greeting: "Здравствуйте!".to_string(),
-                    prompt: "Ты помощник.".to_string(),
-                    price_per_minute: 0.1,
-                }
-            });
+        let assistant_name = AIAssistant::find_localized(&state, booking.assistant_id, lang).await
+            .map(|assistant| assistant.name)
+            .unwrap_or_else(|| tr!(lang, "consultants.unknown"));
         
         // Информационная кнопка
-        let info_text = format!("ℹ️ {} ({} мин)", assistant.name, booking.duration_minutes);
+        let volume = match booking.message_quota {
+            Some(quota) => tr!(lang, "units.messages_short", count = quota),
+            None => tr!(lang, "units.minutes", count = booking.duration_minutes),
+        };
+        let icon = if booking.is_unstarted() { "⏸" } else { "ℹ️" };
+        let info_text = format!("{} {} ({})", icon, assistant_name, volume);
 
         keyboard.push(vec![
             InlineKeyboardButton::callback(info_text, format!("info_booking_{}", booking.id))
         ]);
+
+        // Оплаченные, но не начатые брони можно запустить или вернуть
+        if booking.is_unstarted() {
+            keyboard.extend(make_unstarted_booking_keyboard(booking, lang).inline_keyboard);
+        }
     }
 
     // Добавляем кнопку новой сессии
     if !user_bookings.is_empty() {
         keyboard.push(vec![
-            InlineKeyboardButton::callback("💬 Новая сессия", "new_session")
+            InlineKeyboardButton::callback(tr!(lang, "sessions.new_button"), "new_session")
         ]);
     }
 
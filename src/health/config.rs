@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::net::SocketAddr;
+
+const HEALTH_ENABLED_ENV: &str = "HEALTH_ENABLED";
+const HEALTH_BIND_ENV: &str = "HEALTH_BIND";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HealthConfig {
+    /// Запускать ли HTTP-сервер с `/healthz` и `/readyz`
+    pub enabled: bool,
+    /// Адрес, на котором слушает сервер
+    pub bind: String,
+    /// Сколько ждать ответа базы, сервиса LLM и Telegram при проверке готовности
+    pub check_timeout_seconds: u64,
+    /// Через сколько пропущенных периодов фоновая задача считается зависшей
+    pub missed_heartbeats: u32,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "127.0.0.1:8081".to_string(),
+            check_timeout_seconds: 5,
+            missed_heartbeats: 3,
+        }
+    }
+}
+
+impl HealthConfig {
+    /// Переопределения из окружения (HEALTH_ENABLED, HEALTH_BIND)
+    pub(crate) fn apply_env(&mut self) {
+        if let Ok(value) = env::var(HEALTH_ENABLED_ENV) {
+            self.enabled = value == "true" || value == "1";
+        }
+        if let Ok(value) = env::var(HEALTH_BIND_ENV) {
+            self.bind = value;
+        }
+    }
+
+    pub(crate) fn validate(&self, errors: &mut Vec<String>) {
+        if self.enabled && self.bind.parse::<SocketAddr>().is_err() {
+            errors.push(format!("health.bind {:?} is not a valid socket address", self.bind));
+        }
+        if self.check_timeout_seconds == 0 {
+            errors.push("health.check_timeout_seconds must be positive".to_string());
+        }
+        if self.missed_heartbeats == 0 {
+            errors.push("health.missed_heartbeats must be positive".to_string());
+        }
+    }
+}
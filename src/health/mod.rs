@@ -0,0 +1,213 @@
+pub mod config;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use teloxide::prelude::*;
+
+use crate::bot_state::BotState;
+use crate::retention;
+use crate::settings;
+pub use config::HealthConfig;
+
+/// Запас сверх пропущенных периодов, чтобы не ловить задержки планировщика
+const HEARTBEAT_GRACE: Duration = Duration::from_secs(30);
+
+static HEARTBEATS: OnceLock<Mutex<BTreeMap<&'static str, Heartbeat>>> = OnceLock::new();
+
+/// Настройки проверок здоровья (секция `[health]` основных настроек)
+pub fn config() -> &'static HealthConfig {
+    &crate::settings::config().health
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Heartbeat {
+    last: Instant,
+    period: Duration,
+}
+
+fn heartbeats() -> &'static Mutex<BTreeMap<&'static str, Heartbeat>> {
+    HEARTBEATS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Отмечает очередной проход фоновой задачи, которая запускается раз в `period`
+pub fn heartbeat(task: &'static str, period: Duration) {
+    let mut beats = heartbeats().lock().unwrap_or_else(|e| e.into_inner());
+    beats.insert(task, Heartbeat { last: Instant::now(), period });
+}
+
+/// Результат одной проверки
+#[derive(Debug, Serialize)]
+struct Check {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Check {
+    fn passed(latency: Duration) -> Self {
+        Self { ok: true, latency_ms: Some(latency.as_millis()), error: None }
+    }
+
+    fn alive() -> Self {
+        Self { ok: true, latency_ms: None, error: None }
+    }
+
+    fn failed(error: impl ToString) -> Self {
+        Self { ok: false, latency_ms: None, error: Some(error.to_string()) }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    status: &'static str,
+    checks: BTreeMap<String, Check>,
+}
+
+impl IntoResponse for Report {
+    fn into_response(self) -> Response {
+        let status = if self.status == "ok" {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        };
+        (status, Json(self)).into_response()
+    }
+}
+
+impl Report {
+    fn new(checks: BTreeMap<String, Check>) -> Self {
+        let status = if checks.values().all(|c| c.ok) { "ok" } else { "fail" };
+        Self { status, checks }
+    }
+}
+
+/// Фоновые задачи, которые должны работать в запущенном боте
+fn expected_tasks() -> Vec<&'static str> {
+    let mut tasks = vec!["broadcast", "cache_cleanup", "check_sessions", "mood_checkins", "reminders"];
+    if retention::config().enabled {
+        tasks.push("retention");
+    }
+    tasks
+}
+
+/// Фоновые задачи, от которых давно не было отметки, считаются зависшими
+fn heartbeat_checks() -> BTreeMap<String, Check> {
+    let missed = config().missed_heartbeats.max(1);
+    let beats = heartbeats().lock().unwrap_or_else(|e| e.into_inner());
+
+    beats
+        .iter()
+        .map(|(task, beat)| {
+            let elapsed = beat.last.elapsed();
+            let limit = beat.period * missed + HEARTBEAT_GRACE;
+            let check = if elapsed <= limit {
+                Check::alive()
+            } else {
+                Check::failed(format!("no heartbeat for {}s (limit {}s)", elapsed.as_secs(), limit.as_secs()))
+            };
+            (format!("task:{}", task), check)
+        })
+        .collect()
+}
+
+/// Выполняет проверку с ограничением по времени
+async fn timed<F, T, E>(check: F) -> Check
+where
+    F: Future<Output = Result<T, E>>,
+    E: ToString,
+{
+    let started = Instant::now();
+    match tokio::time::timeout(Duration::from_secs(config().check_timeout_seconds.max(1)), check).await {
+        Ok(Ok(_)) => Check::passed(started.elapsed()),
+        Ok(Err(e)) => Check::failed(e),
+        Err(_) => Check::failed("timed out"),
+    }
+}
+
+async fn check_database(state: &BotState) -> Check {
+    timed(sqlx::query("SELECT 1").execute(&state.db.pool)).await
+}
+
+/// Сервис LLM доступен, если отвечает по HTTP (код ответа не важен)
+async fn check_llm() -> Check {
+    let host = &settings::config().llm.service_host;
+    timed(reqwest::Client::new().get(host).send()).await
+}
+
+async fn check_telegram(bot: &Bot) -> Check {
+    timed(bot.get_me().send()).await
+}
+
+#[derive(Clone)]
+struct HealthState {
+    bot: Bot,
+    state: BotState,
+}
+
+/// Живость: процесс отвечает и фоновые задачи не зависли
+async fn healthz() -> Report {
+    Report::new(heartbeat_checks())
+}
+
+/// Готовность: доступны база, сервис LLM и Telegram, все фоновые задачи запущены и работают
+async fn readyz(State(health): State<HealthState>) -> Report {
+    let (database, llm, telegram) = tokio::join!(
+        check_database(&health.state),
+        check_llm(),
+        check_telegram(&health.bot),
+    );
+
+    let mut checks = heartbeat_checks();
+    // Задача, которая ни разу не отметилась, не запущена
+    for task in expected_tasks() {
+        checks
+            .entry(format!("task:{}", task))
+            .or_insert_with(|| Check::failed("not started"));
+    }
+    checks.insert("database".to_string(), database);
+    checks.insert("llm_service".to_string(), llm);
+    checks.insert("telegram".to_string(), telegram);
+
+    let report = Report::new(checks);
+    if report.status != "ok" {
+        tracing::warn!(failed = ?report.checks.iter().filter(|(_, c)| !c.ok).collect::<Vec<_>>(), "readiness check failed");
+    }
+    report
+}
+
+/// Запускает HTTP-сервер с `/healthz` и `/readyz`, если он включен в настройках
+pub async fn serve(bot: Bot, state: BotState) {
+    let config = config();
+    if !config.enabled {
+        return;
+    }
+
+    let app = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(HealthState { bot, state });
+
+    let listener = match tokio::net::TcpListener::bind(&config.bind).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!(bind = %config.bind, error = %e, "could not bind health server");
+            return;
+        }
+    };
+
+    tracing::info!(bind = %config.bind, "health server listening");
+
+    if let Err(e) = axum::serve(listener, app).await {
+        tracing::error!(error = %e, "health server stopped");
+    }
+}
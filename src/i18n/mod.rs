@@ -0,0 +1,229 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::OnceLock;
+use teloxide::types::{ChatId, User};
+
+use crate::bot_state::BotState;
+
+/// Каталоги сообщений встраиваются в бинарник
+const CATALOGS: [(Locale, &str); 2] = [
+    (Locale::Ru, include_str!("../../locales/ru.toml")),
+    (Locale::En, include_str!("../../locales/en.toml")),
+];
+
+/// Язык интерфейса бота
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    Ru,
+    En,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::Ru, Locale::En];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::Ru => "ru",
+            Locale::En => "en",
+        }
+    }
+
+    /// Название языка на нем самом
+    pub fn native_name(&self) -> &'static str {
+        match self {
+            Locale::Ru => "🇷🇺 Русский",
+            Locale::En => "🇬🇧 English",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "ru" => Some(Locale::Ru),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    /// Язык по language_code из Telegram: русский для русскоязычных стран СНГ, иначе английский
+    pub fn from_language_code(language_code: Option<&str>) -> Self {
+        let Some(code) = language_code else {
+            return Locale::default();
+        };
+
+        match code.split(['-', '_']).next().unwrap_or_default() {
+            "ru" | "uk" | "be" | "kk" => Locale::Ru,
+            _ => Locale::En,
+        }
+    }
+}
+
+type Catalog = HashMap<String, String>;
+
+static CATALOG_MAP: OnceLock<HashMap<Locale, Catalog>> = OnceLock::new();
+
+/// Разворачивает вложенные таблицы TOML в ключи вида "section.key"
+fn flatten(prefix: &str, table: &toml::Table, out: &mut Catalog) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::String(text) => {
+                out.insert(key, text.clone());
+            }
+            toml::Value::Table(nested) => flatten(&key, nested, out),
+            other => tracing::warn!(key = %key, value = %other, "ignoring non-string message"),
+        }
+    }
+}
+
+fn catalogs() -> &'static HashMap<Locale, Catalog> {
+    CATALOG_MAP.get_or_init(|| {
+        CATALOGS
+            .iter()
+            .map(|(locale, source)| {
+                let mut catalog = Catalog::new();
+                match source.parse::<toml::Table>() {
+                    Ok(table) => flatten("", &table, &mut catalog),
+                    Err(e) => tracing::error!(locale = locale.code(), error = %e, "could not parse message catalog"),
+                }
+                (*locale, catalog)
+            })
+            .collect()
+    })
+}
+
+/// Загружает каталоги при старте и предупреждает о непереведенных сообщениях
+pub fn init() {
+    let catalogs = catalogs();
+    let reference = &catalogs[&Locale::default()];
+
+    for locale in Locale::ALL {
+        let missing: Vec<&str> = reference
+            .keys()
+            .filter(|key| !catalogs[&locale].contains_key(*key))
+            .map(String::as_str)
+            .collect();
+
+        if !missing.is_empty() {
+            tracing::warn!(locale = locale.code(), count = missing.len(), missing = %missing.join(", "), "messages missing in catalog");
+        }
+    }
+
+    tracing::info!(locales = %Locale::ALL.map(|l| l.code()).join(", "), "message catalogs loaded");
+}
+
+/// Сообщение по ключу; если перевода нет — из каталога по умолчанию, в крайнем случае сам ключ
+pub fn t(locale: Locale, key: &str) -> String {
+    let catalogs = catalogs();
+    catalogs[&locale]
+        .get(key)
+        .or_else(|| catalogs[&Locale::default()].get(key))
+        .cloned()
+        .unwrap_or_else(|| {
+            tracing::warn!(key, "missing message");
+            key.to_string()
+        })
+}
+
+/// Сообщение с подстановкой именованных параметров {name}
+pub fn t_args(locale: Locale, key: &str, args: &[(&str, &(dyn Display + Sync))]) -> String {
+    args.iter().fold(t(locale, key), |text, (name, value)| {
+        text.replace(&format!("{{{}}}", name), &value.to_string())
+    })
+}
+
+/// Ключ, которому соответствует текст на любом из языков (для кнопок reply-клавиатуры)
+pub fn key_for_text<'a>(text: &str, keys: &[&'a str]) -> Option<&'a str> {
+    keys.iter()
+        .copied()
+        .find(|key| Locale::ALL.iter().any(|locale| catalogs()[locale].get(*key).is_some_and(|m| m == text)))
+}
+
+/// Язык пользователя без обращения к Telegram (для фоновых задач)
+pub async fn user_locale(state: &BotState, chat_id: ChatId) -> Locale {
+    match state.get_user_state(chat_id).await {
+        Ok(user_state) => user_state.locale(),
+        Err(e) => {
+            tracing::error!(chat_id = %chat_id, error = %e, "could not load user locale");
+            Locale::default()
+        }
+    }
+}
+
+/// Запоминает язык клиента Telegram и возвращает язык интерфейса пользователя
+pub async fn locale_for(state: &BotState, chat_id: ChatId, user: Option<&User>) -> Locale {
+    let language_code = user.and_then(|u| u.language_code.clone());
+    // Не сохраняем состояние, которое не удалось прочитать, чтобы не затереть его
+    let mut user_state = match state.get_user_state(chat_id).await {
+        Ok(user_state) => user_state,
+        Err(e) => {
+            tracing::error!(chat_id = %chat_id, error = %e, "could not load user locale");
+            return Locale::from_language_code(language_code.as_deref());
+        }
+    };
+
+    if language_code.is_some() && user_state.language_code != language_code {
+        user_state.language_code = language_code;
+        let locale = user_state.locale();
+        if let Err(e) = state.save_user_state(chat_id, user_state).await {
+            tracing::error!(chat_id = %chat_id, error = %e, "could not save user language");
+        }
+        return locale;
+    }
+
+    user_state.locale()
+}
+
+/// Перевод по ключу: `tr!(lang, "key")` или `tr!(lang, "key", name = value, ...)`
+#[macro_export]
+macro_rules! tr {
+    ($locale:expr, $key:expr) => {
+        $crate::i18n::t($locale, $key)
+    };
+    ($locale:expr, $key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::t_args($locale, $key, &[$((stringify!($name), &$value as &(dyn std::fmt::Display + Sync))),+])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn placeholders(text: &str) -> BTreeSet<&str> {
+        regex::Regex::new(r"\{(\w+)\}")
+            .unwrap()
+            .captures_iter(text)
+            .map(|c| c.get(1).unwrap().as_str())
+            .collect()
+    }
+
+    #[test]
+    fn catalogs_have_the_same_keys() {
+        let catalogs = catalogs();
+        let ru: BTreeSet<&String> = catalogs[&Locale::Ru].keys().collect();
+        let en: BTreeSet<&String> = catalogs[&Locale::En].keys().collect();
+
+        assert!(!ru.is_empty());
+        assert_eq!(ru.difference(&en).collect::<Vec<_>>(), Vec::<&&String>::new(), "missing in en");
+        assert_eq!(en.difference(&ru).collect::<Vec<_>>(), Vec::<&&String>::new(), "missing in ru");
+    }
+
+    #[test]
+    fn catalogs_have_the_same_placeholders() {
+        let catalogs = catalogs();
+        for (key, ru) in &catalogs[&Locale::Ru] {
+            if let Some(en) = catalogs[&Locale::En].get(key) {
+                assert_eq!(placeholders(ru), placeholders(en), "placeholders differ in {}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn arguments_are_substituted() {
+        assert_eq!(t(Locale::En, "no.such.key"), "no.such.key");
+        assert!(!tr!(Locale::Ru, "booking.pay_within", minutes = 15).contains("{minutes}"));
+    }
+}
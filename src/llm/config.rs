@@ -6,7 +6,9 @@ pub struct ServiceChatRequest {
     pub model: String,
     pub messages: Vec<ChatMessage>,
     #[serde(default = "default_temperature")]
-    pub temperature: f32
+    pub temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<ToolDefinition>>,
 }
 fn default_temperature() -> f32 { 0.1 }
 
@@ -19,7 +21,20 @@ pub struct ServiceEmbeddingRequest {
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ServiceChatResponse {
-    pub content: Option<String>
+    pub content: Option<String>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    /// Расход токенов, если сервис его сообщает
+    #[serde(default)]
+    pub usage: Option<TokenUsage>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TokenUsage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -52,3 +67,18 @@ pub struct FunctionCall {
     pub name: String,
     pub arguments: String,
 }
+
+/// Описание инструмента, доступного модели
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ToolDefinition {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub function: FunctionDefinition,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
@@ -1,22 +1,27 @@
 pub mod config;
+pub mod tools;
 
-use std::env;
 use std::fs;
 use std::io;
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 use reqwest::Client;
-use reqwest_middleware::{ClientBuilder};
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
 
 use anyhow::Result;
 
+use crate::metrics;
+use crate::settings;
+
 use crate::llm::config::ChatMessage;
 use crate::llm::config::ServiceChatRequest;
 use crate::llm::config::ServiceChatResponse;
+use crate::llm::config::ServiceEmbeddingRequest;
+use crate::llm::config::ServiceEmbeddingResponse;
+use crate::llm::config::ToolDefinition;
 
-const RETRIES: u32 = 1;
-const LLM_SERVICE_HOST_ENV: &str = "LLM_SERVICE_HOST";
 
 pub fn get_provider_from_model(model: &str) -> String {
     let model_lower = model.to_lowercase();
@@ -29,27 +34,81 @@ pub fn get_provider_from_model(model: &str) -> String {
     }
 }
 
+/// HTTP-клиент сервиса LLM с повтором запроса при временных ошибках
+fn service_client() -> Result<ClientWithMiddleware> {
+    let config = &settings::config().llm;
+    let retry_policy = ExponentialBackoff::builder()
+        .build_with_max_retries(config.retries);
+
+    let client = Client::builder()
+        .timeout(Duration::from_secs(config.request_timeout_seconds))
+        .build()?;
+
+    Ok(ClientBuilder::new(client)
+        .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+        .build())
+}
+
 pub async fn chat(
     messages: Vec<ChatMessage>,
     model: String,
     temperature: f32,
+) -> Result<ServiceChatResponse> {
+    chat_with_tools(messages, model, temperature, None).await
+}
+
+/// Отмечает в текущем спане длительность запроса и ошибку, если она была
+fn record_request<T>(started: Instant, result: &Result<T>) {
+    let span = tracing::Span::current();
+    span.record("elapsed_ms", started.elapsed().as_millis() as u64);
+    if let Err(e) = result {
+        span.record("error", tracing::field::display(e));
+    }
+}
+
+/// Запрос к модели с перечнем доступных ей инструментов
+#[tracing::instrument(
+    name = "llm.chat",
+    skip_all,
+    fields(model = %model, tools = tools.is_some(), elapsed_ms = tracing::field::Empty, error = tracing::field::Empty)
+)]
+pub async fn chat_with_tools(
+    messages: Vec<ChatMessage>,
+    model: String,
+    temperature: f32,
+    tools: Option<Vec<ToolDefinition>>,
+) -> Result<ServiceChatResponse> {
+    let started = Instant::now();
+    let model_label = model.clone();
+    let result = request_chat(messages, model, temperature, tools).await;
+
+    record_request(started, &result);
+    metrics::llm_request(&model_label, "chat", started.elapsed(), result.is_ok());
+    if let Ok(ServiceChatResponse { usage: Some(usage), .. }) = &result {
+        metrics::llm_tokens(&model_label, usage.prompt_tokens, usage.completion_tokens);
+    }
+
+    result
+}
+
+async fn request_chat(
+    messages: Vec<ChatMessage>,
+    model: String,
+    temperature: f32,
+    tools: Option<Vec<ToolDefinition>>,
 ) -> Result<ServiceChatResponse> {
     let provider = get_provider_from_model(&model);
-    let service_host = env::var(LLM_SERVICE_HOST_ENV)?;
+    let service_host = &settings::config().llm.service_host;
 
     let request = ServiceChatRequest {
         provider,
         model,
         messages,
         temperature,
+        tools,
     };
 
-    let retry_policy = ExponentialBackoff::builder()
-        .build_with_max_retries(RETRIES);
-
-    let client = ClientBuilder::new(Client::new())
-        .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-        .build();
+    let client = service_client()?;
 
     let response = client
         .post(format!("{}/chat", service_host))
@@ -63,4 +122,53 @@ pub async fn chat(
     let response = serde_json::from_str::<ServiceChatResponse>(&text)?;
 
     Ok(response)
-}
\ No newline at end of file
+}
+
+/// Получает векторное представление текста через сервис LLM
+#[tracing::instrument(
+    name = "llm.embeddings",
+    skip_all,
+    fields(model = tracing::field::Empty, elapsed_ms = tracing::field::Empty, error = tracing::field::Empty)
+)]
+pub async fn embed(input: &str) -> Result<Vec<f32>> {
+    let model = settings::config().llm.embedding_model.clone();
+    tracing::Span::current().record("model", model.as_str());
+
+    let started = Instant::now();
+    let model_label = model.clone();
+    let result = request_embedding(input, model).await;
+    record_request(started, &result);
+    metrics::llm_request(&model_label, "embeddings", started.elapsed(), result.is_ok());
+
+    result
+}
+
+async fn request_embedding(input: &str, model: String) -> Result<Vec<f32>> {
+    // Модель эмбеддингов по умолчанию принадлежит GigaChat
+    let provider = match get_provider_from_model(&model).as_str() {
+        "unknown" => "gigachat".to_string(),
+        provider => provider.to_string(),
+    };
+    let service_host = &settings::config().llm.service_host;
+
+    let request = ServiceEmbeddingRequest {
+        provider,
+        model,
+        input: input.to_string(),
+    };
+
+    let client = service_client()?;
+
+    let response = client
+        .post(format!("{}/embeddings", service_host))
+        .header("Accept", "application/json")
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_vec(&request)?)
+        .send()
+        .await?;
+
+    let text = response.text().await?;
+    let response = serde_json::from_str::<ServiceEmbeddingResponse>(&text)?;
+
+    Ok(response.content)
+}
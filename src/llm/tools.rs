@@ -0,0 +1,268 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use teloxide::types::ChatId;
+
+use crate::bot_state::BotState;
+use crate::llm;
+use crate::llm::config::{ChatMessage, FunctionDefinition, ServiceChatResponse, ToolCall, ToolDefinition};
+use crate::models::{Goal, MoodEntry, Reminder};
+use crate::settings;
+
+/// Сколько раундов вызова инструментов допускается за один ответ
+const MAX_TOOL_ITERATIONS: usize = 3;
+/// Самое дальнее напоминание, которое можно поставить
+const MAX_REMINDER_DAYS: i64 = 30;
+
+/// Встроенные инструменты, которые может вызывать консультант
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinTool {
+    SetReminder,
+    SaveGoal,
+    CompleteGoal,
+    GetRemainingTime,
+    LogMood,
+}
+
+impl BuiltinTool {
+    pub const ALL: [BuiltinTool; 5] = [
+        BuiltinTool::SetReminder,
+        BuiltinTool::SaveGoal,
+        BuiltinTool::CompleteGoal,
+        BuiltinTool::GetRemainingTime,
+        BuiltinTool::LogMood,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuiltinTool::SetReminder => "set_reminder",
+            BuiltinTool::SaveGoal => "save_goal",
+            BuiltinTool::CompleteGoal => "complete_goal",
+            BuiltinTool::GetRemainingTime => "get_remaining_time",
+            BuiltinTool::LogMood => "log_mood",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|tool| tool.name() == name)
+    }
+
+    pub fn definition(&self) -> ToolDefinition {
+        let (description, parameters) = match self {
+            BuiltinTool::SetReminder => (
+                "Поставить пользователю напоминание в Telegram через указанное число минут",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "text": { "type": "string", "description": "Текст напоминания" },
+                        "in_minutes": { "type": "integer", "minimum": 1, "description": "Через сколько минут напомнить" }
+                    },
+                    "required": ["text", "in_minutes"]
+                }),
+            ),
+            BuiltinTool::SaveGoal => (
+                "Сохранить цель, которую пользователь поставил себе в разговоре, вместе с конкретными шагами. \
+                Чтобы добавить шаги к уже сохраненной цели, передай ее goal_id",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "title": { "type": "string", "description": "Короткая формулировка цели" },
+                        "tasks": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Конкретные шаги к цели"
+                        },
+                        "goal_id": { "type": "integer", "description": "Номер существующей цели" }
+                    },
+                    "required": ["title"]
+                }),
+            ),
+            BuiltinTool::CompleteGoal => (
+                "Отметить цель или задачу пользователя выполненной",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "goal_id": { "type": "integer", "description": "Номер цели или задачи" }
+                    },
+                    "required": ["goal_id"]
+                }),
+            ),
+            BuiltinTool::GetRemainingTime => (
+                "Узнать, сколько времени или сообщений осталось в текущей оплаченной сессии",
+                json!({ "type": "object", "properties": {} }),
+            ),
+            BuiltinTool::LogMood => (
+                "Записать настроение пользователя в дневник по шкале от 1 до 10",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "score": { "type": "integer", "minimum": 1, "maximum": 10 },
+                        "note": { "type": "string", "description": "Короткий комментарий пользователя" }
+                    },
+                    "required": ["score"]
+                }),
+            ),
+        };
+
+        ToolDefinition {
+            type_: "function".to_string(),
+            function: FunctionDefinition {
+                name: self.name().to_string(),
+                description: description.to_string(),
+                parameters,
+            },
+        }
+    }
+}
+
+/// Определения всех инструментов (None, если инструменты отключены)
+pub fn registry() -> Option<Vec<ToolDefinition>> {
+    settings::config().features.llm_tools.then(|| BuiltinTool::ALL.iter().map(BuiltinTool::definition).collect())
+}
+
+/// Данные, доступные инструментам во время ответа
+pub struct ToolContext<'a> {
+    pub state: &'a BotState,
+    pub chat_id: ChatId,
+    pub paid_until: DateTime<Utc>,
+    pub remaining_messages: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct SetReminderArgs {
+    text: String,
+    in_minutes: i64,
+}
+
+#[derive(Deserialize)]
+struct SaveGoalArgs {
+    title: String,
+    #[serde(default)]
+    tasks: Vec<String>,
+    goal_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct CompleteGoalArgs {
+    goal_id: i32,
+}
+
+#[derive(Deserialize)]
+struct LogMoodArgs {
+    score: i16,
+    note: Option<String>,
+}
+
+async fn execute(tool: BuiltinTool, arguments: &str, ctx: &ToolContext<'_>) -> Result<String> {
+    match tool {
+        BuiltinTool::SetReminder => {
+            let args: SetReminderArgs = serde_json::from_str(arguments)?;
+            let minutes = args.in_minutes.clamp(1, MAX_REMINDER_DAYS * 24 * 60);
+            let remind_at = Utc::now() + Duration::minutes(minutes);
+            Reminder::create(ctx.state, ctx.chat_id, args.text.trim(), remind_at).await?;
+            Ok(format!("Напоминание поставлено на {} UTC", remind_at.format("%d.%m.%Y %H:%M")))
+        }
+        BuiltinTool::SaveGoal => {
+            let args: SaveGoalArgs = serde_json::from_str(arguments)?;
+            let goal_id = match args.goal_id {
+                Some(goal_id) => goal_id,
+                None => Goal::create(ctx.state, ctx.chat_id, args.title.trim()).await?.id,
+            };
+            let mut added = 0;
+            for task in args.tasks.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+                if Goal::create_task(ctx.state, ctx.chat_id, goal_id, task).await?.is_none() {
+                    return Ok(format!("Цель #{} не найдена", goal_id));
+                }
+                added += 1;
+            }
+            Ok(format!("Цель #{} сохранена, шагов добавлено: {}", goal_id, added))
+        }
+        BuiltinTool::CompleteGoal => {
+            let args: CompleteGoalArgs = serde_json::from_str(arguments)?;
+            match Goal::complete(ctx.state, ctx.chat_id, args.goal_id).await? {
+                Some(goal) => Ok(format!("Отмечено выполненным: {}", goal.title)),
+                None => Ok(format!("Цель #{} не найдена", args.goal_id)),
+            }
+        }
+        BuiltinTool::GetRemainingTime => Ok(match ctx.remaining_messages {
+            Some(remaining) => format!("Осталось сообщений: {}", remaining),
+            None => format!(
+                "Осталось минут: {}",
+                (ctx.paid_until - Utc::now()).num_minutes().max(0)
+            ),
+        }),
+        BuiltinTool::LogMood => {
+            let args: LogMoodArgs = serde_json::from_str(arguments)?;
+            let entry = MoodEntry::create(ctx.state, ctx.chat_id, args.score, args.note.as_deref()).await?;
+            Ok(format!("Настроение {}/10 записано в дневник", entry.score))
+        }
+    }
+}
+
+/// Выполняет вызов инструмента и оформляет результат как сообщение `tool`
+async fn run_tool_call(call: &ToolCall, ctx: &ToolContext<'_>) -> ChatMessage {
+    let result = match BuiltinTool::from_name(&call.function.name) {
+        Some(tool) => match execute(tool, &call.function.arguments, ctx).await {
+            Ok(result) => {
+                tracing::info!(chat_id = %ctx.chat_id, tool = %call.function.name, "tool executed");
+                result
+            }
+            Err(e) => {
+                tracing::warn!(chat_id = %ctx.chat_id, tool = %call.function.name, error = %e, "tool failed");
+                format!("Ошибка: {}", e)
+            }
+        },
+        None => format!("Неизвестный инструмент: {}", call.function.name),
+    };
+
+    ChatMessage {
+        role: "tool".to_string(),
+        content: Some(result),
+        tool_calls: None,
+        tool_call_id: Some(call.id.clone()),
+        name: Some(call.function.name.clone()),
+    }
+}
+
+/// Запрос к модели с выполнением вызванных ею инструментов.
+/// Сообщения с вызовами и результатами дописываются в `messages`.
+pub async fn chat_with_tools(
+    messages: &mut Vec<ChatMessage>,
+    model: &str,
+    temperature: f32,
+    ctx: &ToolContext<'_>,
+) -> Result<ServiceChatResponse> {
+    let Some(tools) = registry() else {
+        return llm::chat(messages.clone(), model.to_string(), temperature).await;
+    };
+
+    for _ in 0..MAX_TOOL_ITERATIONS {
+        let response = llm::chat_with_tools(
+            messages.clone(),
+            model.to_string(),
+            temperature,
+            Some(tools.clone()),
+        ).await?;
+
+        let Some(calls) = response.tool_calls.clone().filter(|calls| !calls.is_empty()) else {
+            return Ok(response);
+        };
+
+        messages.push(ChatMessage {
+            role: "assistant".to_string(),
+            content: response.content.clone(),
+            tool_calls: Some(calls.clone()),
+            tool_call_id: None,
+            name: None,
+        });
+
+        for call in &calls {
+            messages.push(run_tool_call(call, ctx).await);
+        }
+    }
+
+    // Лимит исчерпан — просим ответить без инструментов
+    tracing::warn!(chat_id = %ctx.chat_id, "tool iteration limit reached");
+    llm::chat(messages.clone(), model.to_string(), temperature).await
+}
@@ -3,17 +3,33 @@ use std::env;
 use std::time::Duration;
 use tokio::time;
 
+mod admin;
+mod analytics;
+mod api;
 mod bot_state;
+mod broadcast;
+mod crypto;
 mod database;
 mod llm;
+mod memory;
+mod metrics;
 mod models;
 mod handlers;
+mod health;
+mod i18n;
+mod moderation;
+mod privacy;
+mod retention;
+mod safety;
+mod settings;
+mod telemetry;
 
 use crate::bot_state::BotState;
 use crate::database::Database;
+use crate::i18n::Locale;
 use crate::models::payment_config::PaymentConfig;
 use crate::handlers::{
-    command_handler, message_handler, callback_handler, 
+    admin_command_handler, chat_member_handler, command_handler, message_handler, callback_handler, 
     pre_checkout_handler, successful_payment_handler
 };
 
@@ -30,74 +46,223 @@ enum Command {
     MySessions,
     #[command(description = "список консультантов")] // Обновлено описание
     Settings,
+    #[command(description = "мои цели и задачи")]
+    Goals,
+    #[command(description = "дневник настроения")]
+    Mood,
+    #[command(description = "выгрузить переписку")]
+    Export,
+    #[command(description = "что консультанты помнят обо мне")]
+    Memories,
+    #[command(description = "стереть память о прошлых сессиях")]
+    Forget,
+    #[command(description = "выгрузить все мои данные")]
+    MyData,
+    #[command(description = "удалить все мои данные")]
+    DeleteMe,
+    #[command(description = "срок хранения переписки")]
+    KeepHistory,
+    #[command(description = "язык интерфейса")]
+    Language,
+}
+
+/// Команды администраторов: не попадают в меню, остальным пользователям не отвечают
+#[derive(BotCommands, Clone, Debug)]
+#[command(rename_rule = "lowercase")]
+enum AdminCommand {
+    Admin,
+    Consultants,
+    Consultant(String),
+    AddConsultant(String),
+    EditConsultant(String),
+    ActivateConsultant(String),
+    DeactivateConsultant(String),
+    DeleteTranslation(String),
+    Slots,
+    AddSlot(String),
+    EditSlot(String),
+    Bookings(String),
+    Grant(String),
+    Refund(String),
+    Admins,
+    AddAdmin(String),
+    RemoveAdmin(String),
+    Broadcast(String),
+    Broadcasts,
+    CancelBroadcast(String),
+    Analytics(String),
+    AnalyticsCsv(String),
+}
+
+/// Регистрирует меню команд Telegram на каждом языке интерфейса
+async fn register_commands(bot: &Bot) {
+    for locale in Locale::ALL {
+        let commands: Vec<_> = Command::bot_commands()
+            .into_iter()
+            .map(|mut command| {
+                let key = format!("commands.{}", command.command.trim_start_matches('/'));
+                command.description = tr!(locale, &key);
+                command
+            })
+            .collect();
+
+        // Русский — язык по умолчанию для клиентов без перевода
+        let request = bot.set_my_commands(commands);
+        let result = if locale == Locale::default() {
+            request.await
+        } else {
+            request.language_code(locale.code()).await
+        };
+
+        if let Err(e) = result {
+            tracing::warn!(locale = locale.code(), error = %e, "could not register bot commands");
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Загружаем .env и инициализируем логирование
+    // Загружаем .env и настройки: ошибка конфигурации останавливает бота до подключения к базе
     dotenvy::dotenv().ok();
-    env_logger::init();
-    log::info!("Starting psychologist bot with PostgreSQL...");
+    let config = settings::init()?;
 
-    // Инициализация базы данных
-    let database_port = env::var("POSTGRES_PORT")
-        .expect("POSTGRES_PORT must be set");
-    let database_host = env::var("POSTGRES_HOST")
-        .expect("POSTGRES_HOST must be set");
-    let database_user = env::var("POSTGRES_USER")
-        .expect("POSTGRES_USER must be set");
-    let database_pass = env::var("POSTGRES_PASSWORD")
-        .expect("POSTGRES_PASSWORD must be set");
-    let database_db = env::var("POSTGRES_DB")
-        .expect("POSTGRES_DB must be set");
-
-    let database_url = format!(
-        "postgres://{}:{}@{}:{}/{}",
-        database_user, database_pass, database_host, database_port, database_db
+    // Гард держим до конца main, чтобы при остановке отправить накопленные трассировки
+    let _telemetry = telemetry::init();
+    tracing::info!(
+        database_host = %config.database.host,
+        database_port = config.database.port,
+        database_name = %config.database.name,
+        llm_service = %config.llm.service_host,
+        "starting bot"
     );
-    
-    let db = Database::new(&database_url).await?;
+
+    // Мастер-ключ шифрования проверяем до подключения к базе
+    crypto::init()?;
+    i18n::init();
+
+    // Инициализация базы данных
+    let db = Database::new(&config.database).await?;
     db.init().await?;
-    log::info!("✅ Database initialized");
+    tracing::info!("database initialized");
+
+    // Данные, сохраненные до включения шифрования, шифруем один раз
+    let encrypted = crypto::encrypt_existing(&db.pool).await?;
+    if encrypted > 0 {
+        tracing::info!(records = encrypted, "encrypted records stored before encryption was enabled");
+    }
 
-    // Настройки оплаты Telegram Stars
+    // Ротация мастер-ключа: `consultant-bot rotate-keys`
+    if env::args().nth(1).as_deref() == Some("rotate-keys") {
+        let rotated = crypto::rotate_keys(&db.pool).await?;
+        tracing::info!(keys = rotated, "re-wrapped user data keys with the current master key");
+        return Ok(());
+    }
+
+    // Отчет о том, что удалила бы очистка по срокам хранения: `consultant-bot retention-report`
+    if env::args().nth(1).as_deref() == Some("retention-report") {
+        let state = BotState::new(db);
+        let report = retention::enforce(&state, true).await?;
+        report.trace();
+        return Ok(());
+    }
+
+    // Настройки оплаты Telegram Stars (токен провайдера для Stars не нужен)
     let payment_config = PaymentConfig {
         provider_token: None,
-        currency: "XTR".to_string(), // Валюта для Telegram Stars
+        currency: config.payments.currency.clone(),
     };
 
     let state = BotState::new(db);
+    let bot = Bot::from_env();
+    register_commands(&bot).await;
 
     // Фоновая задача для проверки сессий
     let state_clone = state.clone();
+    let bot_clone = bot.clone();
+    tokio::spawn(async move {
+        handlers::check_sessions_task(bot_clone, state_clone).await;
+    });
+
+    // Фоновая задача для удаления данных с истекшим сроком хранения
+    let state_clone = state.clone();
+    tokio::spawn(async move {
+        handlers::retention_task(state_clone).await;
+    });
+
+    // Фоновая задача для отправки напоминаний
+    let state_clone = state.clone();
+    let bot_clone = bot.clone();
+    tokio::spawn(async move {
+        handlers::reminders_task(bot_clone, state_clone).await;
+    });
+
+    // Фоновая задача для ежедневных опросов о настроении
+    let state_clone = state.clone();
+    let bot_clone = bot.clone();
+    tokio::spawn(async move {
+        handlers::mood_checkins_task(bot_clone, state_clone).await;
+    });
+
+    // Фоновая задача для отправки рассылок
+    let state_clone = state.clone();
+    let bot_clone = bot.clone();
     tokio::spawn(async move {
-        handlers::check_sessions_task(state_clone).await;
+        handlers::broadcast_task(bot_clone, state_clone).await;
+    });
+
+    // HTTP API для бэк-офиса (запускается, только если включено в настройках)
+    let state_clone = state.clone();
+    let bot_clone = bot.clone();
+    tokio::spawn(async move {
+        api::serve(bot_clone, state_clone).await;
+    });
+
+    // Проверки живости и готовности (запускается, только если включено в настройках)
+    let state_clone = state.clone();
+    let bot_clone = bot.clone();
+    tokio::spawn(async move {
+        health::serve(bot_clone, state_clone).await;
+    });
+
+    // Метрики для Prometheus (запускается, только если включено в настройках)
+    let state_clone = state.clone();
+    tokio::spawn(async move {
+        metrics::serve(state_clone).await;
     });
 
     // Фоновая задача для очистки кэша
     let state_clone = state.clone();
     tokio::spawn(async move {
-        let mut interval = time::interval(Duration::from_secs(600));
+        let period = Duration::from_secs(settings::config().timeouts.cache_cleanup_interval_seconds);
+        let mut interval = time::interval(period);
         loop {
             interval.tick().await;
+            health::heartbeat("cache_cleanup", period);
             state_clone.cleanup_cache().await;
         }
     });
 
-    let bot = Bot::from_env();
-
-    let handler = dptree::entry()
+    let handler = telemetry::update_span()
+        .inspect(|update: Update| metrics::update_received(&update))
         .branch(
             Update::filter_message()
                 .filter_command::<Command>()
                 .endpoint(command_handler)
         )
+        .branch(
+            Update::filter_message()
+                .filter_command::<AdminCommand>()
+                .filter_async(|msg: Message, state: BotState| async move {
+                    admin::is_admin(&state, msg.chat.id).await
+                })
+                .endpoint(admin_command_handler)
+        )
         .branch(
             Update::filter_message()
                 .filter(|msg: Message| {
                     let has_payment = msg.successful_payment().is_some();
                     if has_payment {
-                        log::info!("🎉 Payment detected in filter!");
+                        tracing::info!(chat_id = %msg.chat.id, "successful payment received");
                     }
                     has_payment
                 })
@@ -105,9 +270,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         )
         .branch(Update::filter_pre_checkout_query().endpoint(pre_checkout_handler))
         .branch(Update::filter_callback_query().endpoint(callback_handler))
+        .branch(Update::filter_my_chat_member().endpoint(chat_member_handler))
         .branch(Update::filter_message().endpoint(message_handler));
 
-    log::info!("🚀 Starting dispatcher with correct payment handling...");
+    tracing::info!("starting dispatcher");
     
     Dispatcher::builder(bot, handler)
         .dependencies(dptree::deps![state, payment_config])
@@ -0,0 +1,143 @@
+use teloxide::types::ChatId;
+
+use crate::bot_state::BotState;
+use crate::llm;
+use crate::llm::config::ChatMessage;
+use crate::models::{AIAssistant, UserSession};
+use crate::models::memory::{MemoryKind, UserMemory};
+
+/// Сколько воспоминаний подмешиваем в системный промпт
+const RECALL_LIMIT: usize = 5;
+/// Минимальная близость, чтобы воспоминание считалось относящимся к разговору
+const MIN_SIMILARITY: f32 = 0.3;
+/// Сколько последних сообщений сессии учитываем при составлении итога
+const TRANSCRIPT_MESSAGES: usize = 40;
+const SUMMARY_PREFIX: &str = "Итог:";
+
+/// Запоминает итоги завершенной сессии в фоне, не задерживая ответ пользователю
+pub fn remember_session_in_background(state: &BotState, session: &UserSession) {
+    let state = state.clone();
+    let session = session.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = remember_session(&state, &session).await {
+            tracing::warn!(chat_id = %session.chat_id, error = %e, "could not save memories");
+        }
+    });
+}
+
+/// Выделяет из сессии значимые факты и итог, сохраняет их вместе с эмбеддингами
+#[tracing::instrument(name = "db.remember_session", skip_all, fields(chat_id = session.chat_id.0))]
+pub async fn remember_session(state: &BotState, session: &UserSession) -> anyhow::Result<()> {
+    let transcript: Vec<String> = session.history
+        .iter()
+        .filter(|m| m.role == "user" || m.role == "assistant")
+        .filter_map(|m| {
+            let speaker = if m.role == "user" { "Пользователь" } else { "Консультант" };
+            m.content.as_ref().map(|content| format!("{}: {}", speaker, content))
+        })
+        .collect();
+
+    if transcript.is_empty() {
+        return Ok(());
+    }
+
+    let transcript = transcript[transcript.len().saturating_sub(TRANSCRIPT_MESSAGES)..].join("\n");
+
+    let model = AIAssistant::find_by_id_with_price(state, session.assistant_id).await
+        .map(|a| a.model)
+        .unwrap_or_else(|| "GigaChat-2-Max".to_string());
+
+    let messages = vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: Some(format!(
+                "Ты помогаешь консультанту помнить пользователя между сессиями. \
+                Выпиши из разговора до 5 значимых фактов о пользователе (обстоятельства жизни, \
+                важные люди, цели, что помогает и что нет) — каждый факт с новой строки, без нумерации. \
+                Последней строкой напиши краткий итог сессии, начиная с \"{}\". \
+                Не записывай ничего, чего пользователь не говорил.",
+                SUMMARY_PREFIX
+            )),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: Some(transcript),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        },
+    ];
+
+    let response = llm::chat(messages, model, 0.0).await?;
+    let content = response.content.unwrap_or_default();
+
+    let mut saved = 0;
+    for line in content.lines() {
+        let line = line.trim().trim_start_matches(['-', '•', '*']).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (kind, text) = match line.strip_prefix(SUMMARY_PREFIX) {
+            Some(summary) => (MemoryKind::Summary, summary.trim()),
+            None => (MemoryKind::Fact, line),
+        };
+
+        let embedding = llm::embed(text).await?;
+        UserMemory::save(state, session.chat_id, kind, text, &embedding).await?;
+        saved += 1;
+    }
+
+    tracing::info!(chat_id = %session.chat_id, saved, "memories saved");
+
+    Ok(())
+}
+
+/// Подбирает воспоминания, наиболее близкие к началу нового разговора
+#[tracing::instrument(name = "db.recall_memories", skip_all, fields(chat_id = chat_id.0))]
+pub async fn recall(state: &BotState, chat_id: ChatId, query: &str) -> Vec<UserMemory> {
+    let memories = UserMemory::list_for_user(state, chat_id).await;
+    if memories.is_empty() {
+        return memories;
+    }
+
+    match llm::embed(query).await {
+        Ok(query_embedding) => {
+            let mut scored: Vec<(f32, UserMemory)> = memories
+                .into_iter()
+                .map(|m| (m.similarity(&query_embedding), m))
+                .filter(|(score, _)| *score >= MIN_SIMILARITY)
+                .collect();
+            scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+            scored.into_iter().take(RECALL_LIMIT).map(|(_, m)| m).collect()
+        }
+        Err(e) => {
+            // Без эмбеддинга запроса используем последние итоги сессий
+            tracing::warn!(error = %e, "could not embed query for memory recall");
+            memories
+                .into_iter()
+                .filter(|m| m.kind == MemoryKind::Summary)
+                .take(RECALL_LIMIT)
+                .collect()
+        }
+    }
+}
+
+/// Блок системного промпта с тем, что известно о пользователе из прошлых сессий
+pub fn memory_prompt(memories: &[UserMemory]) -> Option<String> {
+    if memories.is_empty() {
+        return None;
+    }
+
+    let lines: Vec<String> = memories.iter().map(|m| format!("- {}", m.content)).collect();
+
+    Some(format!(
+        "Что известно о пользователе из прошлых сессий (используй бережно, \
+        не пересказывай дословно и не упоминай, что это сохраненные записи):\n{}",
+        lines.join("\n")
+    ))
+}
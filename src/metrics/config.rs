@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::net::SocketAddr;
+
+const METRICS_ENABLED_ENV: &str = "METRICS_ENABLED";
+const METRICS_BIND_ENV: &str = "METRICS_BIND";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// Запускать ли HTTP-сервер с `/metrics` для Prometheus
+    pub enabled: bool,
+    /// Адрес, на котором слушает сервер (без авторизации — не открывайте его наружу)
+    pub bind: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: "127.0.0.1:9100".to_string(),
+        }
+    }
+}
+
+impl MetricsConfig {
+    /// Переопределения из окружения (METRICS_ENABLED, METRICS_BIND)
+    pub(crate) fn apply_env(&mut self) {
+        if let Ok(value) = env::var(METRICS_ENABLED_ENV) {
+            self.enabled = value == "true" || value == "1";
+        }
+        if let Ok(value) = env::var(METRICS_BIND_ENV) {
+            self.bind = value;
+        }
+    }
+
+    pub(crate) fn validate(&self, errors: &mut Vec<String>) {
+        if self.enabled && self.bind.parse::<SocketAddr>().is_err() {
+            errors.push(format!("metrics.bind {:?} is not a valid socket address", self.bind));
+        }
+    }
+}
@@ -0,0 +1,208 @@
+pub mod config;
+
+use axum::extract::State;
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::sync::OnceLock;
+use std::time::Duration;
+use teloxide::types::{Update, UpdateKind};
+
+use crate::bot_state::BotState;
+pub use config::MetricsConfig;
+
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// Настройки сервера метрик (секция `[metrics]` основных настроек)
+pub fn config() -> &'static MetricsConfig {
+    &crate::settings::config().metrics
+}
+
+/// Метрики бота. Счетчики обновляются по ходу работы, а показатели состояния
+/// (активные сессии, кэш, пул соединений) — при каждом запросе `/metrics`
+struct Metrics {
+    registry: Registry,
+    updates: IntCounterVec,
+    llm_duration: HistogramVec,
+    llm_errors: IntCounterVec,
+    llm_tokens: IntCounterVec,
+    invoices_sent: IntCounter,
+    invoices_paid: IntCounter,
+    active_sessions: IntGauge,
+    cache_hits: IntCounter,
+    cache_misses: IntCounter,
+    cache_entries: IntGauge,
+    db_connections: IntGaugeVec,
+    db_max_connections: IntGauge,
+}
+
+impl Metrics {
+    fn new() -> prometheus::Result<Self> {
+        let metrics = Self {
+            registry: Registry::new_custom(Some("consultant_bot".to_string()), None)?,
+            updates: IntCounterVec::new(
+                Opts::new("updates_total", "Telegram updates handled, by type"),
+                &["kind"],
+            )?,
+            llm_duration: HistogramVec::new(
+                HistogramOpts::new("llm_request_duration_seconds", "Latency of requests to the LLM service")
+                    .buckets(vec![0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 15.0, 30.0, 60.0, 120.0]),
+                &["model", "operation"],
+            )?,
+            llm_errors: IntCounterVec::new(
+                Opts::new("llm_request_errors_total", "Failed requests to the LLM service"),
+                &["model", "operation"],
+            )?,
+            llm_tokens: IntCounterVec::new(
+                Opts::new("llm_tokens_total", "Tokens reported by the LLM service"),
+                &["model", "kind"],
+            )?,
+            invoices_sent: IntCounter::new("invoices_sent_total", "Stars invoices sent to users")?,
+            invoices_paid: IntCounter::new("invoices_paid_total", "Stars invoices paid by users")?,
+            active_sessions: IntGauge::new("active_sessions", "Sessions that are currently active")?,
+            cache_hits: IntCounter::new("user_cache_hits_total", "User state lookups served from the cache")?,
+            cache_misses: IntCounter::new("user_cache_misses_total", "User state lookups that went to the database")?,
+            cache_entries: IntGauge::new("user_cache_entries", "User states currently in the cache")?,
+            db_connections: IntGaugeVec::new(
+                Opts::new("db_pool_connections", "Database pool connections, by state"),
+                &["state"],
+            )?,
+            db_max_connections: IntGauge::new("db_pool_max_connections", "Database pool size limit")?,
+        };
+
+        let registry = &metrics.registry;
+        registry.register(Box::new(metrics.updates.clone()))?;
+        registry.register(Box::new(metrics.llm_duration.clone()))?;
+        registry.register(Box::new(metrics.llm_errors.clone()))?;
+        registry.register(Box::new(metrics.llm_tokens.clone()))?;
+        registry.register(Box::new(metrics.invoices_sent.clone()))?;
+        registry.register(Box::new(metrics.invoices_paid.clone()))?;
+        registry.register(Box::new(metrics.active_sessions.clone()))?;
+        registry.register(Box::new(metrics.cache_hits.clone()))?;
+        registry.register(Box::new(metrics.cache_misses.clone()))?;
+        registry.register(Box::new(metrics.cache_entries.clone()))?;
+        registry.register(Box::new(metrics.db_connections.clone()))?;
+        registry.register(Box::new(metrics.db_max_connections.clone()))?;
+
+        Ok(metrics)
+    }
+}
+
+fn metrics() -> &'static Metrics {
+    // Имена и метки метрик фиксированы, поэтому ошибка здесь — ошибка в коде
+    METRICS.get_or_init(|| Metrics::new().expect("metric definitions are valid"))
+}
+
+pub(crate) fn update_kind(update: &Update) -> &'static str {
+    match &update.kind {
+        UpdateKind::Message(message) if message.successful_payment().is_some() => "successful_payment",
+        UpdateKind::Message(_) => "message",
+        UpdateKind::EditedMessage(_) => "edited_message",
+        UpdateKind::CallbackQuery(_) => "callback_query",
+        UpdateKind::PreCheckoutQuery(_) => "pre_checkout_query",
+        UpdateKind::MyChatMember(_) => "my_chat_member",
+        UpdateKind::Error(_) => "unparsed",
+        _ => "other",
+    }
+}
+
+/// Учитывает входящее обновление Telegram
+pub fn update_received(update: &Update) {
+    metrics().updates.with_label_values(&[update_kind(update)]).inc();
+}
+
+/// Учитывает запрос к сервису LLM (`operation` — "chat" или "embeddings")
+pub fn llm_request(model: &str, operation: &str, elapsed: Duration, success: bool) {
+    let metrics = metrics();
+    metrics.llm_duration.with_label_values(&[model, operation]).observe(elapsed.as_secs_f64());
+    if !success {
+        metrics.llm_errors.with_label_values(&[model, operation]).inc();
+    }
+}
+
+pub fn llm_tokens(model: &str, prompt: u64, completion: u64) {
+    let metrics = metrics();
+    metrics.llm_tokens.with_label_values(&[model, "prompt"]).inc_by(prompt);
+    metrics.llm_tokens.with_label_values(&[model, "completion"]).inc_by(completion);
+}
+
+pub fn invoice_sent() {
+    metrics().invoices_sent.inc();
+}
+
+pub fn invoice_paid() {
+    metrics().invoices_paid.inc();
+}
+
+pub fn user_cache_lookup(hit: bool) {
+    if hit {
+        metrics().cache_hits.inc();
+    } else {
+        metrics().cache_misses.inc();
+    }
+}
+
+/// Обновляет показатели состояния перед выдачей метрик
+async fn refresh_gauges(state: &BotState) {
+    let metrics = metrics();
+
+    match sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM user_states WHERE (current_session->>'is_active')::boolean"
+    )
+    .fetch_one(&state.db.pool)
+    .await
+    {
+        Ok(count) => metrics.active_sessions.set(count),
+        Err(e) => tracing::warn!(error = %e, "could not count active sessions for metrics"),
+    }
+
+    metrics.cache_entries.set(state.cached_users().await as i64);
+
+    let pool = &state.db.pool;
+    let idle = pool.num_idle() as i64;
+    metrics.db_connections.with_label_values(&["idle"]).set(idle);
+    metrics.db_connections.with_label_values(&["in_use"]).set(pool.size() as i64 - idle);
+    metrics.db_max_connections.set(pool.options().get_max_connections() as i64);
+}
+
+async fn render_metrics(State(state): State<BotState>) -> Response {
+    refresh_gauges(&state).await;
+
+    match TextEncoder::new().encode_to_string(&metrics().registry.gather()) {
+        Ok(body) => ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], body).into_response(),
+        Err(e) => {
+            tracing::error!(error = %e, "could not encode metrics");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Запускает HTTP-сервер с `/metrics`, если он включен в настройках
+pub async fn serve(state: BotState) {
+    let config = config();
+    if !config.enabled {
+        return;
+    }
+
+    let app = Router::new()
+        .route("/metrics", get(render_metrics))
+        .with_state(state);
+
+    let listener = match tokio::net::TcpListener::bind(&config.bind).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!(bind = %config.bind, error = %e, "could not bind metrics server");
+            return;
+        }
+    };
+
+    tracing::info!(bind = %config.bind, "metrics server listening");
+
+    if let Err(e) = axum::serve(listener, app).await {
+        tracing::error!(error = %e, "metrics server stopped");
+    }
+}
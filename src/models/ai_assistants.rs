@@ -3,6 +3,36 @@ use sqlx::FromRow;
 use sqlx::Row;
 
 use crate::bot_state::BotState;
+use crate::i18n::Locale;
+use crate::models::ConsultantTranslation;
+
+/// Способ тарификации консультанта
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BillingMode {
+    /// Оплата времени сессии (time_slots)
+    PerMinute,
+    /// Оплата пакета сообщений (message_bundles)
+    PerMessage,
+}
+
+impl BillingMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BillingMode::PerMinute => "per_minute",
+            BillingMode::PerMessage => "per_message",
+        }
+    }
+}
+
+impl From<String> for BillingMode {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "per_message" => BillingMode::PerMessage,
+            _ => BillingMode::PerMinute,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct AIAssistant {
@@ -14,12 +44,22 @@ pub struct AIAssistant {
     pub specialty: String,
     pub greeting: String,
     pub price_per_minute: f64,
+    #[sqlx(try_from = "String")]
+    pub billing_mode: BillingMode,
+    pub price_per_message: f64,
+    #[sqlx(default)]
+    pub rating: Option<f64>, // Средняя оценка пользователей
+    #[sqlx(default)]
+    pub ratings_count: i64,
 }
 
 impl AIAssistant {
     pub async fn get_all_assistants(state: &BotState) -> Vec<Self> {
         match sqlx::query_as::<_, AIAssistant>(
-            "SELECT id, name, prompt, model, description, specialty, greeting, price_per_minute 
+            "SELECT id, name, prompt, model, description, specialty, greeting, price_per_minute,
+                    billing_mode, price_per_message,
+                    (SELECT AVG(r.rating)::DOUBLE PRECISION FROM session_ratings r WHERE r.assistant_id = consultants.id) AS rating,
+                    (SELECT COUNT(*) FROM session_ratings r WHERE r.assistant_id = consultants.id) AS ratings_count
              FROM consultants 
              WHERE is_active = true 
              ORDER BY price_per_minute DESC"
@@ -28,7 +68,7 @@ impl AIAssistant {
         .await {
             Ok(assistants) => assistants,
             Err(e) => {
-                log::error!("Error fetching assistants from database: {}", e);
+                tracing::error!(error = %e, "could not fetch assistants");
                 // Fallback to default assistants if DB fails
                 vec![
                     AIAssistant {
@@ -39,6 +79,10 @@ impl AIAssistant {
                         specialty: "Общение и поддержка в повседневных задачах".to_string(),
                         greeting: "Здравствуйте! Я Анна. Я помогу вам обсудить вопросы и получить полезные советы. Расскажите, что вас интересует?".to_string(),
                         price_per_minute: 0.1,
+                        billing_mode: BillingMode::PerMinute,
+                        price_per_message: 0.05,
+                        rating: None,
+                        ratings_count: 0,
                         prompt: "Ты — Анна, виртуальный помощник, ориентированный на поддержку и советы в повседневной жизни. Твоя цель — помогать пользователю разбирать задачи, давать рекомендации и задавать уточняющие вопросы, чтобы пользователь самостоятельно находил решения.".to_string(),
                     }
                 ]
@@ -48,7 +92,10 @@ impl AIAssistant {
 
     pub async fn find_by_id_with_price(state: &BotState, id: i32) -> Option<Self> {
         match sqlx::query_as::<_, AIAssistant>(
-            "SELECT id, name, prompt, model, description, specialty, greeting, price_per_minute 
+            "SELECT id, name, prompt, model, description, specialty, greeting, price_per_minute,
+                    billing_mode, price_per_message,
+                    (SELECT AVG(r.rating)::DOUBLE PRECISION FROM session_ratings r WHERE r.assistant_id = consultants.id) AS rating,
+                    (SELECT COUNT(*) FROM session_ratings r WHERE r.assistant_id = consultants.id) AS ratings_count
              FROM consultants 
              WHERE id = $1 AND is_active = true"
         )
@@ -57,16 +104,66 @@ impl AIAssistant {
         .await {
             Ok(Some(assistant)) => Some(assistant),
             Ok(None) => {
-                log::warn!("Assistant with id {} not found in database", id);
+                tracing::warn!(consultant_id = id, "assistant not found");
                 None
             }
             Err(e) => {
-                log::error!("Error fetching assistant from database: {}", e);
+                tracing::error!(error = %e, "could not fetch assistant");
                 None
             }
         }
     }
 
+    /// Активные консультанты с профилями на языке пользователя
+    pub async fn get_all_localized(state: &BotState, locale: Locale) -> Vec<Self> {
+        Self::localize_all(state, Self::get_all_assistants(state).await, locale).await
+    }
+
+    /// Консультант с профилем на языке пользователя
+    pub async fn find_localized(state: &BotState, id: i32, locale: Locale) -> Option<Self> {
+        let assistant = Self::find_by_id_with_price(state, id).await?;
+        Self::localize_all(state, vec![assistant], locale).await.pop()
+    }
+
+    /// Подставляет переводы профилей; поля без перевода остаются на языке по умолчанию
+    pub async fn localize_all(state: &BotState, mut assistants: Vec<Self>, locale: Locale) -> Vec<Self> {
+        if locale == Locale::default() || assistants.is_empty() {
+            return assistants;
+        }
+
+        let ids: Vec<i32> = assistants.iter().map(|a| a.id).collect();
+        let translations = match ConsultantTranslation::for_consultants(state, &ids, locale).await {
+            Ok(translations) => translations,
+            Err(e) => {
+                tracing::error!(error = %e, "could not fetch consultant translations");
+                return assistants;
+            }
+        };
+
+        for assistant in &mut assistants {
+            if let Some(translation) = translations.iter().find(|t| t.consultant_id == assistant.id) {
+                assistant.apply_translation(translation);
+            }
+        }
+        assistants
+    }
+
+    fn apply_translation(&mut self, translation: &ConsultantTranslation) {
+        let fields = [
+            (&mut self.name, &translation.name),
+            (&mut self.description, &translation.description),
+            (&mut self.specialty, &translation.specialty),
+            (&mut self.greeting, &translation.greeting),
+            (&mut self.prompt, &translation.prompt),
+        ];
+
+        for (field, value) in fields {
+            if let Some(value) = value.as_ref().filter(|v| !v.trim().is_empty()) {
+                *field = value.clone();
+            }
+        }
+    }
+
     pub async fn get_model_by_id(state: &BotState, id: i32) -> Option<String> {
         match sqlx::query(
             "SELECT model FROM consultants WHERE id = $1 AND is_active = true"
@@ -76,11 +173,11 @@ impl AIAssistant {
         .await {
             Ok(Some(row)) => Some(row.get("model")),
             Ok(None) => {
-                log::warn!("Assistant with id {} not found in database", id);
+                tracing::warn!(consultant_id = id, "assistant not found");
                 None
             }
             Err(e) => {
-                log::error!("Error fetching assistant model from database: {}", e);
+                tracing::error!(consultant_id = id, error = %e, "could not fetch assistant model");
                 None
             }
         }
@@ -88,7 +185,10 @@ impl AIAssistant {
 
     pub async fn find_by_model_with_price(state: &BotState, model: &str) -> Option<Self> {
         match sqlx::query_as::<_, AIAssistant>(
-            "SELECT id, name, prompt, model, description, specialty, greeting, price_per_minute 
+            "SELECT id, name, prompt, model, description, specialty, greeting, price_per_minute,
+                    billing_mode, price_per_message,
+                    (SELECT AVG(r.rating)::DOUBLE PRECISION FROM session_ratings r WHERE r.assistant_id = consultants.id) AS rating,
+                    (SELECT COUNT(*) FROM session_ratings r WHERE r.assistant_id = consultants.id) AS ratings_count
              FROM consultants 
              WHERE model = $1 AND is_active = true
              ORDER BY id ASC
@@ -99,11 +199,11 @@ impl AIAssistant {
         .await {
             Ok(Some(assistant)) => Some(assistant),
             Ok(None) => {
-                log::warn!("Assistant with model {} not found in database", model);
+                tracing::warn!(model = %model, "assistant not found");
                 None
             }
             Err(e) => {
-                log::error!("Error fetching assistant from database: {}", e);
+                tracing::error!(error = %e, "could not fetch assistant");
                 None
             }
         }
@@ -117,6 +217,10 @@ impl AIAssistant {
         Self::find_by_id_with_price(state, id).await
     }
 
+    pub fn is_per_message(&self) -> bool {
+        self.billing_mode == BillingMode::PerMessage
+    }
+
     pub fn calculate_price(&self, duration_minutes: u32) -> (f64, u32) {
         let price_ton = self.price_per_minute * duration_minutes as f64;
         let price_nanoton = (price_ton * 1_000_000_000.0) as u32;
@@ -127,8 +231,9 @@ impl AIAssistant {
     pub async fn update_assistant(state: &BotState, assistant: &AIAssistant) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         sqlx::query(
             r#"
-            INSERT INTO consultants (id, model, name, description, specialty, greeting, prompt, price_per_minute)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            INSERT INTO consultants (id, model, name, description, specialty, greeting, prompt, price_per_minute,
+                                     billing_mode, price_per_message)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             ON CONFLICT (id) DO UPDATE SET
                 model = EXCLUDED.model,
                 name = EXCLUDED.name,
@@ -137,6 +242,8 @@ impl AIAssistant {
                 greeting = EXCLUDED.greeting,
                 prompt = EXCLUDED.prompt,
                 price_per_minute = EXCLUDED.price_per_minute,
+                billing_mode = EXCLUDED.billing_mode,
+                price_per_message = EXCLUDED.price_per_message,
                 updated_at = NOW()
             "#
         )
@@ -148,12 +255,63 @@ impl AIAssistant {
         .bind(&assistant.greeting)
         .bind(&assistant.prompt)
         .bind(assistant.price_per_minute)
+        .bind(assistant.billing_mode.as_str())
+        .bind(assistant.price_per_message)
         .execute(&state.db.pool)
         .await?;
 
         Ok(())
     }
 
+    /// Новый консультант; создается неактивным, чтобы администратор успел заполнить профиль
+    pub async fn create_assistant(state: &BotState, model: &str, name: &str) -> Result<i32, sqlx::Error> {
+        sqlx::query_scalar::<_, i32>(
+            "INSERT INTO consultants (model, name, description, specialty, greeting, prompt, is_active)
+             VALUES ($1, $2, '', '', '', '', false)
+             RETURNING id"
+        )
+        .bind(model)
+        .bind(name)
+        .fetch_one(&state.db.pool)
+        .await
+    }
+
+    /// Все консультанты, включая неактивных, с признаком активности (для администраторов)
+    pub async fn list_for_admin(state: &BotState) -> Result<Vec<(Self, bool)>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, name, prompt, model, description, specialty, greeting, price_per_minute,
+                    billing_mode, price_per_message, is_active,
+                    (SELECT AVG(r.rating)::DOUBLE PRECISION FROM session_ratings r WHERE r.assistant_id = consultants.id) AS rating,
+                    (SELECT COUNT(*) FROM session_ratings r WHERE r.assistant_id = consultants.id) AS ratings_count
+             FROM consultants
+             ORDER BY id ASC"
+        )
+        .fetch_all(&state.db.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| Ok((Self::from_row(row)?, row.try_get("is_active")?)))
+            .collect()
+    }
+
+    /// Консультант по ID независимо от активности (для администраторов)
+    pub async fn find_for_admin(state: &BotState, id: i32) -> Result<Option<(Self, bool)>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT id, name, prompt, model, description, specialty, greeting, price_per_minute,
+                    billing_mode, price_per_message, is_active,
+                    (SELECT AVG(r.rating)::DOUBLE PRECISION FROM session_ratings r WHERE r.assistant_id = consultants.id) AS rating,
+                    (SELECT COUNT(*) FROM session_ratings r WHERE r.assistant_id = consultants.id) AS ratings_count
+             FROM consultants
+             WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(&state.db.pool)
+        .await?;
+
+        row.map(|row| Ok((Self::from_row(&row)?, row.try_get("is_active")?)))
+            .transpose()
+    }
+
     // Метод для деактивации консультанта
     pub async fn deactivate_assistant(state: &BotState, id: i32) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         sqlx::query(
@@ -166,10 +324,25 @@ impl AIAssistant {
         Ok(())
     }
 
+    /// Возвращает консультанта в список выбора
+    pub async fn activate_assistant(state: &BotState, id: i32) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sqlx::query(
+            "UPDATE consultants SET is_active = true, updated_at = NOW() WHERE id = $1"
+        )
+        .bind(id)
+        .execute(&state.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
     // Новый метод для получения всех консультантов по модели
     pub async fn find_all_by_model(state: &BotState, model: &str) -> Vec<Self> {
         match sqlx::query_as::<_, AIAssistant>(
-            "SELECT id, name, prompt, model, description, specialty, greeting, price_per_minute 
+            "SELECT id, name, prompt, model, description, specialty, greeting, price_per_minute,
+                    billing_mode, price_per_message,
+                    (SELECT AVG(r.rating)::DOUBLE PRECISION FROM session_ratings r WHERE r.assistant_id = consultants.id) AS rating,
+                    (SELECT COUNT(*) FROM session_ratings r WHERE r.assistant_id = consultants.id) AS ratings_count
              FROM consultants 
              WHERE model = $1 AND is_active = true
              ORDER BY id ASC"
@@ -179,7 +352,7 @@ impl AIAssistant {
         .await {
             Ok(assistants) => assistants,
             Err(e) => {
-                log::error!("Error fetching assistants by model from database: {}", e);
+                tracing::error!(error = %e, "could not fetch assistants by model");
                 vec![]
             }
         }
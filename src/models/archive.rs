@@ -0,0 +1,106 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use sqlx::types::Json;
+use teloxide::types::ChatId;
+use chrono::{DateTime, Utc};
+
+use crate::bot_state::BotState;
+use crate::crypto;
+use crate::llm::config::ChatMessage;
+use crate::models::UserSession;
+
+/// Сохраненная история завершенной сессии
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SessionArchive {
+    pub id: i32,
+    pub chat_id: i64,
+    pub assistant_id: i32,
+    pub booking_id: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub history: Json<Vec<ChatMessage>>,
+}
+
+/// Сведения о сохраненной сессии без самой переписки
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SessionArchiveInfo {
+    pub id: i32,
+    pub assistant_id: i32,
+    pub booking_id: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub messages_count: i32,
+}
+
+impl SessionArchive {
+    /// Сохраняет историю сессии (повторное сохранение той же сессии обновляет запись)
+    #[tracing::instrument(name = "db.archive_session", skip_all, fields(chat_id = session.chat_id.0))]
+    pub async fn save(state: &BotState, session: &UserSession) -> Result<(), crypto::CryptoError> {
+        if session.history.is_empty() {
+            return Ok(());
+        }
+
+        let mut history = session.history.clone();
+        crypto::encrypt_history(&state.db.pool, session.chat_id, &mut history).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO session_archive (chat_id, assistant_id, booking_id, started_at, ended_at, history)
+            VALUES ($1, $2, $3, $4, NOW(), $5)
+            ON CONFLICT (chat_id, started_at) DO UPDATE SET
+                assistant_id = EXCLUDED.assistant_id,
+                ended_at = EXCLUDED.ended_at,
+                history = EXCLUDED.history
+            "#
+        )
+        .bind(session.chat_id.0)
+        .bind(session.assistant_id)
+        .bind(&session.booking_id)
+        .bind(session.session_start)
+        .bind(Json(&history))
+        .execute(&state.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Все сохраненные сессии пользователя, старые первыми
+    #[tracing::instrument(name = "db.list_archived_sessions", skip_all, fields(chat_id = chat_id.0))]
+    pub async fn list_for_user(state: &BotState, chat_id: ChatId) -> Vec<Self> {
+        let mut archives = match sqlx::query_as::<_, SessionArchive>(
+            "SELECT id, chat_id, assistant_id, booking_id, started_at, ended_at, history
+             FROM session_archive
+             WHERE chat_id = $1
+             ORDER BY started_at"
+        )
+        .bind(chat_id.0)
+        .fetch_all(&state.db.pool)
+        .await {
+            Ok(archives) => archives,
+            Err(e) => {
+                tracing::error!(chat_id = %chat_id, error = %e, "could not fetch session archive");
+                return vec![];
+            }
+        };
+
+        for archive in archives.iter_mut() {
+            if let Err(e) = crypto::decrypt_history(&state.db.pool, chat_id, &mut archive.history).await {
+                tracing::error!(chat_id = %chat_id, archive_id = archive.id, error = %e, "could not decrypt archived session");
+            }
+        }
+        archives
+    }
+
+    /// Сессии пользователя без расшифровки переписки, новые первыми
+    pub async fn list_info_for_user(state: &BotState, chat_id: ChatId) -> Result<Vec<SessionArchiveInfo>, sqlx::Error> {
+        sqlx::query_as::<_, SessionArchiveInfo>(
+            "SELECT id, assistant_id, booking_id, started_at, ended_at, jsonb_array_length(history) AS messages_count
+             FROM session_archive
+             WHERE chat_id = $1
+             ORDER BY started_at DESC"
+        )
+        .bind(chat_id.0)
+        .fetch_all(&state.db.pool)
+        .await
+    }
+}
@@ -15,4 +15,104 @@ pub struct Booking {
     pub created_at: DateTime<Utc>,
     pub payment_invoice_message_id: Option<MessageId>,
     pub expires_at: Option<DateTime<Utc>>,
+    pub message_quota: Option<u32>, // Для консультантов с оплатой за сообщения
+    pub credit_applied: f64, // Часть стоимости, оплаченная с баланса
+    pub telegram_payment_charge_id: Option<String>,
+    pub is_refunded: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    /// Часть с баланса списана при создании брони (у старых броней — при запуске сессии)
+    pub credit_reserved: bool,
+}
+
+impl Booking {
+    /// Оплачена, но сессия по ней еще не начиналась
+    pub fn is_unstarted(&self) -> bool {
+        self.is_paid && self.started_at.is_none() && !self.is_completed && !self.is_refunded
+    }
+
+    /// Ключ сообщения со статусом брони
+    pub fn status_key(&self) -> &'static str {
+        if self.is_paid {
+            if self.is_refunded {
+                "booking.status_refunded"
+            } else if self.is_completed {
+                "booking.status_completed"
+            } else if self.started_at.is_none() {
+                "booking.status_unstarted"
+            } else {
+                "booking.status_active"
+            }
+        } else if self.expires_at.is_some_and(|exp| exp > Utc::now()) {
+            "booking.status_awaiting_payment"
+        } else {
+            "booking.status_expired"
+        }
+    }
+
+    /// Сумма, которую нужно оплатить счетом (за вычетом баланса)
+    pub fn amount_due(&self) -> f64 {
+        (self.total_price - self.credit_applied).max(0.0)
+    }
+
+    /// Сколько вернуть на баланс при возврате брони: только то, что уже списано.
+    /// Старые брони без резерва списывали баланс лишь при запуске сессии
+    pub fn credit_to_return(&self) -> f64 {
+        if self.credit_reserved || self.started_at.is_some() { self.credit_applied } else { 0.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paid_booking(credit_applied: f64) -> Booking {
+        Booking {
+            id: "b1".to_string(),
+            user_id: ChatId(1),
+            assistant_id: 1,
+            duration_minutes: 30,
+            total_price: 3.0,
+            invoice_payload: "p1".to_string(),
+            is_paid: true,
+            is_completed: false,
+            created_at: Utc::now(),
+            payment_invoice_message_id: None,
+            expires_at: None,
+            message_quota: None,
+            credit_applied,
+            telegram_payment_charge_id: Some("charge".to_string()),
+            is_refunded: false,
+            started_at: None,
+            credit_reserved: false,
+        }
+    }
+
+    #[test]
+    fn refund_of_unstarted_booking_does_not_create_balance() {
+        let booking = paid_booking(1.0);
+        assert!(booking.is_unstarted());
+        assert_eq!(booking.credit_to_return(), 0.0);
+    }
+
+    #[test]
+    fn refund_of_started_booking_returns_credit() {
+        let mut booking = paid_booking(1.0);
+        booking.started_at = Some(Utc::now());
+        assert_eq!(booking.credit_to_return(), 1.0);
+    }
+
+    #[test]
+    fn refund_of_reserved_booking_returns_credit_before_start() {
+        let mut booking = paid_booking(1.0);
+        booking.credit_reserved = true;
+        assert_eq!(booking.credit_to_return(), 1.0);
+        booking.started_at = Some(Utc::now());
+        assert_eq!(booking.credit_to_return(), 1.0);
+    }
+
+    #[test]
+    fn amount_due_never_negative() {
+        assert_eq!(paid_booking(5.0).amount_due(), 0.0);
+        assert_eq!(paid_booking(1.0).amount_due(), 2.0);
+    }
 }
@@ -0,0 +1,106 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+use crate::bot_state::BotState;
+use crate::i18n::Locale;
+
+/// Перевод профиля консультанта; незаполненные поля берутся из таблицы consultants
+#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
+pub struct ConsultantTranslation {
+    pub consultant_id: i32,
+    pub locale: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub specialty: Option<String>,
+    pub greeting: Option<String>,
+    pub prompt: Option<String>,
+}
+
+impl ConsultantTranslation {
+    /// Переводы нескольких консультантов на один язык
+    pub async fn for_consultants(
+        state: &BotState,
+        consultant_ids: &[i32],
+        locale: Locale,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, ConsultantTranslation>(
+            "SELECT consultant_id, locale, name, description, specialty, greeting, prompt
+             FROM consultant_translations
+             WHERE consultant_id = ANY($1) AND locale = $2"
+        )
+        .bind(consultant_ids)
+        .bind(locale.code())
+        .fetch_all(&state.db.pool)
+        .await
+    }
+
+    /// Все переводы профиля консультанта
+    pub async fn list_for_consultant(state: &BotState, consultant_id: i32) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, ConsultantTranslation>(
+            "SELECT consultant_id, locale, name, description, specialty, greeting, prompt
+             FROM consultant_translations
+             WHERE consultant_id = $1
+             ORDER BY locale"
+        )
+        .bind(consultant_id)
+        .fetch_all(&state.db.pool)
+        .await
+    }
+
+    /// Перевод профиля на один язык (пустой, если его еще нет)
+    pub async fn find(state: &BotState, consultant_id: i32, locale: Locale) -> Result<Self, sqlx::Error> {
+        let translation = sqlx::query_as::<_, ConsultantTranslation>(
+            "SELECT consultant_id, locale, name, description, specialty, greeting, prompt
+             FROM consultant_translations
+             WHERE consultant_id = $1 AND locale = $2"
+        )
+        .bind(consultant_id)
+        .bind(locale.code())
+        .fetch_optional(&state.db.pool)
+        .await?;
+
+        Ok(translation.unwrap_or_else(|| Self {
+            consultant_id,
+            locale: locale.code().to_string(),
+            ..Self::default()
+        }))
+    }
+
+    pub async fn save(&self, state: &BotState) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO consultant_translations (consultant_id, locale, name, description, specialty, greeting, prompt)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             ON CONFLICT (consultant_id, locale) DO UPDATE SET
+                 name = EXCLUDED.name,
+                 description = EXCLUDED.description,
+                 specialty = EXCLUDED.specialty,
+                 greeting = EXCLUDED.greeting,
+                 prompt = EXCLUDED.prompt,
+                 updated_at = NOW()"
+        )
+        .bind(self.consultant_id)
+        .bind(&self.locale)
+        .bind(&self.name)
+        .bind(&self.description)
+        .bind(&self.specialty)
+        .bind(&self.greeting)
+        .bind(&self.prompt)
+        .execute(&state.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Удаляет перевод; профиль на этом языке снова показывается на языке по умолчанию
+    pub async fn delete(state: &BotState, consultant_id: i32, locale: Locale) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "DELETE FROM consultant_translations WHERE consultant_id = $1 AND locale = $2"
+        )
+        .bind(consultant_id)
+        .bind(locale.code())
+        .execute(&state.db.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
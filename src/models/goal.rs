@@ -0,0 +1,135 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use teloxide::types::ChatId;
+use chrono::{DateTime, Utc};
+
+use crate::bot_state::BotState;
+
+/// Сколько открытых целей подмешиваем в контекст новой сессии
+const PROMPT_GOALS_LIMIT: usize = 10;
+
+/// Цель или задача (если задан parent_id), которую пользователь поставил вместе с консультантом
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Goal {
+    pub id: i32,
+    pub chat_id: i64,
+    pub parent_id: Option<i32>,
+    pub title: String,
+    pub is_done: bool,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl Goal {
+    #[tracing::instrument(name = "db.create_goal", skip_all, fields(chat_id = chat_id.0))]
+    pub async fn create(state: &BotState, chat_id: ChatId, title: &str) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Goal>(
+            "INSERT INTO goals (chat_id, title) VALUES ($1, $2)
+             RETURNING id, chat_id, parent_id, title, is_done, created_at, completed_at"
+        )
+        .bind(chat_id.0)
+        .bind(title)
+        .fetch_one(&state.db.pool)
+        .await
+    }
+
+    /// Добавляет задачу к цели пользователя (None, если цель не найдена)
+    pub async fn create_task(
+        state: &BotState,
+        chat_id: ChatId,
+        goal_id: i32,
+        title: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Goal>(
+            "INSERT INTO goals (chat_id, parent_id, title)
+             SELECT $1, $2, $3
+             WHERE EXISTS (SELECT 1 FROM goals WHERE id = $2 AND chat_id = $1 AND parent_id IS NULL)
+             RETURNING id, chat_id, parent_id, title, is_done, created_at, completed_at"
+        )
+        .bind(chat_id.0)
+        .bind(goal_id)
+        .bind(title)
+        .fetch_optional(&state.db.pool)
+        .await
+    }
+
+    /// Все цели и задачи пользователя: сначала незавершенные, задачи следуют за своей целью
+    #[tracing::instrument(name = "db.list_goals", skip_all, fields(chat_id = chat_id.0))]
+    pub async fn list_for_user(state: &BotState, chat_id: ChatId) -> Vec<Self> {
+        let goals = match sqlx::query_as::<_, Goal>(
+            "SELECT id, chat_id, parent_id, title, is_done, created_at, completed_at
+             FROM goals
+             WHERE chat_id = $1
+             ORDER BY is_done, created_at"
+        )
+        .bind(chat_id.0)
+        .fetch_all(&state.db.pool)
+        .await {
+            Ok(goals) => goals,
+            Err(e) => {
+                tracing::error!(error = %e, "could not fetch goals");
+                return vec![];
+            }
+        };
+
+        let mut ordered = Vec::with_capacity(goals.len());
+        for goal in goals.iter().filter(|g| g.parent_id.is_none()) {
+            ordered.push(goal.clone());
+            ordered.extend(goals.iter().filter(|t| t.parent_id == Some(goal.id)).cloned());
+        }
+        ordered
+    }
+
+    /// Переключает отметку о выполнении; возвращает обновленную запись
+    #[tracing::instrument(name = "db.toggle_goal", skip_all, fields(chat_id = chat_id.0, goal_id = id))]
+    pub async fn toggle(state: &BotState, chat_id: ChatId, id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Goal>(
+            "UPDATE goals SET
+                is_done = NOT is_done,
+                completed_at = CASE WHEN is_done THEN NULL ELSE NOW() END
+             WHERE id = $1 AND chat_id = $2
+             RETURNING id, chat_id, parent_id, title, is_done, created_at, completed_at"
+        )
+        .bind(id)
+        .bind(chat_id.0)
+        .fetch_optional(&state.db.pool)
+        .await
+    }
+
+    /// Отмечает цель выполненной (используется консультантом)
+    #[tracing::instrument(name = "db.complete_goal", skip_all, fields(chat_id = chat_id.0, goal_id = id))]
+    pub async fn complete(state: &BotState, chat_id: ChatId, id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Goal>(
+            "UPDATE goals SET is_done = true, completed_at = COALESCE(completed_at, NOW())
+             WHERE id = $1 AND chat_id = $2
+             RETURNING id, chat_id, parent_id, title, is_done, created_at, completed_at"
+        )
+        .bind(id)
+        .bind(chat_id.0)
+        .fetch_optional(&state.db.pool)
+        .await
+    }
+
+    /// Блок системного промпта с открытыми целями, чтобы консультант мог спросить о прогрессе
+    pub fn prompt_block(goals: &[Goal]) -> Option<String> {
+        let open: Vec<String> = goals
+            .iter()
+            .filter(|g| !g.is_done)
+            .take(PROMPT_GOALS_LIMIT)
+            .map(|g| match g.parent_id {
+                Some(parent_id) => format!("  - задача #{} (к цели #{}): {}", g.id, parent_id, g.title),
+                None => format!("- цель #{}: {}", g.id, g.title),
+            })
+            .collect();
+
+        if open.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            "Открытые цели и задачи пользователя из прошлых сессий. Поинтересуйся прогрессом, \
+            а выполненные отметь инструментом complete_goal:\n{}",
+            open.join("\n")
+        ))
+    }
+}
@@ -0,0 +1,149 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use teloxide::types::ChatId;
+use chrono::{DateTime, Utc};
+
+use crate::bot_state::BotState;
+use crate::crypto;
+
+/// Сколько воспоминаний храним на одного пользователя
+pub const MAX_MEMORIES_PER_USER: i64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MemoryKind {
+    /// Значимый факт о пользователе
+    Fact,
+    /// Краткий итог прошедшей сессии
+    Summary,
+}
+
+impl MemoryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MemoryKind::Fact => "fact",
+            MemoryKind::Summary => "summary",
+        }
+    }
+}
+
+impl From<String> for MemoryKind {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "summary" => MemoryKind::Summary,
+            _ => MemoryKind::Fact,
+        }
+    }
+}
+
+/// Долговременная память о пользователе между сессиями
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct UserMemory {
+    pub id: i32,
+    pub chat_id: i64,
+    #[sqlx(try_from = "String")]
+    pub kind: MemoryKind,
+    pub content: String,
+    pub embedding: Vec<f32>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserMemory {
+    /// Сохраняет воспоминание и удаляет самые старые сверх лимита
+    pub async fn save(
+        state: &BotState,
+        chat_id: ChatId,
+        kind: MemoryKind,
+        content: &str,
+        embedding: &[f32],
+    ) -> Result<(), crypto::CryptoError> {
+        let content = crypto::encrypt_text(&state.db.pool, chat_id, content).await?;
+
+        sqlx::query(
+            "INSERT INTO user_memories (chat_id, kind, content, embedding) VALUES ($1, $2, $3, $4)"
+        )
+        .bind(chat_id.0)
+        .bind(kind.as_str())
+        .bind(&content)
+        .bind(embedding)
+        .execute(&state.db.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM user_memories
+            WHERE chat_id = $1 AND id NOT IN (
+                SELECT id FROM user_memories WHERE chat_id = $1 ORDER BY created_at DESC LIMIT $2
+            )
+            "#
+        )
+        .bind(chat_id.0)
+        .bind(MAX_MEMORIES_PER_USER)
+        .execute(&state.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Все воспоминания пользователя, новые первыми
+    pub async fn list_for_user(state: &BotState, chat_id: ChatId) -> Vec<Self> {
+        let mut memories = match sqlx::query_as::<_, UserMemory>(
+            "SELECT id, chat_id, kind, content, embedding, created_at
+             FROM user_memories
+             WHERE chat_id = $1
+             ORDER BY created_at DESC"
+        )
+        .bind(chat_id.0)
+        .fetch_all(&state.db.pool)
+        .await {
+            Ok(memories) => memories,
+            Err(e) => {
+                tracing::error!(chat_id = %chat_id, error = %e, "could not fetch memories");
+                return vec![];
+            }
+        };
+
+        for memory in memories.iter_mut() {
+            match crypto::decrypt_text(&state.db.pool, chat_id, &memory.content).await {
+                Ok(content) => memory.content = content,
+                Err(e) => tracing::error!(chat_id = %chat_id, memory_id = memory.id, error = %e, "could not decrypt memory"),
+            }
+        }
+        memories
+    }
+
+    pub async fn delete(state: &BotState, chat_id: ChatId, id: i32) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM user_memories WHERE id = $1 AND chat_id = $2")
+            .bind(id)
+            .bind(chat_id.0)
+            .execute(&state.db.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_all(state: &BotState, chat_id: ChatId) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM user_memories WHERE chat_id = $1")
+            .bind(chat_id.0)
+            .execute(&state.db.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Косинусная близость к вектору запроса
+    pub fn similarity(&self, query: &[f32]) -> f32 {
+        if self.embedding.len() != query.len() || query.is_empty() {
+            return 0.0;
+        }
+
+        let dot: f32 = self.embedding.iter().zip(query).map(|(a, b)| a * b).sum();
+        let norm_a = self.embedding.iter().map(|a| a * a).sum::<f32>().sqrt();
+        let norm_b = query.iter().map(|b| b * b).sum::<f32>().sqrt();
+
+        if norm_a == 0.0 || norm_b == 0.0 {
+            0.0
+        } else {
+            dot / (norm_a * norm_b)
+        }
+    }
+}
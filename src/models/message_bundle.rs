@@ -0,0 +1,57 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+use crate::i18n::Locale;
+use crate::tr;
+
+/// Сколько дней действует оплаченный пакет сообщений
+pub const MESSAGE_BUNDLE_VALIDITY_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MessageBundle {
+    pub id: i32,
+    pub messages_count: i32,
+    pub description: String,
+    pub is_active: bool,
+    pub sort_order: i32,
+}
+
+impl MessageBundle {
+    pub async fn get_all_active_bundles(state: &crate::bot_state::BotState) -> Vec<Self> {
+        match sqlx::query_as::<_, MessageBundle>(
+            "SELECT id, messages_count, description, is_active, sort_order
+             FROM message_bundles
+             WHERE is_active = true
+             ORDER BY sort_order ASC"
+        )
+        .fetch_all(&state.db.pool)
+        .await {
+            Ok(bundles) if !bundles.is_empty() => bundles,
+            Ok(_) => vec![Self::default_bundle()],
+            Err(e) => {
+                tracing::error!(error = %e, "could not fetch message bundles");
+                // Fallback to default bundle if DB fails
+                vec![Self::default_bundle()]
+            }
+        }
+    }
+
+    fn default_bundle() -> Self {
+        MessageBundle {
+            id: 1,
+            messages_count: 20,
+            description: "Стандартный пакет".to_string(),
+            is_active: true,
+            sort_order: 1,
+        }
+    }
+
+    pub fn calculate_price(&self, price_per_message: f64) -> f64 {
+        price_per_message * self.messages_count as f64
+    }
+
+    pub fn format_price(&self, price_per_message: f64, lang: Locale) -> String {
+        let total_price = self.calculate_price(price_per_message);
+        tr!(lang, "units.message_bundle", count = self.messages_count, price = (total_price * 100.0) as i32)
+    }
+}
@@ -1,13 +1,29 @@
 pub mod ai_assistants;
+pub mod consultant_translation;
 pub mod booking;
 pub mod session;
 pub mod payment_config;
 pub mod user_state;
 pub mod time_slot;
+pub mod message_bundle;
+pub mod rating;
+pub mod memory;
+pub mod goal;
+pub mod mood;
+pub mod reminder;
+pub mod archive;
 
-pub use ai_assistants::AIAssistant;
+pub use ai_assistants::{AIAssistant, BillingMode};
+pub use consultant_translation::ConsultantTranslation;
 pub use booking::Booking;
 pub use session::UserSession;
 pub use payment_config::PaymentConfig;
-pub use user_state::UserState;
-pub use time_slot::TimeSlot;
\ No newline at end of file
+pub use user_state::{PendingInput, UserState};
+pub use time_slot::TimeSlot;
+pub use message_bundle::MessageBundle;
+pub use rating::SessionRating;
+pub use memory::UserMemory;
+pub use goal::Goal;
+pub use mood::{MoodCheckin, MoodEntry};
+pub use reminder::Reminder;
+pub use archive::{SessionArchive, SessionArchiveInfo};
@@ -0,0 +1,201 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use teloxide::types::ChatId;
+use chrono::{DateTime, FixedOffset, NaiveDate, Timelike, Utc};
+
+use crate::bot_state::BotState;
+
+/// Запись о настроении пользователя по шкале от 1 до 10
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MoodEntry {
+    pub id: i32,
+    pub chat_id: i64,
+    pub score: i16,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl MoodEntry {
+    pub async fn create(
+        state: &BotState,
+        chat_id: ChatId,
+        score: i16,
+        note: Option<&str>,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, MoodEntry>(
+            "INSERT INTO mood_entries (chat_id, score, note) VALUES ($1, $2, $3)
+             RETURNING id, chat_id, score, note, created_at"
+        )
+        .bind(chat_id.0)
+        .bind(score.clamp(1, 10))
+        .bind(note)
+        .fetch_one(&state.db.pool)
+        .await
+    }
+
+    /// Записи пользователя начиная с указанного момента, старые первыми
+    #[tracing::instrument(name = "db.list_mood_entries", skip_all, fields(chat_id = chat_id.0))]
+    pub async fn list_since(state: &BotState, chat_id: ChatId, since: DateTime<Utc>) -> Vec<Self> {
+        match sqlx::query_as::<_, MoodEntry>(
+            "SELECT id, chat_id, score, note, created_at
+             FROM mood_entries
+             WHERE chat_id = $1 AND created_at >= $2
+             ORDER BY created_at"
+        )
+        .bind(chat_id.0)
+        .bind(since)
+        .fetch_all(&state.db.pool)
+        .await {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::error!(error = %e, "could not fetch mood entries");
+                vec![]
+            }
+        }
+    }
+
+    /// Добавляет комментарий к уже сохраненной оценке
+    #[tracing::instrument(name = "db.set_mood_note", skip_all, fields(chat_id = chat_id.0))]
+    pub async fn set_note(state: &BotState, chat_id: ChatId, id: i32, note: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE mood_entries SET note = $1 WHERE id = $2 AND chat_id = $3")
+            .bind(note)
+            .bind(id)
+            .bind(chat_id.0)
+            .execute(&state.db.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Блок системного промпта с настроением пользователя за последние дни
+    pub fn prompt_block(entries: &[MoodEntry]) -> Option<String> {
+        if entries.is_empty() {
+            return None;
+        }
+
+        let lines: Vec<String> = entries
+            .iter()
+            .map(|e| match &e.note {
+                Some(note) => format!("- {}: {}/10 ({})", e.created_at.format("%d.%m"), e.score, note),
+                None => format!("- {}: {}/10", e.created_at.format("%d.%m"), e.score),
+            })
+            .collect();
+
+        Some(format!(
+            "Дневник настроения пользователя за последние дни (1 — очень плохо, 10 — отлично). \
+            Учитывай динамику, но не зачитывай записи дословно:\n{}",
+            lines.join("\n")
+        ))
+    }
+}
+
+/// Настройки ежедневного опроса о настроении
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MoodCheckin {
+    pub chat_id: i64,
+    /// Локальное время опроса в минутах от полуночи
+    pub local_minute: i32,
+    /// Смещение часового пояса пользователя от UTC в минутах
+    pub utc_offset_minutes: i32,
+    pub is_enabled: bool,
+    pub last_sent_on: Option<NaiveDate>,
+}
+
+impl MoodCheckin {
+    pub async fn get(state: &BotState, chat_id: ChatId) -> Option<Self> {
+        match sqlx::query_as::<_, MoodCheckin>(
+            "SELECT chat_id, local_minute, utc_offset_minutes, is_enabled, last_sent_on
+             FROM mood_checkins WHERE chat_id = $1"
+        )
+        .bind(chat_id.0)
+        .fetch_optional(&state.db.pool)
+        .await {
+            Ok(checkin) => checkin,
+            Err(e) => {
+                tracing::error!(error = %e, "could not fetch mood check-in settings");
+                None
+            }
+        }
+    }
+
+    /// Сохраняет и включает опрос
+    pub async fn enable(&self, state: &BotState) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO mood_checkins (chat_id, local_minute, utc_offset_minutes, is_enabled, last_sent_on)
+            VALUES ($1, $2, $3, true, $4)
+            ON CONFLICT (chat_id) DO UPDATE SET
+                local_minute = EXCLUDED.local_minute,
+                utc_offset_minutes = EXCLUDED.utc_offset_minutes,
+                is_enabled = true,
+                last_sent_on = EXCLUDED.last_sent_on,
+                updated_at = NOW()
+            "#
+        )
+        .bind(self.chat_id)
+        .bind(self.local_minute)
+        .bind(self.utc_offset_minutes)
+        .bind(self.last_sent_on)
+        .execute(&state.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn disable(state: &BotState, chat_id: ChatId) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE mood_checkins SET is_enabled = false, updated_at = NOW() WHERE chat_id = $1")
+            .bind(chat_id.0)
+            .execute(&state.db.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "db.mood_checkins", skip_all)]
+    pub async fn all_enabled(state: &BotState) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, MoodCheckin>(
+            "SELECT chat_id, local_minute, utc_offset_minutes, is_enabled, last_sent_on
+             FROM mood_checkins WHERE is_enabled = true"
+        )
+        .fetch_all(&state.db.pool)
+        .await
+    }
+
+    #[tracing::instrument(name = "db.mark_mood_checkin_sent", skip_all, fields(chat_id))]
+    pub async fn mark_sent(state: &BotState, chat_id: i64, local_date: NaiveDate) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE mood_checkins SET last_sent_on = $1 WHERE chat_id = $2")
+            .bind(local_date)
+            .bind(chat_id)
+            .execute(&state.db.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Текущее локальное время пользователя
+    pub fn local_now(&self) -> DateTime<FixedOffset> {
+        let offset = FixedOffset::east_opt(self.utc_offset_minutes * 60)
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+        Utc::now().with_timezone(&offset)
+    }
+
+    /// Пора ли отправить сегодняшний опрос
+    pub fn is_due(&self) -> bool {
+        let now = self.local_now();
+        let minute_of_day = (now.hour() * 60 + now.minute()) as i32;
+        minute_of_day >= self.local_minute && self.last_sent_on != Some(now.date_naive())
+    }
+
+    /// Время опроса в формате ЧЧ:ММ и часовой пояс в формате UTC±Ч
+    pub fn describe(&self) -> String {
+        let sign = if self.utc_offset_minutes < 0 { '-' } else { '+' };
+        let offset_hours = self.utc_offset_minutes.abs() / 60;
+        let offset_minutes = self.utc_offset_minutes.abs() % 60;
+        let offset = if offset_minutes == 0 {
+            format!("UTC{}{}", sign, offset_hours)
+        } else {
+            format!("UTC{}{}:{:02}", sign, offset_hours, offset_minutes)
+        };
+        format!("{:02}:{:02} ({})", self.local_minute / 60, self.local_minute % 60, offset)
+    }
+}
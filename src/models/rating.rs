@@ -0,0 +1,88 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use teloxide::types::ChatId;
+use chrono::{DateTime, Utc};
+
+use crate::bot_state::BotState;
+
+/// Оценка пользователя за завершенную сессию
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SessionRating {
+    pub id: i32,
+    pub chat_id: i64,
+    pub assistant_id: i32,
+    pub booking_id: String,
+    pub rating: i16,
+    pub comment: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SessionRating {
+    /// Сохраняет оценку сессии (повторная оценка перезаписывает предыдущую)
+    pub async fn save_rating(
+        state: &BotState,
+        chat_id: ChatId,
+        assistant_id: i32,
+        booking_id: &str,
+        rating: i16,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO session_ratings (chat_id, assistant_id, booking_id, rating)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (booking_id) DO UPDATE SET
+                rating = EXCLUDED.rating,
+                updated_at = NOW()
+            "#
+        )
+        .bind(chat_id.0)
+        .bind(assistant_id)
+        .bind(booking_id)
+        .bind(rating.clamp(1, 5))
+        .execute(&state.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Добавляет текстовый отзыв к уже поставленной оценке
+    pub async fn save_comment(
+        state: &BotState,
+        chat_id: ChatId,
+        booking_id: &str,
+        comment: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE session_ratings SET comment = $1, updated_at = NOW()
+             WHERE booking_id = $2 AND chat_id = $3"
+        )
+        .bind(comment)
+        .bind(booking_id)
+        .bind(chat_id.0)
+        .execute(&state.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Последние отзывы о консультанте
+    pub async fn recent_for_assistant(state: &BotState, assistant_id: i32, limit: i64) -> Vec<Self> {
+        match sqlx::query_as::<_, SessionRating>(
+            "SELECT id, chat_id, assistant_id, booking_id, rating, comment, created_at
+             FROM session_ratings
+             WHERE assistant_id = $1
+             ORDER BY created_at DESC
+             LIMIT $2"
+        )
+        .bind(assistant_id)
+        .bind(limit)
+        .fetch_all(&state.db.pool)
+        .await {
+            Ok(ratings) => ratings,
+            Err(e) => {
+                tracing::error!(error = %e, "could not fetch ratings");
+                vec![]
+            }
+        }
+    }
+}
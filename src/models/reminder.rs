@@ -0,0 +1,65 @@
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+use teloxide::types::ChatId;
+use chrono::{DateTime, Utc};
+
+use crate::bot_state::BotState;
+
+/// Сколько часов пытаемся доставить напоминание после назначенного времени
+const MAX_DELIVERY_DELAY_HOURS: i32 = 24;
+
+/// Напоминание, которое консультант поставил по просьбе пользователя
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Reminder {
+    pub id: i32,
+    pub chat_id: i64,
+    pub text: String,
+    pub remind_at: DateTime<Utc>,
+    pub is_sent: bool,
+}
+
+impl Reminder {
+    #[tracing::instrument(name = "db.create_reminder", skip_all, fields(chat_id = chat_id.0))]
+    pub async fn create(
+        state: &BotState,
+        chat_id: ChatId,
+        text: &str,
+        remind_at: DateTime<Utc>,
+    ) -> Result<Self, sqlx::Error> {
+        sqlx::query_as::<_, Reminder>(
+            "INSERT INTO reminders (chat_id, text, remind_at) VALUES ($1, $2, $3)
+             RETURNING id, chat_id, text, remind_at, is_sent"
+        )
+        .bind(chat_id.0)
+        .bind(text)
+        .bind(remind_at)
+        .fetch_one(&state.db.pool)
+        .await
+    }
+
+    /// Напоминания, время которых уже наступило. Неотправленные повторяются не дольше суток,
+    /// чтобы недоставленное (например, при заблокированном боте) не пробовать бесконечно
+    #[tracing::instrument(name = "db.due_reminders", skip_all)]
+    pub async fn due(state: &BotState) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, Reminder>(
+            "SELECT id, chat_id, text, remind_at, is_sent
+             FROM reminders
+             WHERE is_sent = false AND remind_at <= NOW()
+               AND remind_at > NOW() - make_interval(hours => $1)
+             ORDER BY remind_at"
+        )
+        .bind(MAX_DELIVERY_DELAY_HOURS)
+        .fetch_all(&state.db.pool)
+        .await
+    }
+
+    #[tracing::instrument(name = "db.mark_reminder_sent", skip_all, fields(reminder_id = id))]
+    pub async fn mark_sent(state: &BotState, id: i32) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE reminders SET is_sent = true WHERE id = $1")
+            .bind(id)
+            .execute(&state.db.pool)
+            .await?;
+
+        Ok(())
+    }
+}
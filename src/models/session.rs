@@ -1,8 +1,10 @@
 use serde::{Serialize, Deserialize};
 use teloxide::types::ChatId;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 
 use crate::llm::config::ChatMessage;
+use crate::models::AIAssistant;
+use crate::models::message_bundle::MESSAGE_BUNDLE_VALIDITY_DAYS;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserSession {
@@ -15,4 +17,291 @@ pub struct UserSession {
     pub history: Vec<ChatMessage>,
     pub is_active: bool,
     pub scheduled_start: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub message_quota: Option<u32>, // Лимит сообщений для оплаты за сообщения
+    #[serde(default)]
+    pub booking_id: Option<String>,
+    #[serde(default)]
+    pub unit_price: Option<f64>, // Цена минуты или сообщения у текущего консультанта
+    #[serde(default)]
+    pub flagged_for_review: bool, // Подсистема безопасности обнаружила признаки риска
+    #[serde(default)]
+    pub crisis_pause_applied: bool, // Бесплатная пауза или бесплатный кризисный ответ дается один раз за сессию
+    #[serde(default)]
+    pub crisis_pause_minutes: i64, // Бесплатные минуты, добавленные к paid_until (не возвращаются на баланс)
+}
+
+/// Остаток сессии после пересчета по цене другого консультанта
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwitchQuote {
+    pub remaining_value: f64,
+    pub units: u32,
+    pub per_message: bool,
+}
+
+impl UserSession {
+    /// Сколько сообщений осталось по пакету (None — сессия оплачена по времени)
+    pub fn remaining_messages(&self) -> Option<u32> {
+        self.message_quota
+            .map(|quota| quota.saturating_sub(self.messages_exchanged))
+    }
+
+    /// Стоимость неиспользованной части сессии
+    pub fn unused_value(&self) -> f64 {
+        if !self.is_active {
+            return 0.0;
+        }
+
+        let remaining_minutes = ((self.paid_until - Utc::now()).num_minutes() - self.crisis_pause_minutes).max(0);
+
+        match (self.message_quota, self.unit_price) {
+            (Some(_), Some(unit_price)) => {
+                unit_price * self.remaining_messages().unwrap_or(0) as f64
+            }
+            (None, Some(unit_price)) => unit_price * remaining_minutes as f64,
+            (Some(0), None) => 0.0,
+            (Some(quota), None) => {
+                let remaining = self.remaining_messages().unwrap_or(0);
+                self.total_price * remaining as f64 / quota as f64
+            }
+            (None, None) => {
+                let total_minutes = (self.paid_until - self.session_start).num_minutes() - self.crisis_pause_minutes;
+                if total_minutes <= 0 {
+                    0.0
+                } else {
+                    self.total_price * remaining_minutes as f64 / total_minutes as f64
+                }
+            }
+        }
+    }
+
+    /// Продлевает сессию по времени на паузу для кризисного ответа — не чаще раза за сессию.
+    /// Возвращает true, если пауза добавлена
+    pub fn apply_crisis_pause(&mut self, minutes: i64) -> bool {
+        if self.crisis_pause_applied || self.message_quota.is_some() || minutes <= 0 {
+            return false;
+        }
+
+        self.paid_until += Duration::minutes(minutes);
+        self.crisis_pause_minutes = minutes;
+        self.crisis_pause_applied = true;
+        true
+    }
+
+    /// Не списывает кризисный ответ с пакета сообщений — тоже не чаще раза за сессию.
+    /// Возвращает true, если ответ бесплатный
+    pub fn apply_crisis_free_message(&mut self) -> bool {
+        if self.crisis_pause_applied || self.message_quota.is_none() {
+            return false;
+        }
+
+        self.crisis_pause_applied = true;
+        true
+    }
+
+    /// Сколько минут или сообщений останется, если продолжить сессию с другим консультантом
+    pub fn quote_switch(&self, assistant: &AIAssistant) -> SwitchQuote {
+        let remaining_value = self.unused_value();
+        let per_message = assistant.is_per_message();
+        let unit_price = if per_message {
+            assistant.price_per_message
+        } else {
+            assistant.price_per_minute
+        };
+
+        // Допуск на погрешность дробных цен: 1.2 / 0.1 дает 11.999…, а не 12
+        let units = if unit_price > 0.0 {
+            (remaining_value / unit_price + 1e-9).floor() as u32
+        } else {
+            0
+        };
+
+        SwitchQuote { remaining_value, units, per_message }
+    }
+
+    /// Переводит оставшееся оплаченное время на другого консультанта
+    pub fn apply_switch(&mut self, assistant: &AIAssistant, quote: SwitchQuote) {
+        let now = Utc::now();
+
+        self.assistant_id = assistant.id;
+        // Пауза не входит в пересчитанный остаток, поэтому в новом paid_until ее уже нет
+        self.crisis_pause_minutes = 0;
+
+        if quote.per_message {
+            self.message_quota = Some(self.messages_exchanged + quote.units);
+            self.paid_until = self.paid_until.max(now + Duration::days(MESSAGE_BUNDLE_VALIDITY_DAYS));
+            self.unit_price = Some(assistant.price_per_message);
+        } else {
+            self.message_quota = None;
+            self.paid_until = now + Duration::minutes(quote.units as i64);
+            self.unit_price = Some(assistant.price_per_minute);
+        }
+    }
+
+    /// Можно ли продолжать общение в рамках сессии
+    pub fn can_chat(&self) -> bool {
+        self.is_active
+            && Utc::now() < self.paid_until
+            && self.remaining_messages() != Some(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BillingMode;
+
+    fn minute_session(paid_minutes: i64, total_price: f64) -> UserSession {
+        let now = Utc::now();
+        UserSession {
+            chat_id: ChatId(1),
+            assistant_id: 1,
+            session_start: now,
+            // Запас в полминуты, чтобы остаток в целых минутах не зависел от скорости теста
+            paid_until: now + Duration::minutes(paid_minutes) + Duration::seconds(30),
+            total_price,
+            messages_exchanged: 0,
+            history: Vec::new(),
+            is_active: true,
+            scheduled_start: None,
+            message_quota: None,
+            booking_id: None,
+            unit_price: None,
+            flagged_for_review: false,
+            crisis_pause_applied: false,
+            crisis_pause_minutes: 0,
+        }
+    }
+
+    fn bundle_session(quota: u32, used: u32, total_price: f64) -> UserSession {
+        let mut session = minute_session(60 * 24, total_price);
+        session.message_quota = Some(quota);
+        session.messages_exchanged = used;
+        session
+    }
+
+    fn assistant(billing_mode: BillingMode, price_per_minute: f64, price_per_message: f64) -> AIAssistant {
+        AIAssistant {
+            id: 2,
+            name: "Second".to_string(),
+            prompt: String::new(),
+            model: "model".to_string(),
+            description: String::new(),
+            specialty: String::new(),
+            greeting: String::new(),
+            price_per_minute,
+            billing_mode,
+            price_per_message,
+            rating: None,
+            ratings_count: 0,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn unused_value_of_minute_session() {
+        // Без цены минуты остаток считается долей от оплаченного
+        assert_close(minute_session(30, 3.0).unused_value(), 3.0);
+
+        let mut session = minute_session(30, 3.0);
+        session.unit_price = Some(0.05);
+        assert_close(session.unused_value(), 1.5);
+
+        session.is_active = false;
+        assert_close(session.unused_value(), 0.0);
+    }
+
+    #[test]
+    fn unused_value_of_message_bundle() {
+        assert_close(bundle_session(10, 4, 2.0).unused_value(), 1.2);
+        assert_close(bundle_session(10, 10, 2.0).unused_value(), 0.0);
+
+        let mut session = bundle_session(10, 4, 2.0);
+        session.unit_price = Some(0.25);
+        assert_close(session.unused_value(), 1.5);
+    }
+
+    #[test]
+    fn quote_switch_converts_remaining_value_to_units() {
+        let session = minute_session(30, 3.0);
+
+        let quote = session.quote_switch(&assistant(BillingMode::PerMinute, 0.2, 0.0));
+        assert_eq!(quote, SwitchQuote { remaining_value: 3.0, units: 15, per_message: false });
+
+        let quote = session.quote_switch(&assistant(BillingMode::PerMessage, 0.0, 0.4));
+        assert_eq!(quote.units, 7);
+        assert!(quote.per_message);
+
+        assert_eq!(session.quote_switch(&assistant(BillingMode::PerMinute, 0.0, 0.0)).units, 0);
+    }
+
+    #[test]
+    fn apply_switch_to_per_minute_consultant() {
+        let mut session = bundle_session(10, 4, 2.0);
+        let target = assistant(BillingMode::PerMinute, 0.1, 0.0);
+        let quote = session.quote_switch(&target);
+        assert_eq!(quote.units, 12);
+
+        session.apply_switch(&target, quote);
+        assert_eq!(session.assistant_id, 2);
+        assert_eq!(session.message_quota, None);
+        assert_eq!(session.unit_price, Some(0.1));
+        assert!((session.paid_until - Utc::now() - Duration::minutes(12)).num_seconds().abs() <= 1);
+        assert!(session.can_chat());
+    }
+
+    #[test]
+    fn apply_switch_to_per_message_consultant() {
+        let mut session = minute_session(30, 3.0);
+        session.messages_exchanged = 5;
+        session.apply_crisis_pause(10);
+        let target = assistant(BillingMode::PerMessage, 0.0, 0.5);
+        let quote = session.quote_switch(&target);
+
+        session.apply_switch(&target, quote);
+        assert_eq!(session.message_quota, Some(5 + 6));
+        assert_eq!(session.remaining_messages(), Some(6));
+        assert_eq!(session.unit_price, Some(0.5));
+        assert_eq!(session.crisis_pause_minutes, 0);
+        assert!(session.paid_until >= Utc::now() + Duration::days(MESSAGE_BUNDLE_VALIDITY_DAYS - 1));
+    }
+
+    #[test]
+    fn crisis_pause_is_applied_once_per_session() {
+        let mut session = minute_session(30, 3.0);
+        let paid_until = session.paid_until;
+
+        assert!(session.apply_crisis_pause(10));
+        assert!(!session.apply_crisis_pause(10));
+        assert_eq!(session.paid_until, paid_until + Duration::minutes(10));
+    }
+
+    #[test]
+    fn crisis_pause_is_not_credited_to_balance() {
+        let mut session = minute_session(30, 3.0);
+        session.unit_price = Some(0.1);
+        let before = session.unused_value();
+
+        session.apply_crisis_pause(10);
+        assert!((session.unused_value() - before).abs() < 1e-9);
+    }
+
+    #[test]
+    fn crisis_pause_skips_message_bundles() {
+        let mut session = minute_session(30, 3.0);
+        session.message_quota = Some(10);
+        assert!(!session.apply_crisis_pause(10));
+    }
+
+    #[test]
+    fn crisis_reply_is_free_once_per_bundle() {
+        let mut session = bundle_session(10, 4, 2.0);
+        assert!(session.apply_crisis_free_message());
+        assert!(!session.apply_crisis_free_message());
+
+        assert!(!minute_session(30, 3.0).apply_crisis_free_message());
+    }
 }
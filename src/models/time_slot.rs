@@ -1,6 +1,9 @@
 use serde::{Serialize, Deserialize};
 use sqlx::FromRow;
 
+use crate::i18n::Locale;
+use crate::tr;
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct TimeSlot {
     pub id: i32,
@@ -22,7 +25,7 @@ impl TimeSlot {
         .await {
             Ok(slots) => slots,
             Err(e) => {
-                log::error!("Error fetching time slots from database: {}", e);
+                tracing::error!(error = %e, "could not fetch time slots");
                 // Fallback to default slots if DB fails
                 vec![
                     TimeSlot {
@@ -37,12 +40,68 @@ impl TimeSlot {
         }
     }
 
+    /// Все слоты, включая отключенные (для администраторов)
+    pub async fn get_all_slots(state: &crate::bot_state::BotState) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as::<_, TimeSlot>(
+            "SELECT id, duration_minutes, description, is_active, sort_order
+             FROM time_slots
+             ORDER BY sort_order ASC, id ASC"
+        )
+        .fetch_all(&state.db.pool)
+        .await
+    }
+
+    pub async fn find_by_id(state: &crate::bot_state::BotState, id: i32) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, TimeSlot>(
+            "SELECT id, duration_minutes, description, is_active, sort_order
+             FROM time_slots
+             WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(&state.db.pool)
+        .await
+    }
+
+    /// Новый слот в конце списка
+    pub async fn create(
+        state: &crate::bot_state::BotState,
+        duration_minutes: i32,
+        description: &str,
+    ) -> Result<i32, sqlx::Error> {
+        sqlx::query_scalar::<_, i32>(
+            "INSERT INTO time_slots (duration_minutes, description, sort_order)
+             VALUES ($1, $2, (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM time_slots))
+             RETURNING id"
+        )
+        .bind(duration_minutes)
+        .bind(description)
+        .fetch_one(&state.db.pool)
+        .await
+    }
+
+    pub async fn save(&self, state: &crate::bot_state::BotState) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE time_slots
+             SET duration_minutes = $2, description = $3, is_active = $4, sort_order = $5, updated_at = NOW()
+             WHERE id = $1"
+        )
+        .bind(self.id)
+        .bind(self.duration_minutes)
+        .bind(&self.description)
+        .bind(self.is_active)
+        .bind(self.sort_order)
+        .execute(&state.db.pool)
+        .await?;
+
+        Ok(())
+    }
+
     pub fn calculate_price(&self, price_per_minute: f64) -> f64 {
         price_per_minute * self.duration_minutes as f64
     }
 
-    pub fn format_price(&self, price_per_minute: f64) -> String {
+    pub fn format_price(&self, price_per_minute: f64, lang: Locale) -> String {
         let total_price = self.calculate_price(price_per_minute);
-        format!("{} мин - {} Stars", self.duration_minutes, (total_price * 100.0) as i32)
+        tr!(lang, "units.time_slot", minutes = self.duration_minutes, price = (total_price * 100.0) as i32)
     }
 }
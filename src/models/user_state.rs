@@ -1,9 +1,10 @@
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 use teloxide::types::ChatId;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 
 use super::UserSession;
+use crate::i18n::Locale;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct UserState {
@@ -12,4 +13,56 @@ pub struct UserState {
     pub conversation_history: HashMap<ChatId, Vec<String>>,
     pub user_temperatures: HashMap<ChatId, f32>,
     pub scheduled_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub balance: f64, // Неиспользованный остаток завершенных сессий
+    #[serde(default)]
+    pub pending_input: Option<PendingInput>,
+    #[serde(default)]
+    pub locale: Option<Locale>, // Язык, выбранный пользователем вручную
+    #[serde(default)]
+    pub language_code: Option<String>, // Язык клиента Telegram
+}
+
+impl UserState {
+    /// Язык интерфейса: выбранный вручную или по настройкам Telegram
+    pub fn locale(&self) -> Locale {
+        self.locale
+            .unwrap_or_else(|| Locale::from_language_code(self.language_code.as_deref()))
+    }
+}
+
+/// Ожидаемый от пользователя свободный текст (не сообщение консультанту)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PendingInput {
+    /// Текстовый отзыв к оценке сессии
+    FeedbackComment { booking_id: String },
+    /// Название новой цели, добавляемой вручную
+    NewGoal,
+    /// Комментарий к записи в дневнике настроения
+    MoodNote {
+        entry_id: i32,
+        #[serde(default)]
+        requested_at: Option<DateTime<Utc>>,
+    },
+    /// Время ежедневного опроса о настроении
+    MoodCheckinTime {
+        #[serde(default)]
+        requested_at: Option<DateTime<Utc>>,
+    },
+}
+
+/// Сколько минут ждем заметку к настроению или время опроса; позже текст уходит консультанту
+pub const MOOD_INPUT_TIMEOUT_MINUTES: i64 = 10;
+
+impl PendingInput {
+    /// Вопрос дневника настроения остался без ответа слишком долго
+    pub fn is_expired(&self) -> bool {
+        match self {
+            PendingInput::MoodNote { requested_at, .. } | PendingInput::MoodCheckinTime { requested_at } => {
+                requested_at.is_none_or(|at| Utc::now() - at > Duration::minutes(MOOD_INPUT_TIMEOUT_MINUTES))
+            }
+            PendingInput::FeedbackComment { .. } | PendingInput::NewGoal => false,
+        }
+    }
 }
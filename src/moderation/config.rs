@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyCategory {
+    MedicalDiagnosis,
+    MedicationDosage,
+    HumanClaim,
+    BannedPhrase,
+}
+
+impl PolicyCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyCategory::MedicalDiagnosis => "medical_diagnosis",
+            PolicyCategory::MedicationDosage => "medication_dosage",
+            PolicyCategory::HumanClaim => "human_claim",
+            PolicyCategory::BannedPhrase => "banned_phrase",
+        }
+    }
+
+    /// Пояснение для модели при перегенерации ответа
+    pub fn instruction(&self) -> &'static str {
+        match self {
+            PolicyCategory::MedicalDiagnosis => "не ставь медицинских и психиатрических диагнозов",
+            PolicyCategory::MedicationDosage => "не называй лекарства, дозировки и схемы приема",
+            PolicyCategory::HumanClaim => "не утверждай, что ты человек, психолог или врач — ты ИИ-консультант",
+            PolicyCategory::BannedPhrase => "не используй запрещенные формулировки",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyRule {
+    pub id: String,
+    pub category: PolicyCategory,
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModerationConfig {
+    pub enabled: bool,
+    /// Сколько раз пробуем перегенерировать ответ, прежде чем заменить его
+    pub max_regenerations: u32,
+    /// Ответ, которым заменяется нарушающий правила текст
+    pub fallback_reply: String,
+    pub rules: Vec<PolicyRule>,
+    /// Фразы, которые не должны встречаться в ответах (без учета регистра)
+    pub banned_phrases: Vec<String>,
+}
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        let rule = |id: &str, category, pattern: &str| PolicyRule {
+            id: id.to_string(),
+            category,
+            pattern: pattern.to_string(),
+        };
+
+        Self {
+            enabled: true,
+            max_regenerations: 1,
+            fallback_reply: "Извините, я не могу ответить на это в таком виде. \
+                Я ИИ-консультант и не ставлю диагнозов и не назначаю лечение — \
+                по медицинским вопросам лучше обратиться к врачу. \
+                Давайте продолжим разговор о том, что вы чувствуете."
+                .to_string(),
+            rules: vec![
+                rule("diagnosis_ru", PolicyCategory::MedicalDiagnosis,
+                    r"(?i)(у вас (клиническая )?(депрессия|биполярн|шизофрени|пограничное расстройство|птср|окр|сдвг|тревожное расстройство)|ставлю (вам )?диагноз|ваш диагноз)"),
+                rule("diagnosis_en", PolicyCategory::MedicalDiagnosis,
+                    r"(?i)(you (have|suffer from) (clinical )?(depression|bipolar|schizophrenia|ptsd|ocd|adhd|an anxiety disorder)|i diagnose you)"),
+                rule("dosage", PolicyCategory::MedicationDosage,
+                    r"(?i)(\d+([.,]\d+)?\s?(мг|mg|мкг|mcg)\b|принимайте .{0,40}(таблет|капсул)|take \d+ (pills|tablets|capsules))"),
+                // Миллилитры — дозировка, только если рядом упомянуто лекарство (а не вода или чай)
+                rule("dosage_ml", PolicyCategory::MedicationDosage,
+                    r"(?i)(\d+([.,]\d+)?\s?(мл|ml)\b.{0,30}(сироп|раствор|капл|препарат|лекарств|настойк|syrup|solution|drops|medication|medicine)|(сироп|раствор|капл|препарат|лекарств|настойк|syrup|solution|drops|medication|medicine).{0,30}\b\d+([.,]\d+)?\s?(мл|ml)\b)"),
+                rule("human_claim_ru", PolicyCategory::HumanClaim,
+                    r"(?i)(я (живой|настоящий|реальный) (человек|психолог|психотерапевт)|я (дипломированный|практикующий|лицензированный) (психолог|психотерапевт|врач))"),
+                rule("human_claim_en", PolicyCategory::HumanClaim,
+                    r"(?i)(i('| a)m (a )?(real|licensed|human|certified) (person|psychologist|therapist|doctor))"),
+            ],
+            banned_phrases: Vec::new(),
+        }
+    }
+}
+
+impl ModerationConfig {
+    pub(crate) fn validate(&self, errors: &mut Vec<String>) {
+        for rule in &self.rules {
+            if rule.id.trim().is_empty() {
+                errors.push(format!("moderation rule {:?} has an empty id", rule.pattern));
+            }
+            if let Err(e) = Regex::new(&rule.pattern) {
+                errors.push(format!("moderation rule {:?} has an invalid pattern: {}", rule.id, e));
+            }
+        }
+        if self.enabled && self.fallback_reply.trim().is_empty() {
+            errors.push("moderation.fallback_reply must not be empty".to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_dosage(text: &str) -> bool {
+        ModerationConfig::default()
+            .rules
+            .iter()
+            .filter(|rule| rule.category == PolicyCategory::MedicationDosage)
+            .any(|rule| Regex::new(&rule.pattern).unwrap().is_match(text))
+    }
+
+    #[test]
+    fn milliliters_of_water_are_not_a_dosage() {
+        assert!(!is_dosage("Выпейте стакан воды, 200 мл, и сделайте пару глубоких вдохов."));
+        assert!(!is_dosage("Try a warm cup of tea, about 250 ml."));
+    }
+
+    #[test]
+    fn medication_amounts_are_a_dosage() {
+        assert!(is_dosage("Примите 500 мг парацетамола"));
+        assert!(is_dosage("Давайте ребенку 5 мл сиропа дважды в день"));
+        assert!(is_dosage("the syrup dose is 10 ml"));
+        assert!(is_dosage("take 2 pills before bed"));
+    }
+}
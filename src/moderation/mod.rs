@@ -0,0 +1,198 @@
+pub mod config;
+
+use regex::{Regex, RegexBuilder};
+use std::sync::OnceLock;
+use teloxide::types::ChatId;
+
+use crate::bot_state::BotState;
+use crate::crypto;
+use crate::llm;
+use crate::llm::config::ChatMessage;
+
+pub use config::{ModerationConfig, PolicyCategory};
+
+/// Что сделали с ответом, нарушившим правила
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModerationAction {
+    Regenerated,
+    Replaced,
+}
+
+impl ModerationAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationAction::Regenerated => "regenerated",
+            ModerationAction::Replaced => "replaced",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PolicyViolation {
+    pub rule_id: String,
+    pub category: PolicyCategory,
+    pub excerpt: String,
+}
+
+/// Итог модерации: текст для отправки и найденные нарушения
+#[derive(Debug, Clone)]
+pub struct ModeratedReply {
+    pub text: String,
+    pub violations: Vec<(PolicyViolation, ModerationAction)>,
+}
+
+struct CompiledRule {
+    id: String,
+    category: PolicyCategory,
+    regex: Regex,
+}
+
+static COMPILED_RULES: OnceLock<Vec<CompiledRule>> = OnceLock::new();
+
+/// Настройки модерации ответов (секция `[moderation]` основных настроек)
+pub fn config() -> &'static ModerationConfig {
+    &crate::settings::config().moderation
+}
+
+fn rules() -> &'static [CompiledRule] {
+    COMPILED_RULES.get_or_init(|| {
+        let config = config();
+
+        let mut compiled: Vec<CompiledRule> = config
+            .rules
+            .iter()
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(regex) => Some(CompiledRule { id: rule.id.clone(), category: rule.category, regex }),
+                Err(e) => {
+                    tracing::error!(rule_id = %rule.id, pattern = %rule.pattern, error = %e, "invalid moderation rule pattern");
+                    None
+                }
+            })
+            .collect();
+
+        // Запрещенные фразы ищем буквально, без учета регистра
+        compiled.extend(config.banned_phrases.iter().filter_map(|phrase| {
+            RegexBuilder::new(&regex::escape(phrase))
+                .case_insensitive(true)
+                .build()
+                .ok()
+                .map(|regex| CompiledRule {
+                    id: format!("banned:{}", phrase),
+                    category: PolicyCategory::BannedPhrase,
+                    regex,
+                })
+        }));
+
+        compiled
+    })
+}
+
+/// Проверяет ответ консультанта на соответствие правилам
+pub fn check_reply(text: &str) -> Option<PolicyViolation> {
+    if !config().enabled {
+        return None;
+    }
+
+    rules().iter().find_map(|rule| {
+        rule.regex.find(text).map(|m| PolicyViolation {
+            rule_id: rule.id.clone(),
+            category: rule.category,
+            excerpt: m.as_str().to_string(),
+        })
+    })
+}
+
+fn correction_message(violation: &PolicyViolation) -> ChatMessage {
+    ChatMessage {
+        role: "system".to_string(),
+        content: Some(format!(
+            "Твой предыдущий вариант ответа нарушил правила сервиса: {}. \
+            Перепиши ответ пользователю, сохранив поддерживающий тон, но без этого нарушения.",
+            violation.category.instruction()
+        )),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }
+}
+
+/// Проверяет ответ и при нарушении перегенерирует его, а если не помогло — заменяет безопасным текстом
+pub async fn moderate_reply(
+    messages: &[ChatMessage],
+    model: &str,
+    temperature: f32,
+    reply: String,
+) -> ModeratedReply {
+    let config = config();
+    let mut violations = Vec::new();
+    let mut current = reply;
+    let mut attempts = 0;
+
+    while let Some(violation) = check_reply(&current) {
+        if attempts >= config.max_regenerations {
+            violations.push((violation, ModerationAction::Replaced));
+            current = config.fallback_reply.clone();
+            break;
+        }
+        attempts += 1;
+
+        let mut retry = messages.to_vec();
+        retry.push(correction_message(&violation));
+        violations.push((violation, ModerationAction::Regenerated));
+
+        match llm::chat(retry, model.to_string(), temperature).await {
+            Ok(response) => match response.content {
+                Some(content) => current = content,
+                None => {
+                    current = config.fallback_reply.clone();
+                    break;
+                }
+            },
+            Err(e) => {
+                tracing::warn!(error = %e, "moderation regeneration failed");
+                current = config.fallback_reply.clone();
+                break;
+            }
+        }
+    }
+
+    ModeratedReply { text: current, violations }
+}
+
+/// Сохраняет нарушение правил для статистики по консультанту
+#[tracing::instrument(name = "db.log_violation", skip_all, fields(chat_id = chat_id.0))]
+pub async fn log_violation(
+    state: &BotState,
+    chat_id: ChatId,
+    assistant_id: i32,
+    violation: &PolicyViolation,
+    action: ModerationAction,
+) -> Result<(), crypto::CryptoError> {
+    let excerpt: String = violation.excerpt.chars().take(500).collect();
+    let excerpt = crypto::encrypt_text(&state.db.pool, chat_id, &excerpt).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO moderation_violations (chat_id, assistant_id, rule_id, category, action, excerpt)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#
+    )
+    .bind(chat_id.0)
+    .bind(assistant_id)
+    .bind(&violation.rule_id)
+    .bind(violation.category.as_str())
+    .bind(action.as_str())
+    .bind(excerpt)
+    .execute(&state.db.pool)
+    .await?;
+
+    tracing::warn!(
+        chat_id = %chat_id,
+        assistant_id,
+        rule_id = %violation.rule_id,
+        action = action.as_str(),
+        "consultant reply violated moderation policy"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use chrono::Utc;
+use serde_json::{json, Value};
+use sqlx::{PgPool, Postgres, Transaction};
+use teloxide::types::ChatId;
+
+use crate::bot_state::BotState;
+use crate::crypto;
+
+/// Таблицы с данными пользователя и запросы для их выгрузки.
+/// Эмбеддинги воспоминаний не выгружаются — это производные данные без смысла для человека.
+const EXPORT_QUERIES: [(&str, &str); 12] = [
+    ("user_state", "SELECT chat_id, current_assistant_id, current_session, conversation_history, user_temperatures, balance, pending_input, keep_history, locale, language_code, created_at, updated_at FROM user_states WHERE chat_id = $1"),
+    ("bookings", "SELECT * FROM bookings WHERE chat_id = $1 ORDER BY created_at"),
+    ("session_transcripts", "SELECT id, assistant_id, booking_id, started_at, ended_at, history FROM session_archive WHERE chat_id = $1 ORDER BY started_at"),
+    ("session_stats", "SELECT assistant_id, booking_id, started_at, ended_at, messages FROM session_stats WHERE chat_id = $1 ORDER BY started_at"),
+    ("ratings", "SELECT assistant_id, booking_id, rating, comment, created_at FROM session_ratings WHERE chat_id = $1 ORDER BY created_at"),
+    ("memories", "SELECT id, kind, content, created_at FROM user_memories WHERE chat_id = $1 ORDER BY created_at"),
+    ("goals", "SELECT id, parent_id, title, is_done, created_at, completed_at FROM goals WHERE chat_id = $1 ORDER BY created_at"),
+    ("mood_entries", "SELECT score, note, created_at FROM mood_entries WHERE chat_id = $1 ORDER BY created_at"),
+    ("mood_checkin", "SELECT local_minute, utc_offset_minutes, is_enabled, last_sent_on FROM mood_checkins WHERE chat_id = $1"),
+    ("reminders", "SELECT text, remind_at, is_sent, created_at FROM reminders WHERE chat_id = $1 ORDER BY remind_at"),
+    ("safety_flags", "SELECT booking_id, assistant_id, category, source, excerpt, created_at FROM safety_flags WHERE chat_id = $1 ORDER BY created_at"),
+    ("moderation_events", "SELECT assistant_id, rule_id, category, action, excerpt, created_at FROM moderation_violations WHERE chat_id = $1 ORDER BY created_at"),
+];
+
+/// Что было сделано при удалении данных пользователя
+#[derive(Debug, Clone, Default)]
+pub struct ErasureReport {
+    pub deleted_rows: u64,
+    pub anonymized_rows: u64,
+    /// Оплаченные брони, сохраненные как платежные документы
+    pub retained_payments: u64,
+}
+
+async fn rows_as_json(pool: &PgPool, query: &str, chat_id: ChatId) -> Result<Value, sqlx::Error> {
+    let sql = format!("SELECT COALESCE(json_agg(t), '[]'::json) FROM ({}) t", query);
+    sqlx::query_scalar::<_, Value>(&sql)
+        .bind(chat_id.0)
+        .fetch_one(pool)
+        .await
+}
+
+/// Собирает все данные, хранящиеся о пользователе, в один JSON-документ
+#[tracing::instrument(name = "db.export_user_data", skip_all, fields(chat_id = chat_id.0))]
+pub async fn export_user_data(state: &BotState, chat_id: ChatId) -> Result<Value, crypto::CryptoError> {
+    let mut data = serde_json::Map::new();
+
+    for (section, query) in EXPORT_QUERIES {
+        data.insert(section.to_string(), rows_as_json(&state.db.pool, query, chat_id).await?);
+    }
+
+    // Пользователь получает свои данные в открытом виде
+    let mut data = Value::Object(data);
+    crypto::decrypt_json(&state.db.pool, chat_id, &mut data).await?;
+
+    Ok(json!({
+        "chat_id": chat_id.0,
+        "generated_at": Utc::now(),
+        "data": data,
+    }))
+}
+
+async fn execute(tx: &mut Transaction<'_, Postgres>, sql: &str, chat_id: ChatId) -> Result<u64, sqlx::Error> {
+    Ok(sqlx::query(sql).bind(chat_id.0).execute(&mut **tx).await?.rows_affected())
+}
+
+/// Удаляет данные пользователя внутри уже открытой транзакции (используется и при очистке по сроку хранения)
+pub async fn erase_in_transaction(
+    tx: &mut Transaction<'_, Postgres>,
+    chat_id: ChatId,
+) -> Result<ErasureReport, sqlx::Error> {
+    let mut report = ErasureReport::default();
+
+    for sql in [
+        "DELETE FROM user_states WHERE chat_id = $1",
+        "DELETE FROM session_archive WHERE chat_id = $1",
+        "DELETE FROM session_stats WHERE chat_id = $1",
+        "DELETE FROM user_memories WHERE chat_id = $1",
+        "DELETE FROM goals WHERE chat_id = $1",
+        "DELETE FROM mood_entries WHERE chat_id = $1",
+        "DELETE FROM mood_checkins WHERE chat_id = $1",
+        "DELETE FROM reminders WHERE chat_id = $1",
+        "DELETE FROM safety_flags WHERE chat_id = $1",
+        "DELETE FROM bookings WHERE chat_id = $1 AND is_paid = false",
+        "DELETE FROM broadcast_recipients WHERE chat_id = $1",
+        // Без ключа пользователя не расшифровать и случайно уцелевшие копии данных
+        "DELETE FROM user_data_keys WHERE chat_id = $1",
+    ] {
+        report.deleted_rows += execute(tx, sql, chat_id).await?;
+    }
+
+    // Оценки остаются в рейтинге консультантов, но без автора и текста отзыва;
+    // статистика модерации — без автора и отрывка ответа
+    for sql in [
+        "UPDATE session_ratings SET chat_id = 0, comment = NULL WHERE chat_id = $1",
+        "UPDATE moderation_violations SET chat_id = 0, excerpt = '' WHERE chat_id = $1",
+    ] {
+        report.anonymized_rows += execute(tx, sql, chat_id).await?;
+    }
+
+    report.retained_payments = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM bookings WHERE chat_id = $1 AND is_paid = true"
+    )
+    .bind(chat_id.0)
+    .fetch_one(&mut **tx)
+    .await? as u64;
+
+    Ok(report)
+}
+
+/// Удаляет данные пользователя. Оплаченные брони остаются как платежные документы,
+/// но отвязываются от содержимого разговоров; оценки и статистика модерации обезличиваются.
+#[tracing::instrument(name = "db.erase_user_data", skip_all, fields(chat_id = chat_id.0))]
+pub async fn erase_user_data(state: &BotState, chat_id: ChatId) -> Result<ErasureReport, sqlx::Error> {
+    let mut tx = state.db.pool.begin().await?;
+    let report = erase_in_transaction(&mut tx, chat_id).await?;
+
+    tx.commit().await?;
+    state.evict_user_state(chat_id).await;
+    crypto::forget_data_key(chat_id);
+
+    tracing::info!(
+        chat_id = %chat_id,
+        deleted_rows = report.deleted_rows,
+        anonymized_rows = report.anonymized_rows,
+        retained_payments = report.retained_payments,
+        "user data erased"
+    );
+
+    Ok(report)
+}
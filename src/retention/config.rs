@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+
+const RETENTION_DRY_RUN_ENV: &str = "RETENTION_DRY_RUN";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// Очистка удаляет данные безвозвратно, поэтому выключена по умолчанию:
+    /// перед включением проверьте отчет `retention-report`
+    pub enabled: bool,
+    /// Только считать, что было бы удалено, ничего не удаляя
+    pub dry_run: bool,
+    /// Как часто запускается очистка
+    pub check_interval_minutes: u64,
+    /// Сколько дней хранится переписка после окончания сессии и извлеченные из нее воспоминания
+    pub transcript_days: i32,
+    /// Срок хранения переписки для тех, кто попросил хранить ее дольше (0 — бессрочно)
+    pub extended_transcript_days: i32,
+    /// Через сколько месяцев без активности удаляются данные пользователя (0 — никогда)
+    pub inactive_months: i32,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dry_run: false,
+            check_interval_minutes: 60,
+            transcript_days: 180,
+            extended_transcript_days: 0,
+            inactive_months: 24,
+        }
+    }
+}
+
+impl RetentionConfig {
+    /// RETENTION_DRY_RUN переключает пробный режим
+    pub(crate) fn apply_env(&mut self) {
+        if let Ok(value) = env::var(RETENTION_DRY_RUN_ENV) {
+            self.dry_run = value == "true" || value == "1";
+        }
+    }
+
+    pub(crate) fn validate(&self, errors: &mut Vec<String>) {
+        if self.check_interval_minutes == 0 {
+            errors.push("retention.check_interval_minutes must be positive".to_string());
+        }
+        if self.transcript_days < 1 {
+            errors.push("retention.transcript_days must be at least 1".to_string());
+        }
+        if self.extended_transcript_days < 0 {
+            errors.push("retention.extended_transcript_days must not be negative".to_string());
+        } else if self.extended_transcript_days > 0 && self.extended_transcript_days < self.transcript_days {
+            errors.push(format!(
+                "retention.extended_transcript_days ({}) is shorter than transcript_days ({})",
+                self.extended_transcript_days, self.transcript_days
+            ));
+        }
+        if self.inactive_months < 0 {
+            errors.push("retention.inactive_months must not be negative".to_string());
+        }
+    }
+}
@@ -0,0 +1,226 @@
+pub mod config;
+
+use teloxide::types::ChatId;
+
+use crate::bot_state::BotState;
+use crate::crypto;
+use crate::privacy;
+pub use config::RetentionConfig;
+
+/// Настройки сроков хранения (секция `[retention]` основных настроек)
+pub fn config() -> &'static RetentionConfig {
+    &crate::settings::config().retention
+}
+
+/// Что удалено (или было бы удалено в пробном режиме) за один проход очистки
+#[derive(Debug, Clone, Default)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    /// Удаленные сессии из архива переписки
+    pub transcripts: u64,
+    /// Очищенные истории завершенных сессий в состоянии пользователя
+    pub session_histories: u64,
+    /// Очищенные отрывки сообщений в отметках безопасности и модерации
+    pub excerpts: u64,
+    /// Удаленные записи настроения, прошедшие напоминания и выполненные цели
+    pub personal_records: u64,
+    /// Удаленные воспоминания консультантов о пользователе
+    pub memories: u64,
+    /// Пользователи, данные которых удалены из-за долгого отсутствия
+    pub inactive_users: u64,
+    /// Строки, удаленные или обезличенные вместе с данными неактивных пользователей
+    pub inactive_rows: u64,
+}
+
+impl RetentionReport {
+    pub fn is_empty(&self) -> bool {
+        self.transcripts == 0
+            && self.session_histories == 0
+            && self.excerpts == 0
+            && self.personal_records == 0
+            && self.memories == 0
+            && self.inactive_users == 0
+    }
+
+    /// Записывает итог прохода очистки в журнал
+    pub fn trace(&self) {
+        tracing::info!(
+            dry_run = self.dry_run,
+            transcripts = self.transcripts,
+            session_histories = self.session_histories,
+            excerpts = self.excerpts,
+            personal_records = self.personal_records,
+            memories = self.memories,
+            inactive_users = self.inactive_users,
+            inactive_rows = self.inactive_rows,
+            "retention pass finished"
+        );
+    }
+}
+
+/// Попросил ли пользователь хранить переписку дольше обычного
+pub async fn keeps_history(state: &BotState, chat_id: ChatId) -> bool {
+    sqlx::query_scalar::<_, bool>("SELECT keep_history FROM user_states WHERE chat_id = $1")
+        .bind(chat_id.0)
+        .fetch_optional(&state.db.pool)
+        .await
+        .ok()
+        .flatten()
+        .unwrap_or(false)
+}
+
+pub async fn set_keep_history(state: &BotState, chat_id: ChatId, keep: bool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO user_states (chat_id, keep_history) VALUES ($1, $2)
+         ON CONFLICT (chat_id) DO UPDATE SET keep_history = EXCLUDED.keep_history, updated_at = NOW()"
+    )
+    .bind(chat_id.0)
+    .bind(keep)
+    .execute(&state.db.pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Условие истечения срока хранения записи пользователя `t` с датой `column`:
+/// для тех, кто попросил хранить дольше, действует увеличенный срок (0 — бессрочно)
+fn expired(column: &str) -> String {
+    format!(
+        "{column} < NOW() - make_interval(days => $1)
+         AND (
+             NOT EXISTS (SELECT 1 FROM user_states u WHERE u.chat_id = t.chat_id AND u.keep_history)
+             OR ($2 > 0 AND {column} < NOW() - make_interval(days => $2))
+         )"
+    )
+}
+
+/// Удаляет данные с истекшим сроком хранения. В пробном режиме все изменения откатываются,
+/// а отчет показывает, что было бы удалено.
+#[tracing::instrument(name = "db.retention_enforce", skip_all, fields(dry_run))]
+pub async fn enforce(state: &BotState, dry_run: bool) -> Result<RetentionReport, sqlx::Error> {
+    let config = config();
+    let mut report = RetentionReport { dry_run, ..Default::default() };
+    let mut tx = state.db.pool.begin().await?;
+
+    // Для тех, кто попросил хранить дольше, действует увеличенный срок (0 — бессрочно)
+    report.transcripts = sqlx::query(
+        r#"
+        DELETE FROM session_archive a
+        WHERE a.ended_at < NOW() - make_interval(days => $1)
+          AND (
+              NOT EXISTS (SELECT 1 FROM user_states u WHERE u.chat_id = a.chat_id AND u.keep_history)
+              OR ($2 > 0 AND a.ended_at < NOW() - make_interval(days => $2))
+          )
+        "#
+    )
+    .bind(config.transcript_days)
+    .bind(config.extended_transcript_days)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    // Последняя сессия хранится и в состоянии пользователя — очищаем ее историю по тем же правилам
+    let cleared_sessions: Vec<i64> = sqlx::query_scalar(
+        r#"
+        UPDATE user_states
+        SET current_session = jsonb_set(current_session, '{history}', '[]'::jsonb)
+        WHERE current_session IS NOT NULL
+          AND jsonb_array_length(current_session->'history') > 0
+          AND (current_session->>'is_active')::boolean = false
+          AND (current_session->>'paid_until')::timestamptz < NOW() - make_interval(days => $1)
+          AND (
+              NOT keep_history
+              OR ($2 > 0 AND (current_session->>'paid_until')::timestamptz < NOW() - make_interval(days => $2))
+          )
+        RETURNING chat_id
+        "#
+    )
+    .bind(config.transcript_days)
+    .bind(config.extended_transcript_days)
+    .fetch_all(&mut *tx)
+    .await?;
+    report.session_histories = cleared_sessions.len() as u64;
+
+    // Отрывки сообщений — та же переписка; сами отметки остаются для статистики
+    for sql in [
+        "UPDATE safety_flags SET excerpt = '' WHERE excerpt <> '' AND created_at < NOW() - make_interval(days => $1)",
+        "UPDATE moderation_violations SET excerpt = '' WHERE excerpt <> '' AND created_at < NOW() - make_interval(days => $1)",
+    ] {
+        report.excerpts += sqlx::query(sql)
+            .bind(config.transcript_days)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+    }
+
+    // Дневник настроения, прошедшие напоминания и выполненные цели (незавершенные подцели не трогаем)
+    for sql in [
+        format!("DELETE FROM mood_entries t WHERE {}", expired("t.created_at")),
+        format!("DELETE FROM reminders t WHERE {}", expired("t.remind_at")),
+        format!(
+            "DELETE FROM goals t WHERE t.is_done
+               AND NOT EXISTS (SELECT 1 FROM goals c WHERE c.parent_id = t.id AND NOT c.is_done)
+               AND {}",
+            expired("COALESCE(t.completed_at, t.created_at)")
+        ),
+    ] {
+        report.personal_records += sqlx::query(&sql)
+            .bind(config.transcript_days)
+            .bind(config.extended_transcript_days)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+    }
+
+    // Воспоминания извлечены из переписки и хранятся не дольше нее
+    report.memories = sqlx::query(&format!("DELETE FROM user_memories t WHERE {}", expired("t.created_at")))
+        .bind(config.transcript_days)
+        .bind(config.extended_transcript_days)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    // Неактивных пользователей с деньгами на балансе или неиспользованной оплатой не трогаем
+    let inactive_users: Vec<i64> = if config.inactive_months > 0 {
+        sqlx::query_scalar(
+            r#"
+            SELECT u.chat_id FROM user_states u
+            WHERE u.updated_at < NOW() - make_interval(months => $1)
+              AND NOT u.keep_history
+              AND u.balance <= 0
+              AND NOT EXISTS (
+                  SELECT 1 FROM bookings b
+                  WHERE b.chat_id = u.chat_id AND b.is_paid AND NOT b.is_completed AND NOT b.is_refunded
+              )
+            "#
+        )
+        .bind(config.inactive_months)
+        .fetch_all(&mut *tx)
+        .await?
+    } else {
+        vec![]
+    };
+
+    for chat_id in &inactive_users {
+        let erased = privacy::erase_in_transaction(&mut tx, ChatId(*chat_id)).await?;
+        report.inactive_rows += erased.deleted_rows + erased.anonymized_rows;
+    }
+    report.inactive_users = inactive_users.len() as u64;
+
+    if dry_run {
+        tx.rollback().await?;
+        return Ok(report);
+    }
+
+    tx.commit().await?;
+
+    // Кэш не должен вернуть в базу удаленные данные
+    for chat_id in cleared_sessions.iter().chain(&inactive_users) {
+        state.evict_user_state(ChatId(*chat_id)).await;
+    }
+    for chat_id in &inactive_users {
+        crypto::forget_data_key(ChatId(*chat_id));
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use regex::Regex;
+use std::collections::HashMap;
+use std::env;
+
+const SAFETY_CLASSIFIER_MODEL_ENV: &str = "SAFETY_CLASSIFIER_MODEL";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafetyCategory {
+    Suicide,
+    SelfHarm,
+    Abuse,
+}
+
+impl SafetyCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SafetyCategory::Suicide => "suicide",
+            SafetyCategory::SelfHarm => "self_harm",
+            SafetyCategory::Abuse => "abuse",
+        }
+    }
+
+    pub fn from_label(label: &str) -> Option<Self> {
+        match label {
+            l if l.starts_with("suicide") => Some(SafetyCategory::Suicide),
+            l if l.starts_with("self_harm") => Some(SafetyCategory::SelfHarm),
+            l if l.starts_with("abuse") => Some(SafetyCategory::Abuse),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafetyRule {
+    pub category: SafetyCategory,
+    pub pattern: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafetyConfig {
+    pub enabled: bool,
+    /// Модель для дополнительной LLM-классификации (None — только правила)
+    pub classifier_model: Option<String>,
+    /// Не списывать оплаченное время, пока показывается кризисный ответ
+    pub suspend_billing: bool,
+    /// На сколько минут продлевается сессия при срабатывании
+    pub pause_minutes: i64,
+    pub default_locale: String,
+    /// Сообщения с контактами кризисной помощи по языкам
+    pub crisis_messages: HashMap<String, String>,
+    pub rules: Vec<SafetyRule>,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        let crisis_messages = HashMap::from([
+            (
+                "ru".to_string(),
+                "💛 Похоже, вам сейчас очень тяжело. Вы не одни, и помощь доступна прямо сейчас.\n\n\
+                • Экстренные службы: 112\n\
+                • Телефон доверия (бесплатно, круглосуточно): 8-800-2000-122\n\
+                • Помощь пострадавшим от домашнего насилия: 8-800-7000-600\n\n\
+                Если вы в опасности — пожалуйста, позвоните по одному из этих номеров или обратитесь к близким. \
+                Я — ИИ и не могу заменить живого специалиста."
+                    .to_string(),
+            ),
+            (
+                "en".to_string(),
+                "💛 It sounds like you are going through something really hard. You are not alone, and help is available right now.\n\n\
+                • Emergency services: 112 (EU) / 911 (US)\n\
+                • US: 988 Suicide & Crisis Lifeline (call or text 988)\n\
+                • UK & Ireland: Samaritans, 116 123\n\n\
+                If you are in danger, please call one of these numbers or reach out to someone you trust. \
+                I am an AI and cannot replace a human professional."
+                    .to_string(),
+            ),
+        ]);
+
+        let rule = |category, pattern: &str| SafetyRule { category, pattern: pattern.to_string() };
+
+        Self {
+            enabled: true,
+            classifier_model: None,
+            suspend_billing: true,
+            pause_minutes: 10,
+            default_locale: "ru".to_string(),
+            crisis_messages,
+            rules: vec![
+                rule(SafetyCategory::Suicide, r"(?i)(суицид|покончить с собой|убить себя|не хочу (больше )?жить|хочу умереть|свести сч[её]ты с жизнью|нет смысла жить|лучше бы меня не было)"),
+                rule(SafetyCategory::Suicide, r"(?i)(suicid|kill myself|want to die|end my life|don'?t want to live)"),
+                rule(SafetyCategory::SelfHarm, r"(?i)(режу себя|порезать себя|порезы на|причиняю себе боль|селфхарм|самоповрежд)"),
+                rule(SafetyCategory::SelfHarm, r"(?i)(self[- ]?harm|cut(ting)? myself|hurt(ing)? myself)"),
+                rule(SafetyCategory::Abuse, r"(?i)((меня|нас) (бь[её]т|избивает|насилует)|домашнее насилие|изнасил)"),
+                rule(SafetyCategory::Abuse, r"(?i)((he|she|they) (hits|beats|abuses) me|being abused|domestic violence|raped)"),
+            ],
+        }
+    }
+}
+
+impl SafetyConfig {
+    /// SAFETY_CLASSIFIER_MODEL задает модель классификатора (пустая строка — только правила)
+    pub(crate) fn apply_env(&mut self) {
+        if let Ok(model) = env::var(SAFETY_CLASSIFIER_MODEL_ENV) {
+            self.classifier_model = Some(model).filter(|m| !m.is_empty());
+        }
+    }
+
+    pub(crate) fn validate(&self, errors: &mut Vec<String>) {
+        for rule in &self.rules {
+            if let Err(e) = Regex::new(&rule.pattern) {
+                errors.push(format!("safety rule {:?} has an invalid pattern: {}", rule.pattern, e));
+            }
+        }
+        if self.pause_minutes < 0 {
+            errors.push("safety.pause_minutes must not be negative".to_string());
+        }
+        if !self.crisis_messages.contains_key(&self.default_locale) {
+            errors.push(format!(
+                "safety.crisis_messages has no message for default_locale {:?}",
+                self.default_locale
+            ));
+        }
+    }
+}
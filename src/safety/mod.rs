@@ -0,0 +1,189 @@
+pub mod config;
+
+use regex::Regex;
+use std::sync::OnceLock;
+use teloxide::types::ChatId;
+
+use crate::bot_state::BotState;
+use crate::crypto;
+use crate::llm;
+use crate::llm::config::ChatMessage;
+
+pub use config::{SafetyConfig, SafetyCategory};
+
+/// Откуда пришел сигнал о риске
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectionSource {
+    Rule,
+    Classifier,
+}
+
+impl DetectionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DetectionSource::Rule => "rule",
+            DetectionSource::Classifier => "classifier",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SafetyFinding {
+    pub category: SafetyCategory,
+    pub source: DetectionSource,
+}
+
+struct CompiledRule {
+    category: SafetyCategory,
+    regex: Regex,
+}
+
+static COMPILED_RULES: OnceLock<Vec<CompiledRule>> = OnceLock::new();
+
+/// Настройки подсистемы безопасности (секция `[safety]` основных настроек)
+pub fn config() -> &'static SafetyConfig {
+    &crate::settings::config().safety
+}
+
+fn rules() -> &'static [CompiledRule] {
+    COMPILED_RULES.get_or_init(|| {
+        config()
+            .rules
+            .iter()
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(regex) => Some(CompiledRule { category: rule.category, regex }),
+                Err(e) => {
+                    tracing::error!(pattern = %rule.pattern, error = %e, "invalid safety rule pattern");
+                    None
+                }
+            })
+            .collect()
+    })
+}
+
+/// Проверяет сообщение пользователя правилами и, если настроено, LLM-классификатором
+pub async fn screen_message(text: &str) -> Option<SafetyFinding> {
+    let config = config();
+    if !config.enabled {
+        return None;
+    }
+
+    if let Some(rule) = rules().iter().find(|rule| rule.regex.is_match(text)) {
+        return Some(SafetyFinding {
+            category: rule.category,
+            source: DetectionSource::Rule,
+        });
+    }
+
+    let model = config.classifier_model.as_ref()?;
+    match classify(text, model).await {
+        Ok(Some(category)) => Some(SafetyFinding {
+            category,
+            source: DetectionSource::Classifier,
+        }),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::warn!(error = %e, "safety classifier failed");
+            None
+        }
+    }
+}
+
+async fn classify(text: &str, model: &str) -> anyhow::Result<Option<SafetyCategory>> {
+    let messages = vec![
+        ChatMessage {
+            role: "system".to_string(),
+            content: Some(
+                "Ты — классификатор безопасности. Определи, есть ли в сообщении пользователя \
+                признаки суицидальных намерений, самоповреждения или насилия над ним. \
+                Ответь ровно одним словом: suicide, self_harm, abuse или none."
+                    .to_string(),
+            ),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        },
+        ChatMessage {
+            role: "user".to_string(),
+            content: Some(text.to_string()),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        },
+    ];
+
+    let response = llm::chat(messages, model.to_string(), 0.0).await?;
+    let answer = response.content.unwrap_or_default().trim().to_lowercase();
+
+    Ok(SafetyCategory::from_label(&answer))
+}
+
+/// Сообщение с контактами кризисной помощи на языке пользователя
+pub fn crisis_message(language_code: Option<&str>) -> &'static str {
+    let config = config();
+    let language = language_code
+        .and_then(|code| code.split(['-', '_']).next())
+        .unwrap_or(&config.default_locale);
+
+    config
+        .crisis_messages
+        .get(language)
+        .or_else(|| config.crisis_messages.get(&config.default_locale))
+        .map(String::as_str)
+        .unwrap_or("If you are in danger, please call your local emergency number.")
+}
+
+/// Инструкция консультанту, если в сообщении обнаружен риск
+pub fn assistant_guidance(category: SafetyCategory) -> ChatMessage {
+    ChatMessage {
+        role: "system".to_string(),
+        content: Some(format!(
+            "Внимание: в последнем сообщении пользователя есть признаки риска ({}). \
+            Отвечай бережно и без осуждения, признай чувства пользователя, не обсуждай способы \
+            причинения вреда, мягко предложи обратиться на горячую линию или в экстренные службы \
+            и спроси, находится ли он сейчас в безопасности.",
+            category.as_str()
+        )),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }
+}
+
+/// Сохраняет отметку о риске для последующей проверки
+#[tracing::instrument(name = "db.flag_for_review", skip_all, fields(chat_id = chat_id.0))]
+pub async fn flag_for_review(
+    state: &BotState,
+    chat_id: ChatId,
+    booking_id: Option<&str>,
+    assistant_id: Option<i32>,
+    finding: &SafetyFinding,
+    text: &str,
+) -> Result<(), crypto::CryptoError> {
+    let excerpt: String = text.chars().take(500).collect();
+    let excerpt = crypto::encrypt_text(&state.db.pool, chat_id, &excerpt).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO safety_flags (chat_id, booking_id, assistant_id, category, source, excerpt)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#
+    )
+    .bind(chat_id.0)
+    .bind(booking_id)
+    .bind(assistant_id)
+    .bind(finding.category.as_str())
+    .bind(finding.source.as_str())
+    .bind(excerpt)
+    .execute(&state.db.pool)
+    .await?;
+
+    tracing::warn!(
+        chat_id = %chat_id,
+        category = finding.category.as_str(),
+        source = finding.source.as_str(),
+        "safety flag raised"
+    );
+
+    Ok(())
+}
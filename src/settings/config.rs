@@ -0,0 +1,392 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+
+use crate::admin::AdminConfig;
+use crate::api::ApiConfig;
+use crate::broadcast::BroadcastConfig;
+use crate::health::HealthConfig;
+use crate::metrics::MetricsConfig;
+use crate::moderation::ModerationConfig;
+use crate::retention::RetentionConfig;
+use crate::safety::SafetyConfig;
+use crate::telemetry::TelemetryConfig;
+
+const BOT_CONFIG_PATH_ENV: &str = "BOT_CONFIG_PATH";
+const POSTGRES_HOST_ENV: &str = "POSTGRES_HOST";
+const POSTGRES_PORT_ENV: &str = "POSTGRES_PORT";
+const POSTGRES_USER_ENV: &str = "POSTGRES_USER";
+const POSTGRES_PASSWORD_ENV: &str = "POSTGRES_PASSWORD";
+const POSTGRES_DB_ENV: &str = "POSTGRES_DB";
+const LLM_SERVICE_HOST_ENV: &str = "LLM_SERVICE_HOST";
+const LLM_EMBEDDING_MODEL_ENV: &str = "LLM_EMBEDDING_MODEL";
+const LLM_TOOLS_ENABLED_ENV: &str = "LLM_TOOLS_ENABLED";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    pub name: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_seconds: u64,
+    pub idle_timeout_seconds: u64,
+    pub max_lifetime_seconds: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: 5432,
+            user: String::new(),
+            password: String::new(),
+            name: String::new(),
+            max_connections: 20,
+            min_connections: 5,
+            acquire_timeout_seconds: 30,
+            idle_timeout_seconds: 300,
+            max_lifetime_seconds: 1800,
+        }
+    }
+}
+
+// Пароль не должен попадать в логи
+impl fmt::Debug for DatabaseConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DatabaseConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("user", &self.user)
+            .field("password", &"***")
+            .field("name", &self.name)
+            .field("max_connections", &self.max_connections)
+            .field("min_connections", &self.min_connections)
+            .field("acquire_timeout_seconds", &self.acquire_timeout_seconds)
+            .field("idle_timeout_seconds", &self.idle_timeout_seconds)
+            .field("max_lifetime_seconds", &self.max_lifetime_seconds)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LlmConfig {
+    /// Адрес сервиса LLM, например "http://localhost:8000"
+    pub service_host: String,
+    /// Модель для эмбеддингов (долговременная память)
+    pub embedding_model: String,
+    /// Сколько раз повторять запрос при временной ошибке
+    pub retries: u32,
+    /// Предельное время одного запроса к сервису
+    pub request_timeout_seconds: u64,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            service_host: String::new(),
+            embedding_model: "Embeddings".to_string(),
+            retries: 1,
+            request_timeout_seconds: 120,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaymentsConfig {
+    /// Валюта счетов; цены пересчитываются в Telegram Stars, поэтому поддерживается только XTR
+    pub currency: String,
+    /// Сколько минут неоплаченный счет держит бронь
+    pub invoice_expiry_minutes: i64,
+}
+
+impl Default for PaymentsConfig {
+    fn default() -> Self {
+        Self {
+            currency: "XTR".to_string(),
+            invoice_expiry_minutes: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeoutsConfig {
+    /// Сколько секунд состояние пользователя живет в кэше
+    pub user_cache_ttl_seconds: u64,
+    pub cache_cleanup_interval_seconds: u64,
+    /// Как часто проверяются истекшие сессии и брони
+    pub session_check_interval_seconds: u64,
+    pub reminders_interval_seconds: u64,
+}
+
+impl Default for TimeoutsConfig {
+    fn default() -> Self {
+        Self {
+            user_cache_ttl_seconds: 300,
+            cache_cleanup_interval_seconds: 600,
+            session_check_interval_seconds: 60,
+            reminders_interval_seconds: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Предельный размер истории переписки пользователя в базе, КБ
+    pub max_history_kb: usize,
+    /// Предельный размер настроек температуры пользователя в базе, КБ
+    pub max_temperatures_kb: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_history_kb: 5120,
+            max_temperatures_kb: 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeaturesConfig {
+    /// Разрешить консультантам вызывать инструменты (напоминания, цели и т.д.)
+    pub llm_tools: bool,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        Self { llm_tools: true }
+    }
+}
+
+/// Все настройки бота: один TOML-файл, в котором у каждой подсистемы своя секция
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub database: DatabaseConfig,
+    pub llm: LlmConfig,
+    pub payments: PaymentsConfig,
+    pub timeouts: TimeoutsConfig,
+    pub limits: LimitsConfig,
+    pub features: FeaturesConfig,
+    pub admin: AdminConfig,
+    pub api: ApiConfig,
+    pub broadcast: BroadcastConfig,
+    pub health: HealthConfig,
+    pub metrics: MetricsConfig,
+    pub moderation: ModerationConfig,
+    pub retention: RetentionConfig,
+    pub safety: SafetyConfig,
+    pub telemetry: TelemetryConfig,
+}
+
+pub(crate) fn parse_env<T: FromStr>(name: &str, value: &str) -> anyhow::Result<T>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .trim()
+        .parse()
+        .map_err(|e| anyhow::anyhow!("invalid {} {:?}: {}", name, value, e))
+}
+
+impl AppConfig {
+    /// Загружает настройки из TOML-файла (BOT_CONFIG_PATH) с переопределением из окружения
+    pub fn load() -> anyhow::Result<Self> {
+        let mut config: Self = match env::var(BOT_CONFIG_PATH_ENV) {
+            Ok(path) => toml::from_str(&fs::read_to_string(path)?)?,
+            Err(_) => Self::default(),
+        };
+
+        if let Ok(value) = env::var(POSTGRES_HOST_ENV) {
+            config.database.host = value;
+        }
+        if let Ok(value) = env::var(POSTGRES_PORT_ENV) {
+            config.database.port = parse_env(POSTGRES_PORT_ENV, &value)?;
+        }
+        if let Ok(value) = env::var(POSTGRES_USER_ENV) {
+            config.database.user = value;
+        }
+        if let Ok(value) = env::var(POSTGRES_PASSWORD_ENV) {
+            config.database.password = value;
+        }
+        if let Ok(value) = env::var(POSTGRES_DB_ENV) {
+            config.database.name = value;
+        }
+        if let Ok(value) = env::var(LLM_SERVICE_HOST_ENV) {
+            config.llm.service_host = value;
+        }
+        if let Ok(value) = env::var(LLM_EMBEDDING_MODEL_ENV) {
+            config.llm.embedding_model = value;
+        }
+        if let Ok(value) = env::var(LLM_TOOLS_ENABLED_ENV) {
+            config.features.llm_tools = value != "false" && value != "0";
+        }
+        config.admin.apply_env()?;
+        config.api.apply_env();
+        config.broadcast.apply_env()?;
+        config.health.apply_env();
+        config.metrics.apply_env();
+        config.retention.apply_env();
+        config.safety.apply_env();
+        config.telemetry.apply_env()?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Проверяет настройки целиком и сообщает обо всех ошибках сразу
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut errors = Vec::new();
+
+        let database = &self.database;
+        for (value, name) in [
+            (&database.host, "database.host (POSTGRES_HOST)"),
+            (&database.user, "database.user (POSTGRES_USER)"),
+            (&database.name, "database.name (POSTGRES_DB)"),
+        ] {
+            if value.trim().is_empty() {
+                errors.push(format!("{} must be set", name));
+            }
+        }
+        if database.port == 0 {
+            errors.push("database.port must not be 0".to_string());
+        }
+        if database.max_connections == 0 {
+            errors.push("database.max_connections must be positive".to_string());
+        }
+        if database.min_connections > database.max_connections {
+            errors.push(format!(
+                "database.min_connections ({}) exceeds max_connections ({})",
+                database.min_connections, database.max_connections
+            ));
+        }
+        if database.acquire_timeout_seconds == 0 {
+            errors.push("database.acquire_timeout_seconds must be positive".to_string());
+        }
+
+        let llm = &self.llm;
+        if llm.service_host.trim().is_empty() {
+            errors.push("llm.service_host (LLM_SERVICE_HOST) must be set".to_string());
+        } else if !llm.service_host.starts_with("http://") && !llm.service_host.starts_with("https://") {
+            errors.push(format!("llm.service_host {:?} must start with http:// or https://", llm.service_host));
+        }
+        if llm.embedding_model.trim().is_empty() {
+            errors.push("llm.embedding_model must not be empty".to_string());
+        }
+        if llm.request_timeout_seconds == 0 {
+            errors.push("llm.request_timeout_seconds must be positive".to_string());
+        }
+
+        if self.payments.currency != "XTR" {
+            errors.push(format!("payments.currency {:?} is not supported, only XTR", self.payments.currency));
+        }
+        if self.payments.invoice_expiry_minutes < 1 {
+            errors.push("payments.invoice_expiry_minutes must be at least 1".to_string());
+        }
+
+        let timeouts = &self.timeouts;
+        for (value, name) in [
+            (timeouts.user_cache_ttl_seconds, "timeouts.user_cache_ttl_seconds"),
+            (timeouts.cache_cleanup_interval_seconds, "timeouts.cache_cleanup_interval_seconds"),
+            (timeouts.session_check_interval_seconds, "timeouts.session_check_interval_seconds"),
+            (timeouts.reminders_interval_seconds, "timeouts.reminders_interval_seconds"),
+        ] {
+            if value == 0 {
+                errors.push(format!("{} must be positive", name));
+            }
+        }
+
+        if self.limits.max_history_kb == 0 {
+            errors.push("limits.max_history_kb must be positive".to_string());
+        }
+        if self.limits.max_temperatures_kb == 0 {
+            errors.push("limits.max_temperatures_kb must be positive".to_string());
+        }
+
+        self.admin.validate(&mut errors);
+        self.api.validate(&mut errors);
+        self.broadcast.validate(&mut errors);
+        self.health.validate(&mut errors);
+        self.metrics.validate(&mut errors);
+        self.moderation.validate(&mut errors);
+        self.retention.validate(&mut errors);
+        self.safety.validate(&mut errors);
+        self.telemetry.validate(&mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            anyhow::bail!("invalid configuration:\n  - {}", errors.join("\n  - "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid() -> AppConfig {
+        let mut config = AppConfig::default();
+        config.database.host = "localhost".to_string();
+        config.database.user = "bot".to_string();
+        config.database.name = "bot".to_string();
+        config.llm.service_host = "http://localhost:8000".to_string();
+        config
+    }
+
+    fn errors(config: &AppConfig) -> String {
+        config.validate().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn defaults_with_required_values_are_valid() {
+        valid().validate().unwrap();
+    }
+
+    #[test]
+    fn missing_required_values_are_reported() {
+        let message = errors(&AppConfig::default());
+        assert!(message.contains("database.host"));
+        assert!(message.contains("llm.service_host"));
+    }
+
+    #[test]
+    fn all_errors_are_reported_together() {
+        let mut config = valid();
+        config.payments.currency = "USD".to_string();
+        config.payments.invoice_expiry_minutes = 0;
+        config.broadcast.messages_per_second = 0;
+        config.metrics.enabled = true;
+        config.metrics.bind = "not an address".to_string();
+        config.moderation.rules[0].pattern = "(".to_string();
+
+        let message = errors(&config);
+        for expected in [
+            "payments.currency",
+            "payments.invoice_expiry_minutes",
+            "broadcast.messages_per_second",
+            "metrics.bind",
+            "invalid pattern",
+        ] {
+            assert!(message.contains(expected), "{} not in {}", expected, message);
+        }
+    }
+
+    #[test]
+    fn service_host_needs_a_scheme() {
+        let mut config = valid();
+        config.llm.service_host = "localhost:8000".to_string();
+        assert!(errors(&config).contains("must start with http://"));
+    }
+}
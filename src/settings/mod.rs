@@ -0,0 +1,19 @@
+pub mod config;
+
+use std::sync::OnceLock;
+
+pub use config::AppConfig;
+
+static APP_CONFIG: OnceLock<AppConfig> = OnceLock::new();
+
+/// Загружает и проверяет настройки при старте, чтобы ошибка конфигурации проявилась сразу.
+/// Вызывается до настройки логирования: от нее зависят и настройки телеметрии
+pub fn init() -> anyhow::Result<&'static AppConfig> {
+    let config = AppConfig::load()?;
+    Ok(APP_CONFIG.get_or_init(|| config))
+}
+
+/// Основные настройки бота (загружаются в [`init`] при старте)
+pub fn config() -> &'static AppConfig {
+    APP_CONFIG.get().expect("settings::init must be called at startup")
+}
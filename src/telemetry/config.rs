@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::EnvFilter;
+use std::env;
+
+const LOG_FORMAT_ENV: &str = "LOG_FORMAT";
+const LOG_FILTER_ENV: &str = "RUST_LOG";
+const OTLP_ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+const SERVICE_NAME_ENV: &str = "OTEL_SERVICE_NAME";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Формат логов: text или json (одна запись — одна строка JSON)
+    pub format: LogFormat,
+    /// Фильтр в синтаксисе RUST_LOG, например "info,sqlx=warn"
+    pub filter: String,
+    /// Писать ли в лог закрытие спанов с их длительностью
+    pub span_durations: bool,
+    /// Адрес OTLP-коллектора (gRPC), например "http://localhost:4317";
+    /// используется, только если бот собран с feature `otlp`
+    pub otlp_endpoint: Option<String>,
+    /// Имя сервиса в трассировках
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::Text,
+            filter: "info".to_string(),
+            span_durations: false,
+            otlp_endpoint: None,
+            service_name: "consultant-bot".to_string(),
+        }
+    }
+}
+
+impl TelemetryConfig {
+    /// Переопределения из окружения (LOG_FORMAT, RUST_LOG, OTEL_*)
+    pub(crate) fn apply_env(&mut self) -> anyhow::Result<()> {
+        if let Ok(value) = env::var(LOG_FORMAT_ENV) {
+            self.format = match value.to_lowercase().as_str() {
+                "json" => LogFormat::Json,
+                "text" => LogFormat::Text,
+                other => anyhow::bail!("unknown {} {}, expected text or json", LOG_FORMAT_ENV, other),
+            };
+        }
+        if let Ok(value) = env::var(LOG_FILTER_ENV) {
+            self.filter = value;
+        }
+        if let Ok(value) = env::var(OTLP_ENDPOINT_ENV) {
+            self.otlp_endpoint = Some(value).filter(|v| !v.trim().is_empty());
+        }
+        if let Ok(value) = env::var(SERVICE_NAME_ENV) {
+            self.service_name = value;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn validate(&self, errors: &mut Vec<String>) {
+        if let Err(e) = EnvFilter::try_new(&self.filter) {
+            errors.push(format!("telemetry.filter {:?} is invalid: {}", self.filter, e));
+        }
+        if let Some(endpoint) = &self.otlp_endpoint
+            && !endpoint.starts_with("http://")
+            && !endpoint.starts_with("https://")
+        {
+            errors.push(format!("telemetry.otlp_endpoint {:?} must start with http:// or https://", endpoint));
+        }
+        if self.service_name.trim().is_empty() {
+            errors.push("telemetry.service_name must not be empty".to_string());
+        }
+    }
+}
@@ -0,0 +1,166 @@
+pub mod config;
+
+use teloxide::dptree::di::DependencyMap;
+use teloxide::dptree::{self, Handler, HandlerDescription, HandlerSignature};
+use teloxide::types::Update;
+use tracing::{field, Instrument};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+use crate::bot_state::BotState;
+pub use config::{LogFormat, TelemetryConfig};
+
+
+/// Настройки логирования и трассировки (секция `[telemetry]` основных настроек)
+pub fn config() -> &'static TelemetryConfig {
+    &crate::settings::config().telemetry
+}
+
+/// Держит экспортер трассировок; при удалении отправляет накопленные спаны
+pub struct TelemetryGuard {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take()
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Error flushing traces: {}", e);
+        }
+    }
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_provider(config: &TelemetryConfig) -> Option<opentelemetry_sdk::trace::SdkTracerProvider> {
+    use opentelemetry_otlp::WithExportConfig;
+
+    let endpoint = config.otlp_endpoint.as_ref()?;
+    let exporter = match opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            eprintln!("Could not create OTLP exporter for {}: {}", endpoint, e);
+            return None;
+        }
+    };
+
+    let resource = opentelemetry_sdk::Resource::builder()
+        .with_service_name(config.service_name.clone())
+        .build();
+
+    Some(
+        opentelemetry_sdk::trace::SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource)
+            .build(),
+    )
+}
+
+/// Настраивает вывод логов (текст или JSON) и, если задан коллектор, экспорт трассировок в OTLP.
+/// Записи `log::` из зависимостей попадают туда же и наследуют текущий спан.
+pub fn init() -> TelemetryGuard {
+    let config = config();
+
+    let filter = EnvFilter::try_new(&config.filter).unwrap_or_else(|e| {
+        eprintln!("Invalid log filter {:?}, using \"info\": {}", config.filter, e);
+        EnvFilter::new("info")
+    });
+    let span_events = if config.span_durations { FmtSpan::CLOSE } else { FmtSpan::NONE };
+
+    let (text, json) = match config.format {
+        LogFormat::Text => (Some(fmt::layer().with_span_events(span_events)), None),
+        LogFormat::Json => (
+            None,
+            Some(
+                fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(true)
+                    .with_span_events(span_events),
+            ),
+        ),
+    };
+
+    let registry = tracing_subscriber::registry().with(filter).with(text).with(json);
+
+    #[cfg(feature = "otlp")]
+    {
+        use opentelemetry::trace::TracerProvider as _;
+
+        let provider = otlp_provider(config);
+        let layer = provider
+            .as_ref()
+            .map(|p| tracing_opentelemetry::layer().with_tracer(p.tracer("consultant-bot")));
+        registry.with(layer).init();
+
+        if let Some(endpoint) = provider.as_ref().and(config.otlp_endpoint.as_ref()) {
+            tracing::info!(endpoint = %endpoint, "exporting traces");
+        }
+        TelemetryGuard { provider }
+    }
+
+    #[cfg(not(feature = "otlp"))]
+    {
+        registry.init();
+
+        if config.otlp_endpoint.is_some() {
+            tracing::warn!("OTEL_EXPORTER_OTLP_ENDPOINT is set, but the bot was built without the `otlp` feature");
+        }
+        TelemetryGuard {}
+    }
+}
+
+/// Начало дерева обработчиков: вся обработка обновления идет внутри спана `update`
+/// с идентификаторами обновления, чата, консультанта и текущей сессии
+pub fn update_span<'a, Output, Descr>() -> Handler<'a, Output, Descr>
+where
+    Output: Send + 'a,
+    Descr: HandlerDescription,
+{
+    dptree::from_fn_with_description(
+        Descr::entry(),
+        |deps: DependencyMap, cont| async move {
+            let Some(update) = deps.try_get::<Update>() else {
+                return cont(deps).await;
+            };
+
+            let span = tracing::info_span!(
+                "update",
+                update_id = update.id.0,
+                kind = crate::metrics::update_kind(&update),
+                chat_id = field::Empty,
+                consultant_id = field::Empty,
+                session_id = field::Empty,
+            );
+
+            if let Some(chat) = update.chat() {
+                span.record("chat_id", chat.id.0);
+
+                if let Some(state) = deps.try_get::<BotState>()
+                    && let Ok(user_state) = state.get_user_state(chat.id).instrument(span.clone()).await
+                {
+                    span.record("consultant_id", user_state.current_assistant_id);
+                    if let Some(booking_id) = user_state
+                        .current_session
+                        .as_ref()
+                        .filter(|s| s.is_active)
+                        .and_then(|s| s.booking_id.as_deref())
+                    {
+                        span.record("session_id", booking_id);
+                    }
+                }
+            }
+
+            cont(deps).instrument(span).await
+        },
+        HandlerSignature::Entry,
+    )
+}